@@ -8,9 +8,10 @@
 extern crate rusty_ci;
 
 use clap::{clap_app, crate_version, AppSettings, Arg, SubCommand};
-use rusty_ci::{unwrap, File};
 use rusty_ci::{Bash, BuildSystem, MailNotifier, Makefile, MasterConfig, Quiet, Worker};
-use rusty_yaml::Yaml;
+use rusty_ci::{Change, Diagnostic, File, Format, MailFile, MasterFile, Runner, State, STATE_PATH};
+use std::convert::TryFrom;
+use std::path::Path;
 use std::process::exit;
 use version_compare::Version;
 
@@ -39,11 +40,44 @@ fn main() {
                   (@arg MASTER_YAML: +takes_value default_value("template.yaml") "The path to write the master YAML file")
                   (@arg MAIL_YAML: +takes_value default_value("mail.yaml") "The path to write the mail list YAML file")
               )
+              (@subcommand check =>
+                  (about: "Check a master YAML file for names that don't exist and other mistakes")
+                  (version: "0.1.0")
+                  (author: "Adam McDaniel <adam.mcdaniel17@gmail.com>")
+                  (@arg MASTER_YAML: +required "The path to the master YAML file")
+              )
+              (@subcommand render =>
+                  (about: "Print a master YAML file with every file it includes merged into it")
+                  (version: "0.1.0")
+                  (author: "Adam McDaniel <adam.mcdaniel17@gmail.com>")
+                  (@arg format: -f --format +takes_value possible_value[yaml toml json] "The format to print in (defaults to the format of the master file)")
+                  (@arg MASTER_YAML: +required "The path to the master YAML file")
+              )
+              (@subcommand explain =>
+                  (about: "Explain which schedulers and builders a change would trigger")
+                  (version: "0.1.0")
+                  (author: "Adam McDaniel <adam.mcdaniel17@gmail.com>")
+                  (@arg branch: -b --branch +takes_value "The branch the change is on (defaults to the checked out branch with --range)")
+                  (@arg files: -f --files +takes_value +use_delimiter "The changed files, separated by commas")
+                  (@arg range: -r --range +takes_value conflicts_with[files] "A range of commits in the local git repository, like main..HEAD")
+                  (@arg MASTER_YAML: +required "The path to the master YAML file")
+              )
+              (@subcommand run =>
+                  (about: "Run a builder's script on this machine without buildbot")
+                  (version: "0.1.0")
+                  (author: "Adam McDaniel <adam.mcdaniel17@gmail.com>")
+                  (@arg here: --here "Run in the current directory instead of a fresh clone of the builder's repo")
+                  (@arg MASTER_YAML: +required "The path to the master YAML file")
+                  (@arg BUILDER: +required "The name of the builder to run")
+              )
               (@subcommand stop =>
-                  (about: "Stop rusty-ci")
+                  (about: "Stop the master and workers of rusty-ci")
                   (version: "0.1.0")
                   (author: "Adam McDaniel <adam.mcdaniel17@gmail.com>")
                   (@arg quiet: -q --quiet "Don't ask user anything")
+                  (@arg master: -m --master "Stop the master")
+                  (@arg worker: -w --worker +takes_value +multiple number_of_values(1) "Stop the worker with this name")
+                  (@arg MASTER_YAML: +required "The path to the YAML file")
               )
   )
   .subcommand(
@@ -98,6 +132,41 @@ fn main() {
         )
         .setting(AppSettings::ArgRequiredElseHelp)
     )
+    .subcommand(
+    SubCommand::with_name("plan")
+        .about("Show how building from YAML file(s) would change the generated files, without writing them")
+        .version("0.1.0")
+        .author("Adam McDaniel <adam.mcdaniel17@gmail.com>")
+        .arg(
+          Arg::with_name("MAIL_YAML")
+            .short("m")
+            .long("mail")
+            .takes_value(true)
+            .help("The path to the YAML file dedicated to SMTP authentication info for sending email notifications")
+        )
+        .arg(
+          Arg::with_name("MASTER_YAML")
+            .required(true)
+            .help("The path to the master YAML file")
+        )
+        .setting(AppSettings::ArgRequiredElseHelp)
+    )
+    .subcommand(
+    SubCommand::with_name("workers")
+        .about("Manage the workers of a rusty-ci project")
+        .version("0.1.0")
+        .author("Adam McDaniel <adam.mcdaniel17@gmail.com>")
+        .subcommand(
+          SubCommand::with_name("rotate-password")
+            .about("Replace the saved password of a worker with a new one")
+            .arg(
+              Arg::with_name("NAME")
+                .required(true)
+                .help("The name of the worker")
+            )
+        )
+        .setting(AppSettings::ArgRequiredElseHelp)
+    )
     .setting(AppSettings::ArgRequiredElseHelp)
     .after_help("To start a project, run the `setup` subcommand.\nBe sure to follow the instructions after each subcommand very carefully!")
     .get_matches();
@@ -107,24 +176,46 @@ fn main() {
         Some(subcommand) => {
             let sub_matches = matches.subcommand_matches(subcommand).unwrap();
             if sub_matches.is_present("bash") {
-                Box::new(Bash::default())
+                Box::new(Bash)
             } else if sub_matches.is_present("make") {
-                Box::new(Makefile::default())
+                Box::new(Makefile)
             } else if sub_matches.is_present("quiet") {
-                Box::new(Quiet::default())
+                Box::new(Quiet)
             } else {
                 // Default is bash
-                Box::new(Bash::default())
+                Box::new(Bash)
             }
         }
         // Default is bash
-        None => Box::new(Bash::default()),
+        None => Box::new(Bash),
     };
 
     match matches.subcommand_name() {
         Some("stop") => {
+            let sub_matches = matches.subcommand_matches("stop").unwrap();
+            let yaml_path = sub_matches.value_of("MASTER_YAML").unwrap();
+            let file = read_master(yaml_path);
+            let names: Vec<&str> = match sub_matches.values_of("worker") {
+                Some(names) => names.collect(),
+                None => vec![],
+            };
+            // With no --master or --worker, everything is stopped
+            let everything = !sub_matches.is_present("master") && names.is_empty();
+
+            let mut workers = read_workers(&file);
+            if !everything {
+                for name in &names {
+                    if !workers.iter().any(|w| &w.get_name() == name) {
+                        error!("There is no worker named \"{}\" in {}", name, yaml_path);
+                        exit(1);
+                    }
+                }
+                workers.retain(|w| names.contains(&w.get_name().as_str()));
+            }
+
             info!("Stopping Rusty-CI...");
-            if let Err(e) = buildsystem.stop() {
+            let master = everything || sub_matches.is_present("master");
+            if let Err(e) = buildsystem.stop(master, &workers) {
                 error!("There was a problem stopping Rusty-CI: {}", e);
                 exit(1);
             };
@@ -134,109 +225,101 @@ fn main() {
             install(buildsystem);
         }
         Some("build") => {
+            let sub_matches = matches.subcommand_matches("build").unwrap();
+            let yaml_path = sub_matches.value_of("MASTER_YAML").unwrap();
+            info!("Building rusty-ci from {}...", &yaml_path);
+            // If the MAIL_YAML argument is passed, read the mail notifier from it
+            let (master_file, mail_file) = read_files(yaml_path, sub_matches.value_of("MAIL_YAML"));
+            build(buildsystem, master_file, mail_file)
+        }
+        Some("rebuild") => {
+            let sub_matches = matches.subcommand_matches("rebuild").unwrap();
+            let yaml_path = sub_matches.value_of("MASTER_YAML").unwrap();
+            info!("Rebuilding rusty-ci from {}...", &yaml_path);
+            // If the MAIL_YAML argument is passed, read the mail notifier from it
+            let (master_file, mail_file) = read_files(yaml_path, sub_matches.value_of("MAIL_YAML"));
+            rebuild(buildsystem, master_file, mail_file)
+        }
+        Some("start") => {
             let yaml_path = matches
-                .subcommand_matches("build")
+                .subcommand_matches("start")
                 .unwrap()
                 .value_of("MASTER_YAML")
                 .unwrap();
-            info!("Building rusty-ci from {}...", &yaml_path);
-            let content = match File::read(yaml_path) {
-                Ok(s) => s,
-                Err(e) => {
-                    error!(
-                        "There was a problem reading {}: {}",
-                        yaml_path,
-                        e.to_string()
-                    );
-                    exit(1);
-                }
-            };
-            let master_yaml = Yaml::from(content);
-
-            // If the MAIL_YAML argument is passed, open the file with that path
-            // and return a yaml object from its contents
-            let mail_yaml = match matches
-                .subcommand_matches("build")
-                .unwrap()
-                .value_of("MAIL_YAML")
-            {
-                Some(mail_yaml) => match File::read(mail_yaml) {
-                    Ok(s) => Some(Yaml::from(s)),
-                    Err(e) => {
-                        error!(
-                            "There was a problem reading {}: {}",
-                            mail_yaml,
-                            e.to_string()
-                        );
-                        exit(1);
-                    }
-                },
-                None => None,
-            };
-            build(buildsystem, master_yaml, mail_yaml)
+            info!("Starting workers and master from {}...", &yaml_path);
+            start(buildsystem, read_master(yaml_path));
         }
-        Some("rebuild") => {
+        Some("check") => {
             let yaml_path = matches
-                .subcommand_matches("rebuild")
+                .subcommand_matches("check")
                 .unwrap()
                 .value_of("MASTER_YAML")
                 .unwrap();
-            info!("Rebuilding rusty-ci from {}...", &yaml_path);
-            let content = match File::read(yaml_path) {
-                Ok(s) => s,
-                Err(e) => {
-                    error!(
-                        "There was a problem reading {}: {}",
-                        yaml_path,
-                        e.to_string()
-                    );
-                    exit(1);
-                }
+            info!("Checking {}...", &yaml_path);
+            check(read_master(yaml_path));
+        }
+        Some("plan") => {
+            let sub_matches = matches.subcommand_matches("plan").unwrap();
+            let (master_file, mail_file) = read_files(
+                sub_matches.value_of("MASTER_YAML").unwrap(),
+                sub_matches.value_of("MAIL_YAML"),
+            );
+            plan(master_file, mail_file);
+        }
+        Some("render") => {
+            let sub_matches = matches.subcommand_matches("render").unwrap();
+            let master_file = read_master(sub_matches.value_of("MASTER_YAML").unwrap());
+            let format = match sub_matches.value_of("format") {
+                Some("toml") => Format::Toml,
+                Some("json") => Format::Json,
+                Some(_) => Format::Yaml,
+                None => master_file
+                    .get_source()
+                    .map(|source| source.get_format())
+                    .unwrap_or(Format::Yaml),
             };
-            let master_yaml = Yaml::from(content);
-
-            // If the MAIL_YAML argument is passed, open the file with that path
-            // and return a yaml object from its contents
-            let mail_yaml = match matches
-                .subcommand_matches("rebuild")
-                .unwrap()
-                .value_of("MAIL_YAML")
-            {
-                Some(mail_yaml) => match File::read(mail_yaml) {
-                    Ok(s) => Some(Yaml::from(s)),
+            render(master_file, format);
+        }
+        Some("explain") => {
+            let sub_matches = matches.subcommand_matches("explain").unwrap();
+            let yaml_path = sub_matches.value_of("MASTER_YAML").unwrap();
+            let branch = sub_matches.value_of("branch").map(String::from);
+            let change = match (sub_matches.value_of("range"), branch) {
+                (Some(range), branch) => match Change::from_git_range(range, branch) {
+                    Ok(change) => change,
                     Err(e) => {
                         error!(
-                            "There was a problem reading {}: {}",
-                            mail_yaml,
-                            e.to_string()
+                            "There was a problem reading the commits in {}: {}",
+                            range, e
                         );
                         exit(1);
                     }
                 },
-                None => None,
-            };
-            rebuild(buildsystem, master_yaml, mail_yaml)
-        }
-        Some("start") => {
-            let yaml_path = matches
-                .subcommand_matches("start")
-                .unwrap()
-                .value_of("MASTER_YAML")
-                .unwrap();
-            info!("Starting workers and master from {}...", &yaml_path);
-            let content = match File::read(yaml_path) {
-                Ok(s) => s,
-                Err(e) => {
-                    error!(
-                        "There was a problem reading {}: {}",
-                        yaml_path,
-                        e.to_string()
-                    );
+                (None, Some(branch)) => match sub_matches.values_of("files") {
+                    Some(files) => Change::new(branch.as_str(), files.collect()),
+                    None => Change::new(branch.as_str(), vec![]),
+                },
+                (None, None) => {
+                    error!("Give the branch of the change with --branch, or a range of commits with --range");
                     exit(1);
                 }
             };
-            let yaml = Yaml::from(content);
-            start(buildsystem, yaml);
+            explain(read_master(yaml_path), change);
+        }
+        Some("run") => {
+            let sub_matches = matches.subcommand_matches("run").unwrap();
+            let yaml_path = sub_matches.value_of("MASTER_YAML").unwrap();
+            run(
+                read_master(yaml_path),
+                sub_matches.value_of("BUILDER").unwrap(),
+                sub_matches.is_present("here"),
+            );
+        }
+        Some("workers") => {
+            let workers_matches = matches.subcommand_matches("workers").unwrap();
+            if let Some(rotate_matches) = workers_matches.subcommand_matches("rotate-password") {
+                rotate_password(rotate_matches.value_of("NAME").unwrap());
+            }
         }
         Some("setup") => {
             let master_path = matches
@@ -262,16 +345,17 @@ fn main() {
 }
 
 /// This function gets the "require" section from the YAML file to verify Rusty-CI version
-fn confirm_version(master_yaml: &Yaml) {
+fn confirm_version(master_file: &MasterFile) {
     info!("Verifying required Rusty-CI version...");
-    if !master_yaml.has_section("requires") {
-        error!("There was a problem checking the required Rusty-CI version: `requires` section was not declared");
-        exit(1);
-    }
-
-    let version_str = unwrap(&master_yaml, "requires");
+    let version_str = match &master_file.requires {
+        Some(version) => version,
+        None => {
+            error!("There was a problem checking the required Rusty-CI version: `requires` section was not declared");
+            exit(1);
+        }
+    };
 
-    let required_version = Version::from(&version_str).unwrap();
+    let required_version = Version::from(version_str).unwrap();
     let crate_version = Version::from(crate_version!()).unwrap();
     if required_version > crate_version {
         error!(
@@ -316,13 +400,16 @@ master:
   # Two minutes is a good poll interval
   poll-interval: 120
 
+  # The port the master listens on for workers (defaults to 9989)
+  # worker-port: 9989
+
 # This section holds data specific to the handler that will look for
 # pull requests / merge requests on your repository
 merge-request-handler:
   # This is basically the website you're using for version control
   # Right now, github and gitlab are the only supported sites
   # If you're using an unsupported version control system, no worries,
-  # rusty-ci just wont run on pull requests.
+  # write `none` here and rusty-ci just wont run on pull requests.
   version-control-system: github
   # The username of the owner of the repository
   owner: adam-mcdaniel
@@ -351,6 +438,19 @@ workers:
     # The worker's files will be installed in this directory.
     # This can also be an absolute path
     working-dir: 'test-worker'
+    # These optional sections control the worker's connection to the master
+    # The number of seconds between pings to keep the connection alive (defaults to 600)
+    # keepalive: 600
+    # The most seconds to wait between attempts to reconnect (defaults to 300)
+    # max-delay: 300
+    # How many times to try to reconnect before giving up (defaults to forever)
+    # max-retries: 10
+    # The umask of the worker's processes, in octal
+    # umask: 022
+    # The number of cpus the worker tells the master it has (defaults to detecting them)
+    # numcpus: 4
+    # Let the master shut down the worker, with either `signal` or `file`
+    # allow-shutdown: signal
 
 
 # This section holds each scheduler.
@@ -399,11 +499,12 @@ builders:
   rusty-ci-test:
     # This is the shell script that the workers will run when this builder is executed
     # You can have as many instructions as youd like
-    # Mind you, you cannot use the |, >, <, >>, <<, etc. operators. Sadly, buildbot
-    # passes each item separated by whitespace as another parameter to function.
+    # Each instruction is run with `/bin/sh -c`, so you can use the |, >, &&, etc. operators.
+    # A `cd` instruction on its own line changes the directory of the following instructions.
+    shell: true
     script:
       - echo Hello world!
-      - echo Im an instruction in a script!
+      - echo Im an instruction in a script! | tee script.log
     # These are the workers to delegate this build job to
     workers:
       - test-worker
@@ -460,38 +561,295 @@ smtp-password: "p@$$w0rd""#,
     Ok(())
 }
 
-/// This method takes a boxed BuildSystem trait object and runs its install routine
-fn start(mut b: Box<dyn BuildSystem>, yaml: Yaml) {
-    confirm_version(&yaml);
+/// Prints every error in a configuration, pointing at where it is
+/// in the file, and exits with error code 1.
+/// The library only ever reports configuration errors,
+/// so this is the one place where a bad configuration stops rusty-ci.
+fn exit_with_errors(errors: Vec<Diagnostic>) -> ! {
+    for e in &errors {
+        eprintln!("{}\n", e);
+    }
+    error!("Found {} problem(s) in the configuration", errors.len());
+    exit(1);
+}
+
+/// This function constructs the master config from the master file,
+/// and adds the mail notifier from the mail file if it was given.
+fn master_config(master_file: MasterFile, mail_file: Option<MailFile>) -> MasterConfig {
+    let mut errors = vec![];
 
-    let mut workers = vec![];
-    let workers_section = match yaml.get_section("workers") {
-        Ok(w) => w,
+    let master = match MasterConfig::try_from(master_file.clone()) {
+        Ok(master) => Some(master),
         Err(e) => {
-            error!("There was a problem reading the yaml file: {}", e);
+            errors.extend(master_file.diagnose(e));
+            None
+        }
+    };
+
+    let mail_notifier = match mail_file.map(|file| (file.clone(), MailNotifier::try_from(file))) {
+        Some((_, Ok(mn))) => Some(mn),
+        Some((file, Err(e))) => {
+            errors.extend(file.diagnose(e));
+            None
+        }
+        None => None,
+    };
+
+    match master {
+        Some(mut master) if errors.is_empty() => {
+            if let Some(mn) = mail_notifier {
+                master.set_mail_notifier(mn);
+            }
+            master
+        }
+        _ => exit_with_errors(errors),
+    }
+}
+
+/// Gives the workers of the master config the passwords saved in the state file,
+/// and saves the passwords of any new workers.
+/// Without this, every build would lock out workers whose `buildbot.tac` isn't rewritten.
+fn with_saved_passwords(mut master: MasterConfig) -> MasterConfig {
+    let mut state = match State::load(STATE_PATH) {
+        Ok(state) => state,
+        Err(e) => {
+            error!(
+                "There was a problem reading the saved worker passwords: {}",
+                e
+            );
             exit(1);
         }
     };
-    for worker in workers_section {
-        workers.push(Worker::from(worker));
+
+    master.sync_passwords(&mut state);
+
+    if let Err(e) = state.save(STATE_PATH) {
+        error!("There was a problem saving the worker passwords: {}", e);
+        exit(1);
+    }
+    master
+}
+
+/// This function replaces the saved password of a worker with a new random one.
+/// The new password is used the next time the project is built.
+fn rotate_password(name: &str) {
+    let mut state = match State::load(STATE_PATH) {
+        Ok(state) => state,
+        Err(e) => {
+            error!(
+                "There was a problem reading the saved worker passwords: {}",
+                e
+            );
+            exit(1);
+        }
+    };
+
+    if state.rotate_password(name).is_none() {
+        error!(
+            "The worker '{}' doesn't have a saved password. Has the project been built yet?",
+            name
+        );
+        exit(1);
+    }
+
+    if let Err(e) = state.save(STATE_PATH) {
+        error!("There was a problem saving the worker passwords: {}", e);
+        exit(1);
+    }
+
+    info!("Rotated the password of the worker '{}'", name);
+    info!("Run the `rebuild` subcommand to give the master and the worker the new password.");
+    info!(
+        "If the worker runs on another machine, copy its new `buildbot.tac` over after rebuilding."
+    );
+}
+
+/// This function verifies that every builder, worker and scheduler
+/// referenced in the master file exists, and reports anything suspicious.
+fn check(master_file: MasterFile) {
+    confirm_version(&master_file);
+
+    // The tokens aren't needed to check the config, so it can be checked without them
+    let master = match master_file.to_config_without_tokens() {
+        Ok(master) => master,
+        Err(e) => exit_with_errors(master_file.diagnose(e)),
+    };
+    let issues = rusty_ci::check(&master);
+
+    for issue in &issues {
+        if issue.is_error() {
+            error!("{}", issue);
+        } else {
+            warn!("{}", issue);
+        }
+    }
+
+    let errors = issues.iter().filter(|i| i.is_error()).count();
+    if errors > 0 {
+        error!(
+            "Found {} error(s) and {} warning(s)",
+            errors,
+            issues.len() - errors
+        );
+        exit(1);
     }
+
+    info!("No errors found, {} warning(s)", issues.len());
+}
+
+/// This method takes a boxed BuildSystem trait object and runs its install routine
+fn start(mut b: Box<dyn BuildSystem>, master_file: MasterFile) {
+    confirm_version(&master_file);
+
+    let workers = read_workers(&master_file);
     match b.start(&workers) {
         Ok(_) => {
-            let master = &yaml.get_section("master").unwrap();
+            let master = master_file.master.unwrap_or_default();
             println!("Successfully started workers and master");
             println!("Run `tail -f master/twistd.log` to see the log output for your CI!");
             println!(
                 "Go to http://{}:{} to view your webgui",
-                unwrap(master, "webserver-ip"),
-                unwrap(master, "webserver-port")
+                master.webserver_ip.unwrap_or_default(),
+                master.webserver_port.unwrap_or_default()
             )
         }
         Err(e) => {
-            println!("There was a problem while starting: {}", e);
+            error!("There was a problem while starting: {}", e);
+            exit(1);
         }
     };
 }
 
+/// Reads a master file written in YAML, TOML or JSON, and exits if it can't be read
+fn read_master(path: &str) -> MasterFile {
+    match MasterFile::load(path) {
+        Ok(file) => file,
+        Err(e) => exit_with_errors(e),
+    }
+}
+
+/// Reads a master file and the mail file if there is one, and exits if either can't be read.
+/// Both are read before exiting, so every reference that can't be resolved is reported at once.
+fn read_files(master_path: &str, mail_path: Option<&str>) -> (MasterFile, Option<MailFile>) {
+    let master_file = MasterFile::load(master_path);
+    let mail_file = mail_path.map(MailFile::load).transpose();
+    match (master_file, mail_file) {
+        (Ok(master_file), Ok(mail_file)) => (master_file, mail_file),
+        (master_file, mail_file) => {
+            let mut errors = master_file.err().unwrap_or_default();
+            errors.extend(mail_file.err().unwrap_or_default());
+            exit_with_errors(errors)
+        }
+    }
+}
+
+/// Reads the workers of a master file, and exits if any worker is invalid
+fn read_workers(master_file: &MasterFile) -> Vec<Worker> {
+    match master_file.to_workers() {
+        Ok(workers) => workers,
+        Err(e) => exit_with_errors(master_file.diagnose(e)),
+    }
+}
+
+/// Prints the diff between the files a build would write and the files on disk.
+/// The saved worker passwords are used, but nothing is saved.
+fn plan(master_file: MasterFile, mail_file: Option<MailFile>) {
+    confirm_version(&master_file);
+
+    let mut master = master_config(master_file, mail_file);
+    // The state isn't saved, so a worker without a saved password gets a random one here
+    // that the next build won't use. That's fine, since the plan masks every password.
+    match State::load(STATE_PATH) {
+        Ok(mut state) => master.sync_passwords(&mut state),
+        Err(e) => {
+            error!(
+                "There was a problem reading the saved worker passwords: {}",
+                e
+            );
+            exit(1);
+        }
+    }
+
+    match rusty_ci::plan(&master, ".") {
+        Ok(plan) => println!("{}", plan),
+        Err(e) => {
+            error!("There was a problem comparing the generated files: {}", e);
+            exit(1);
+        }
+    }
+}
+
+/// Prints the master file with its includes merged in, so it can be read in one piece
+fn render(master_file: MasterFile, format: Format) {
+    match master_file.render(format) {
+        Ok(text) => println!("{}", text.trim_end()),
+        Err(e) => {
+            error!("There was a problem writing out the configuration: {}", e);
+            exit(1);
+        }
+    }
+}
+
+/// Prints which schedulers and builders in the master file a change would trigger
+fn explain(master_file: MasterFile, change: Change) {
+    let schedulers = match master_file.to_expanded_schedulers() {
+        Ok(schedulers) => schedulers,
+        Err(e) => exit_with_errors(master_file.diagnose(e)),
+    };
+
+    println!("{}", rusty_ci::explain(&schedulers, &change));
+}
+
+/// Runs a builder from the master file on this machine, and exits with an error if it fails
+fn run(master_file: MasterFile, name: &str, here: bool) {
+    // A builder with a matrix is run by the name of one of its combinations
+    let sections = master_file.builders.clone().unwrap_or_default();
+    let found = sections.iter().find(|(section_name, section)| {
+        *section_name == name
+            || (section.matrix.is_some() && name.starts_with(&format!("{}-", section_name)))
+    });
+    let builders = match found {
+        Some((section_name, section)) => match section.to_builders(section_name) {
+            Ok(builders) => builders,
+            Err(e) => exit_with_errors(master_file.diagnose(e)),
+        },
+        None => vec![],
+    };
+    let names: Vec<String> = builders.iter().map(|b| b.get_name()).collect();
+    let builder = match builders.into_iter().find(|b| b.get_name() == name) {
+        Some(builder) => builder,
+        None if found.is_some_and(|(section_name, _)| section_name == name) => {
+            error!(
+                "The builder \"{}\" has a matrix, so run one of {}",
+                name,
+                names.join(", ")
+            );
+            exit(1);
+        }
+        None => {
+            error!("There is no builder named \"{}\"", name);
+            exit(1);
+        }
+    };
+
+    let runner = if here {
+        Runner::in_checkout(".")
+    } else {
+        let dir = Path::new(".rusty-ci").join("run").join(name);
+        info!("Cloning the repository into {}...", dir.display());
+        Runner::clone_into(dir)
+    };
+
+    let report = runner.run(&builder);
+    print!("{}", report);
+    if report.success() {
+        info!("Builder \"{}\" passed", name);
+    } else {
+        error!("Builder \"{}\" failed", name);
+        exit(1);
+    }
+}
+
 /// This method takes a boxed BuildSystem trait object and runs its install routine
 fn install(mut b: Box<dyn BuildSystem>) {
     match b.install() {
@@ -499,34 +857,20 @@ fn install(mut b: Box<dyn BuildSystem>) {
             println!("Successfully finished install");
         }
         Err(e) => {
-            println!("There was a problem while installing: {}", e);
+            error!("There was a problem while installing: {}", e);
+            exit(1);
         }
     };
 }
 
 /// This function takes a boxed BuildSystem trait object and uses it
 /// to run the `build` method on the object with the proper data.
-/// It constructs the workers and the master config file from an input yaml,
+/// It constructs the workers and the master config file from the input files,
 /// and feeds it to the buildsystem.
-fn build(mut b: Box<dyn BuildSystem>, master_yaml: Yaml, mail_yaml: Option<Yaml>) {
-    confirm_version(&master_yaml);
-
-    let mut workers = vec![];
-    let workers_section = match master_yaml.get_section("workers") {
-        Ok(w) => w,
-        Err(e) => {
-            error!("There was a problem reading the yaml file: {}", e);
-            exit(1);
-        }
-    };
-    for worker in workers_section {
-        workers.push(Worker::from(worker));
-    }
-    let mut master = MasterConfig::from(master_yaml);
+fn build(mut b: Box<dyn BuildSystem>, master_file: MasterFile, mail_file: Option<MailFile>) {
+    confirm_version(&master_file);
 
-    if let Some(mn) = mail_yaml {
-        master.set_mail_notifier(MailNotifier::from(mn));
-    }
+    let master = with_saved_passwords(master_config(master_file, mail_file));
 
     match b.build(master) {
         Ok(_) => {
@@ -534,34 +878,20 @@ fn build(mut b: Box<dyn BuildSystem>, master_yaml: Yaml, mail_yaml: Option<Yaml>
         }
         Err(e) => {
             error!("There was a problem while building: {}", e);
+            exit(1);
         }
     };
 }
 
 /// This function takes a boxed BuildSystem trait object and uses it
 /// to run the `rebuild` method on the object with the proper data.
-/// It constructs the workers and the master config file from an input yaml,
+/// It constructs the workers and the master config file from the input files,
 /// and feeds it to the buildsystem.
 /// Rebuilding a rusty-ci project does not kill its running processes.
-fn rebuild(mut b: Box<dyn BuildSystem>, master_yaml: Yaml, mail_yaml: Option<Yaml>) {
-    confirm_version(&master_yaml);
+fn rebuild(mut b: Box<dyn BuildSystem>, master_file: MasterFile, mail_file: Option<MailFile>) {
+    confirm_version(&master_file);
 
-    let mut workers = vec![];
-    let workers_section = match master_yaml.get_section("workers") {
-        Ok(w) => w,
-        Err(e) => {
-            error!("There was a problem reading the yaml file: {}", e);
-            exit(1);
-        }
-    };
-    for worker in workers_section {
-        workers.push(Worker::from(worker));
-    }
-    let mut master = MasterConfig::from(master_yaml);
-
-    if let Some(mn) = mail_yaml {
-        master.set_mail_notifier(MailNotifier::from(mn));
-    }
+    let master = with_saved_passwords(master_config(master_file, mail_file));
 
     match b.rebuild(master) {
         Ok(_) => {
@@ -569,6 +899,7 @@ fn rebuild(mut b: Box<dyn BuildSystem>, master_yaml: Yaml, mail_yaml: Option<Yam
         }
         Err(e) => {
             error!("There was a problem while building: {}", e);
+            exit(1);
         }
     };
 }
@@ -1,12 +1,11 @@
+use std::convert::TryFrom;
 use std::fmt::{Display, Error, Formatter};
-/// we cannot use result for from trait definitions.
-///
-/// This means we must handle the error some other way.
-/// For now, Im using exit. I might
-/// change this in the future, as needed.
-use std::process::exit;
 
-use crate::{unquote, unwrap, Step};
+use super::error::{empty_name, explain_typos, missing, require_sections, unknown_yaml_sections};
+use super::masterconfig::config_entry;
+use super::step::CLONE_METHODS;
+use crate::helper::python_ast::{render, Call, Expr, Stmt};
+use crate::{scalar, shell_words, unwrap, CloneOptions, ConfigError, Step, VersionControlSystem};
 use rusty_yaml::Yaml;
 use std::path::PathBuf;
 
@@ -14,128 +13,772 @@ use std::path::PathBuf;
 /// instance of the builder running your script.
 /// All this constant is used for is prepending
 /// to the working dir for all paths.
-const START_DIR: &str = "./build";
+pub(crate) const START_DIR: &str = "./build";
+
+/// Every section a builder can have. Anything else is reported as a typo.
+pub const BUILDER_SECTIONS: &[&str] = &[
+    "workers",
+    "script",
+    "repo",
+    "shell",
+    "vcs",
+    "branch",
+    "shallow",
+    "depth",
+    "submodules",
+    "method",
+    "matrix",
+];
+
+/// What a builder's script and workers write to use the value of an axis of its matrix,
+/// like `${matrix.toolchain}`
+pub const MATRIX_REFERENCE: &str = "${matrix.";
 
 /// The Builder struct encapsulates all the operations involved in
 /// defining a builder in buildbot. A builder works by giving tasks
 /// called steps to workers.
 ///
 /// A Builder object is composed of the name of the builder, the list
-/// of worker names that the builder will give the steps to, and the
-/// steps themselves.
+/// of worker names that the builder will give the steps to, the
+/// repository to clone, and the steps themselves.
 pub struct Builder {
     name: String,
     workernames: Vec<String>,
+    /// The url of the repository to clone before running the steps
+    repo: String,
+    /// How to clone the repository
+    clone_options: CloneOptions,
+    /// The VCS hosting the repository, which decides the clone step we use.
+    /// If this is None, the builder didn't specify one, and plain git is used
+    /// unless the master config tells us otherwise.
+    vcs: Option<VersionControlSystem>,
+    /// The steps to run after cloning the repository
     steps: Vec<Step>,
+    /// The name of the builder with a matrix this one was expanded from, if it was.
+    /// A scheduler that triggers that name triggers every builder expanded from it.
+    matrix_name: Option<String>,
 }
 
 /// The implmentation of the Builder struct
 impl Builder {
-    /// Create a new builder from a name, a list of worker names, and a list of steps
-    fn new<S: Display>(name: S, workernames: Vec<S>, steps: Vec<Step>) -> Self {
+    /// Starts building a builder named `name` in Rust, instead of reading it from YAML.
+    /// The builder needs at least one worker, a repo, and a line of script.
+    pub fn builder<S: ToString>(name: S) -> BuilderBuilder {
+        BuilderBuilder {
+            name: name.to_string(),
+            workernames: vec![],
+            repo: None,
+            script: vec![],
+            shell: false,
+            vcs: None,
+            clone_options: CloneOptions::default(),
+            matrix: None,
+        }
+    }
+
+    /// Reads a builder from YAML, and expands its matrix into a builder
+    /// for each combination, if it has one
+    pub fn expand(yaml: Yaml) -> Result<Vec<Builder>, Vec<ConfigError>> {
+        builder_from_yaml(yaml)?.build_matrix()
+    }
+
+    /// Create a new builder from a name, a list of worker names, a repository, and a list of steps
+    fn new<S: Display>(
+        name: S,
+        workernames: Vec<S>,
+        repo: S,
+        clone_options: CloneOptions,
+        vcs: Option<VersionControlSystem>,
+        steps: Vec<Step>,
+    ) -> Self {
         Self {
             name: name.to_string(),
             workernames: workernames.iter().map(|s| s.to_string()).collect(),
+            repo: repo.to_string(),
+            clone_options,
+            vcs,
             steps,
+            matrix_name: None,
+        }
+    }
+
+    /// Sets the VCS used to clone the repository,
+    /// unless the builder chose its own with the `vcs` section.
+    pub fn set_default_vcs(&mut self, vcs: VersionControlSystem) {
+        if self.vcs.is_none() {
+            self.vcs = Some(vcs);
+        }
+    }
+
+    /// This method returns the step that refreshes the builder's copy of the repository
+    pub fn get_clone_step(&self) -> Step {
+        let options = self.clone_options.clone();
+        match self.vcs {
+            Some(VersionControlSystem::GitHub) => Step::github_clone(&self.repo, options),
+            Some(VersionControlSystem::GitLab) => Step::gitlab_clone(&self.repo, options),
+            Some(VersionControlSystem::Unsupported) | None => Step::git_clone(&self.repo, options),
         }
     }
 
+    /// This method returns every step of the builder, starting with the clone step
+    pub fn get_steps(&self) -> Vec<Step> {
+        let mut steps = vec![self.get_clone_step()];
+        steps.extend(self.steps.clone());
+        steps
+    }
+
     /// This method returns the name of the builder
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
+
+    /// The name of the builder with a matrix this one was expanded from, if it was
+    pub fn get_matrix_name(&self) -> Option<String> {
+        self.matrix_name.clone()
+    }
+
+    /// This method returns the names of the workers the builder gives its steps to
+    pub fn get_workernames(&self) -> Vec<String> {
+        self.workernames.clone()
+    }
+
+    /// The Python statements that build the builder's factory
+    /// and add the builder to the master config
+    pub fn statements(&self) -> Vec<Stmt> {
+        let mut stmts = vec![Stmt::assign("temp_factory", Call::new("util.BuildFactory"))];
+        for step in self.get_steps() {
+            stmts.push(Stmt::expr(
+                Expr::name("temp_factory")
+                    .method("addStep")
+                    .arg(step.to_expr()),
+            ));
+        }
+        stmts.push(Stmt::expr(
+            config_entry("builders").method("append").arg(
+                Call::new("util.BuilderConfig")
+                    .kwarg("name", Expr::lit(&self.name))
+                    .kwarg("workernames", Expr::lit(&self.workernames))
+                    .kwarg("factory", "temp_factory"),
+            ),
+        ));
+        stmts
+    }
 }
 
 /// This impl converts a Builder into the Python code for buildbot that will
 /// give us the behaviour we want.
 impl Display for Builder {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(
-            f,
-            "
-temp_factory = util.BuildFactory()
-{steps}
-c['builders'].append(
-    util.BuilderConfig(name=\"{name}\",
-    workernames={:?},
-    factory=temp_factory))
-        ",
-            self.workernames,
-            name = self.name,
-            steps = self
-                .steps
-                .iter()
-                .map(|s| { format!("temp_factory.addStep({})", s) })
-                .collect::<Vec<String>>()
-                .join("\n"),
-        )
+        write!(f, "{}", render(&self.statements()))
+    }
+}
+
+/// Parses a boolean section, returning a BadValue error if it isn't `true` or `false`
+fn boolean(yaml: &Yaml, section: &str, context: &str) -> Result<bool, ConfigError> {
+    match unwrap(yaml, section).as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(ConfigError::BadValue {
+            context: context.to_string(),
+            section: section.to_string(),
+            value: other.to_string(),
+            reason: String::from("expected `true` or `false`"),
+        }),
+    }
+}
+
+/// Reads the optional `branch`, `shallow`, `depth`, `submodules`, and `method`
+/// sections of a builder that control how the repository is cloned.
+fn clone_options(yaml: &Yaml, context: &str) -> Result<CloneOptions, Vec<ConfigError>> {
+    let mut options = CloneOptions::default();
+    let mut errors = vec![];
+
+    if yaml.has_section("branch") {
+        options.branch = Some(unwrap(yaml, "branch"));
+    }
+
+    if yaml.has_section("shallow") {
+        match boolean(yaml, "shallow", context) {
+            Ok(true) => options.depth = Some(1),
+            Ok(false) => options.depth = None,
+            Err(e) => errors.push(e),
+        }
+    }
+
+    // An explicit depth overrides the `shallow` section
+    if yaml.has_section("depth") {
+        let depth = unwrap(yaml, "depth");
+        match depth.parse::<u32>() {
+            Ok(n) if n > 0 => options.depth = Some(n),
+            _ => errors.push(ConfigError::BadValue {
+                context: context.to_string(),
+                section: String::from("depth"),
+                value: depth,
+                reason: String::from("the depth must be a positive number of commits"),
+            }),
+        }
+    }
+
+    if yaml.has_section("submodules") {
+        match boolean(yaml, "submodules", context) {
+            Ok(submodules) => options.submodules = submodules,
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if yaml.has_section("method") {
+        let method = unwrap(yaml, "method");
+        if CLONE_METHODS.contains(&method.as_str()) {
+            options.method = method;
+        } else {
+            errors.push(ConfigError::BadValue {
+                context: context.to_string(),
+                section: String::from("method"),
+                value: method,
+                reason: format!("the method must be one of {}", CLONE_METHODS.join(", ")),
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(options)
+    } else {
+        Err(errors)
     }
 }
 
-/// This impl takes a rust-yaml::Yaml object and converts it into a Builder object
-impl From<Yaml> for Builder {
-    fn from(yaml: Yaml) -> Builder {
-        // The name of the yaml section will be used as the name of the builder
-        let name = yaml.get_name();
-
-        // Verify that the yaml contains the `workers`, `script`, and `repo` sections
-        // If not, tell user, and exit with error code 1.
-        for section in ["workers", "script", "repo"].iter() {
-            if !yaml.has_section(section) {
-                error!(
-                    "There was an error creating a builder: '{}' section not specified for '{}'",
-                    section, name
-                );
-                exit(1);
+/// The `matrix` of a builder, which expands it into a builder for every
+/// combination of the values of its axes, like every toolchain with every feature set.
+///
+/// Excluded combinations are left out, and included ones are added after the rest.
+/// An excluded combination can name just some of the axes, to leave out every
+/// combination with those values. An included one needs a value for every axis.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Matrix {
+    axes: Vec<(String, Vec<String>)>,
+    exclude: Vec<Vec<(String, String)>>,
+    include: Vec<Vec<(String, String)>>,
+}
+
+impl Matrix {
+    /// Adds an axis, and the values it takes
+    pub fn axis<S: ToString>(mut self, name: S, values: &[S]) -> Self {
+        let values = values.iter().map(ToString::to_string).collect();
+        self.axes.push((name.to_string(), values));
+        self
+    }
+
+    /// Leaves out every combination with these values
+    pub fn exclude<S: ToString>(mut self, values: &[(S, S)]) -> Self {
+        self.exclude.push(pairs(values));
+        self
+    }
+
+    /// Adds a combination, which can use values the axes don't list
+    pub fn include<S: ToString>(mut self, values: &[(S, S)]) -> Self {
+        self.include.push(pairs(values));
+        self
+    }
+
+    /// Retrieves the name of each axis, in order
+    pub fn get_axes(&self) -> Vec<String> {
+        self.axes.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Every combination to build, as the value of each axis in order.
+    /// The combinations vary the last axis fastest, so `stable-default`
+    /// comes before `stable-all`, which comes before `beta-default`.
+    pub fn combinations(
+        &self,
+        context: &str,
+    ) -> Result<Vec<Vec<(String, String)>>, Vec<ConfigError>> {
+        let bad = |value: &str, reason: String| ConfigError::BadValue {
+            context: context.to_string(),
+            section: String::from("matrix"),
+            value: value.to_string(),
+            reason,
+        };
+        let mut errors = vec![];
+
+        if self.axes.is_empty() {
+            errors.push(bad("", String::from("the matrix needs at least one axis")));
+        }
+        for (axis, values) in &self.axes {
+            if values.is_empty() {
+                errors.push(bad(axis, format!("the axis '{}' has no values", axis)));
             }
         }
-        // Now that we've verified the required sections exist, continue
-
-        let mut steps: Vec<Step> = vec![];
-
-        // Because of the way buildbot processes shell commands,
-        // you cannot call the change directory, or cd command as an instruction.
-        // Well, you can, but it wont change the directory.
-        //
-        // To fix this, we keep track of the current working directory using a PathBuf.
-        // When the script uses the `cd` command, it will modify this path.
-        let mut workdir = PathBuf::new();
-        // We want to start in the builders starting directory
-        workdir.push(START_DIR);
-
-        // Get the url for the repo from the yaml section
-        let url = unwrap(&yaml, "repo");
-
-        // Refresh your copy of the repository
-        steps.push(Step::git_clone(&url));
-        steps.push(Step::gitlab_clone(url));
-
-        // Run each instruction in the script section
-        for instruction in yaml.get_section("script").unwrap() {
-            // Here we turn the instruction into a slice of each word so we can match it
-            match instruction
-                .to_string()
-                .split_whitespace()
-                .collect::<Vec<&str>>()[..]
-            {
-                ["cd", path] => workdir.push(path),
-                _ => steps.push(Step::command(
-                    unquote(&instruction.to_string()),
-                    match workdir.to_str() {
-                        Some(s) => Some(s.to_string()),
-                        None => None,
+        let axes = self.get_axes();
+        for (name, value) in self.exclude.iter().chain(&self.include).flatten() {
+            if !axes.contains(name) {
+                errors.push(bad(
+                    &format!("{}: {}", name, value),
+                    format!("there's no axis named '{}' in the matrix", name),
+                ));
+            }
+        }
+        for combination in &self.include {
+            for axis in &axes {
+                if !combination.iter().any(|(name, _)| name == axis) {
+                    let value = combination
+                        .iter()
+                        .map(|(name, value)| format!("{}: {}", name, value))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    errors.push(bad(
+                        &value,
+                        format!(
+                            "an included combination needs a value for the axis '{}'",
+                            axis
+                        ),
+                    ));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut combinations: Vec<Vec<(String, String)>> = vec![vec![]];
+        for (axis, values) in &self.axes {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((axis.clone(), value.clone()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations.retain(|combination| {
+            !self
+                .exclude
+                .iter()
+                .any(|excluded| excluded.iter().all(|pair| combination.contains(pair)))
+        });
+
+        // Put the values of an included combination in the order of the axes
+        for included in &self.include {
+            let combination: Vec<(String, String)> = axes
+                .iter()
+                .filter_map(|axis| included.iter().find(|(name, _)| name == axis).cloned())
+                .collect();
+            if !combinations.contains(&combination) {
+                combinations.push(combination);
+            }
+        }
+
+        if combinations.is_empty() {
+            return Err(vec![bad(
+                "exclude",
+                String::from("every combination of the matrix is excluded"),
+            )]);
+        }
+        Ok(combinations)
+    }
+}
+
+fn pairs<S: ToString>(values: &[(S, S)]) -> Vec<(String, String)> {
+    values
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Replaces each `${matrix.axis}` in `text` with the value of the axis in `combination`
+fn substitute(
+    text: &str,
+    combination: &[(String, String)],
+    context: &str,
+    section: &str,
+) -> Result<String, ConfigError> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find(MATRIX_REFERENCE) {
+        let after = &rest[i + MATRIX_REFERENCE.len()..];
+        let end = match after.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let axis = &after[..end];
+        match combination.iter().find(|(name, _)| name == axis) {
+            Some((_, value)) => {
+                result.push_str(&rest[..i]);
+                result.push_str(value);
+            }
+            None => {
+                return Err(ConfigError::BadValue {
+                    context: context.to_string(),
+                    section: section.to_string(),
+                    value: text.to_string(),
+                    reason: if combination.is_empty() {
+                        String::from("the builder doesn't have a matrix")
+                    } else {
+                        format!("there's no axis named '{}' in the matrix", axis)
                     },
-                )),
-            };
+                })
+            }
         }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
 
-        // Get the workers from the yaml file
-        let mut workers: Vec<String> = vec![];
-        for worker in yaml.get_section("workers").unwrap() {
-            workers.push(worker.to_string());
+/// This builds a Builder with the same sections the YAML uses.
+/// Every mistake is collected and returned from `build`, like `Builder::try_from` does.
+#[derive(Clone, Debug)]
+pub struct BuilderBuilder {
+    name: String,
+    workernames: Vec<String>,
+    repo: Option<String>,
+    script: Vec<String>,
+    shell: bool,
+    vcs: Option<VersionControlSystem>,
+    clone_options: CloneOptions,
+    matrix: Option<Matrix>,
+}
+
+impl BuilderBuilder {
+    /// Adds a worker for the builder to give its steps to
+    pub fn worker<S: ToString>(mut self, name: S) -> Self {
+        self.workernames.push(name.to_string());
+        self
+    }
+
+    /// The url of the repository to clone before running the script
+    pub fn repo<S: ToString>(mut self, url: S) -> Self {
+        self.repo = Some(url.to_string());
+        self
+    }
+
+    /// Adds a line to the script, like a line of the `script` section.
+    /// `cd` lines change the directory of the lines after them.
+    pub fn script<S: ToString>(mut self, line: S) -> Self {
+        self.script.push(line.to_string());
+        self
+    }
+
+    /// Run the script lines with `/bin/sh -c`
+    pub fn shell(mut self, shell: bool) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// The VCS to clone with, instead of the merge request handler's
+    pub fn vcs(mut self, vcs: VersionControlSystem) -> Self {
+        self.vcs = Some(vcs);
+        self
+    }
+
+    /// Replaces every option for cloning the repository
+    pub fn clone_options(mut self, options: CloneOptions) -> Self {
+        self.clone_options = options;
+        self
+    }
+
+    /// The branch to clone
+    pub fn branch<S: ToString>(mut self, branch: S) -> Self {
+        self.clone_options.branch = Some(branch.to_string());
+        self
+    }
+
+    /// Only clone the last `depth` commits
+    pub fn depth(mut self, depth: u32) -> Self {
+        self.clone_options.depth = Some(depth);
+        self
+    }
+
+    /// Clone the submodules of the repository too
+    pub fn submodules(mut self, submodules: bool) -> Self {
+        self.clone_options.submodules = submodules;
+        self
+    }
+
+    /// How buildbot refreshes its copy of the repository, one of `CLONE_METHODS`
+    pub fn method<S: ToString>(mut self, method: S) -> Self {
+        self.clone_options.method = method.to_string();
+        self
+    }
+
+    /// Expands the builder into a builder for each combination of the matrix's axes.
+    /// The script and workers can use the value of an axis with `${matrix.axis}`.
+    pub fn matrix(mut self, matrix: Matrix) -> Self {
+        self.matrix = Some(matrix);
+        self
+    }
+
+    /// Builds the builder, or returns everything wrong with it.
+    /// A builder with a matrix is more than one builder, so it's built with `build_matrix`.
+    pub fn build(self) -> Result<Builder, Vec<ConfigError>> {
+        let context = format!("builder '{}'", self.name);
+        let mut errors = vec![];
+
+        if self.matrix.is_some() {
+            errors.push(ConfigError::BadValue {
+                context: context.clone(),
+                section: String::from("matrix"),
+                value: self.name.clone(),
+                reason: String::from("a builder with a matrix is expanded into more than one"),
+            });
+        }
+
+        if self.name.is_empty() {
+            errors.push(empty_name(&context));
+        }
+        if self.workernames.is_empty() {
+            errors.push(missing(&context, "workers"));
+        }
+        if self.repo.is_none() {
+            errors.push(missing(&context, "repo"));
         }
+        if self.script.is_empty() {
+            errors.push(missing(&context, "script"));
+        }
+
+        if self.clone_options.depth == Some(0) {
+            errors.push(ConfigError::BadValue {
+                context: context.clone(),
+                section: String::from("depth"),
+                value: String::from("0"),
+                reason: String::from("the depth must be a positive number of commits"),
+            });
+        }
+        if !CLONE_METHODS.contains(&self.clone_options.method.as_str()) {
+            errors.push(ConfigError::BadValue {
+                context: context.clone(),
+                section: String::from("method"),
+                value: self.clone_options.method.clone(),
+                reason: format!("the method must be one of {}", CLONE_METHODS.join(", ")),
+            });
+        }
+
+        let steps = match script_steps(&self.script, self.shell, &context) {
+            Ok(steps) => steps,
+            Err(e) => {
+                errors.extend(e);
+                vec![]
+            }
+        };
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Builder::new(
+            self.name,
+            self.workernames,
+            self.repo.unwrap_or_default(),
+            self.clone_options,
+            self.vcs,
+            steps,
+        ))
+    }
+
+    /// Builds a builder for each combination of the matrix, named after the builder
+    /// and the value of each axis, like `cargo-test-stable-default`.
+    /// Without a matrix, this is just the one builder.
+    ///
+    /// Each mistake is only returned once, and not once for every combination.
+    pub fn build_matrix(mut self) -> Result<Vec<Builder>, Vec<ConfigError>> {
+        let context = format!("builder '{}'", self.name);
+        let matrix = self.matrix.take();
+        let combinations = match &matrix {
+            Some(matrix) => matrix.combinations(&context)?,
+            None => vec![vec![]],
+        };
+
+        let mut builders = vec![];
+        let mut errors: Vec<ConfigError> = vec![];
+        for combination in combinations {
+            let mut builder = self.clone();
+            let mut found = vec![];
+            for (lines, section) in &mut [
+                (&mut builder.script, "script"),
+                (&mut builder.workernames, "workers"),
+            ] {
+                for line in lines.iter_mut() {
+                    match substitute(line, &combination, &context, section) {
+                        Ok(substituted) => *line = substituted,
+                        Err(e) => found.push(e),
+                    }
+                }
+            }
 
-        // Return the new builder
-        Builder::new(name, workers, steps)
+            // The builder is built with the name it was given, so its mistakes point at it
+            match builder.build() {
+                Ok(mut builder) if found.is_empty() => {
+                    if matrix.is_some() {
+                        let values: Vec<String> =
+                            combination.into_iter().map(|(_, value)| value).collect();
+                        builder.name = format!("{}-{}", self.name, values.join("-"));
+                        builder.matrix_name = Some(self.name.clone());
+                    }
+                    builders.push(builder);
+                }
+                Ok(_) => {}
+                Err(e) => found.extend(e),
+            }
+            for error in found {
+                if !errors.contains(&error) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(builders)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Turns the lines of a builder's script into steps
+fn script_steps(
+    script: &[String],
+    shell: bool,
+    context: &str,
+) -> Result<Vec<Step>, Vec<ConfigError>> {
+    let mut steps: Vec<Step> = vec![];
+
+    // Because of the way buildbot processes shell commands,
+    // you cannot call the change directory, or cd command as an instruction.
+    // Well, you can, but it wont change the directory.
+    //
+    // To fix this, we keep track of the current working directory using a PathBuf.
+    // When the script uses the `cd` command, it will modify this path.
+    let mut workdir = PathBuf::new();
+    // We want to start in the builders starting directory
+    workdir.push(START_DIR);
+
+    // A multi-line entry, like a YAML `|` block, is one line per instruction,
+    // unless the builder runs it with the shell, which can take the whole block.
+    let instructions: Vec<String> = if shell {
+        script.iter().map(|s| s.trim_end().to_string()).collect()
+    } else {
+        script
+            .iter()
+            .flat_map(|s| s.lines())
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect()
+    };
+
+    // Run each instruction in the script section
+    let mut errors = vec![];
+    for instruction in instructions {
+        // Here we split the instruction into its words so we can match it
+        let words = match shell_words(&instruction) {
+            Ok(words) => words,
+            // The shell will report its own syntax errors
+            Err(_) if shell => vec![],
+            Err(e) => {
+                errors.push(ConfigError::BadValue {
+                    context: context.to_string(),
+                    section: String::from("script"),
+                    value: instruction,
+                    reason: e,
+                });
+                continue;
+            }
+        };
+
+        let workdir_str = workdir.to_str().map(String::from);
+        match &words[..] {
+            [cd, path] if cd == "cd" => workdir.push(path),
+            _ if shell => steps.push(Step::shell_command(instruction, workdir_str)),
+            _ => steps.push(Step::command(instruction, workdir_str)),
+        };
+    }
+
+    if errors.is_empty() {
+        Ok(steps)
+    } else {
+        Err(errors)
+    }
+}
+
+/// This impl takes a rust-yaml::Yaml object and converts it into a Builder object.
+/// A builder with a matrix is more than one builder, so it's read with `Builder::expand`.
+impl TryFrom<Yaml> for Builder {
+    type Error = Vec<ConfigError>;
+
+    fn try_from(yaml: Yaml) -> Result<Builder, Self::Error> {
+        builder_from_yaml(yaml)?.build()
+    }
+}
+
+/// Reads each section of a builder from YAML
+fn builder_from_yaml(yaml: Yaml) -> Result<BuilderBuilder, Vec<ConfigError>> {
+    // The name of the yaml section will be used as the name of the builder
+    let name = yaml.get_name();
+
+    let context = format!("builder '{}'", name);
+
+    // Verify that the yaml contains the `workers`, `script`, and `repo` sections
+    // If not, return every section that is missing.
+    let mut errors = require_sections(&yaml, &["workers", "script", "repo"], &context);
+    errors.extend(unknown_yaml_sections(&yaml, BUILDER_SECTIONS, &context));
+    if !errors.is_empty() {
+        return Err(explain_typos(errors));
+    }
+    // Now that we've verified the required sections exist, continue
+
+    // Get the url for the repo from the yaml section
+    let mut builder = Builder::builder(name)
+        .repo(unwrap(&yaml, "repo"))
+        .clone_options(clone_options(&yaml, &context)?);
+
+    // Run the script with `/bin/sh -c` if the builder asks for it
+    if yaml.has_section("shell") {
+        builder = builder.shell(boolean(&yaml, "shell", &context).map_err(|e| vec![e])?);
+    }
+
+    // The builder can choose the VCS to clone with instead of the merge request handler's
+    if yaml.has_section("vcs") {
+        builder = builder.vcs(unwrap(&yaml, "vcs").parse().map_err(|e| vec![e])?);
+    }
+
+    for instruction in yaml.get_section("script").unwrap() {
+        builder = builder.script(scalar(&instruction));
+    }
+
+    // Get the workers from the yaml file
+    for worker in yaml.get_section("workers").unwrap() {
+        builder = builder.worker(scalar(&worker));
+    }
+
+    if yaml.has_section("matrix") {
+        builder = builder.matrix(matrix_from_yaml(&yaml.get_section("matrix").unwrap()));
+    }
+
+    Ok(builder)
+}
+
+/// Reads the `matrix` section of a builder: each axis with its values,
+/// and the lists of combinations in `exclude` and `include`
+fn matrix_from_yaml(yaml: &Yaml) -> Matrix {
+    let combination = |yaml: Yaml| -> Vec<(String, String)> {
+        yaml.get_section_names()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| (name.clone(), unwrap(&yaml, name)))
+            .collect()
+    };
+
+    let mut matrix = Matrix::default();
+    for section in yaml.clone() {
+        match section.get_name().as_str() {
+            "exclude" => matrix.exclude.extend(section.into_iter().map(combination)),
+            "include" => matrix.include.extend(section.into_iter().map(combination)),
+            axis => {
+                let values: Vec<String> = section.clone().into_iter().map(|v| scalar(&v)).collect();
+                matrix.axes.push((axis.to_string(), values));
+            }
+        }
     }
+    matrix
 }
@@ -0,0 +1,227 @@
+use crate::helper::python::regex_error;
+use crate::{Builder, MasterConfig};
+use regex::Regex;
+use std::fmt::{Display, Error, Formatter};
+
+/// How bad a problem found by `check` is.
+/// Errors will break the generated buildbot project,
+/// warnings are just things that are probably mistakes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found while cross referencing the parts of a MasterConfig.
+///
+/// The path is the YAML path of the offending entry,
+/// for example `schedulers.any-change.builders[0]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckIssue {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+impl CheckIssue {
+    fn error<S: ToString>(path: S, message: S) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn warning<S: ToString>(path: S, message: S) -> Self {
+        Self {
+            severity: Severity::Warning,
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl Display for CheckIssue {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Checks that every name referenced in the master config actually exists.
+///
+/// This reports:
+/// - builders in `schedulers.*.builders` that aren't defined,
+/// - workers in `builders.*.workers` that aren't defined,
+/// - schedulers in `schedulers.*.depends` that aren't defined,
+/// - workers that no builder uses, and builders that no scheduler triggers,
+/// - cycles among the `depends` sections of the schedulers,
+/// - regular expressions in `branch` and `triggers` that don't compile.
+pub fn check(master: &MasterConfig) -> Vec<CheckIssue> {
+    let mut issues = vec![];
+
+    let worker_names: Vec<String> = master.get_workers().iter().map(|w| w.get_name()).collect();
+    let builder_names: Vec<String> = master.get_builders().iter().map(|b| b.get_name()).collect();
+    let scheduler_names: Vec<String> = master
+        .get_schedulers()
+        .iter()
+        .map(|s| s.get_name())
+        .collect();
+
+    // Every builder must delegate to workers that exist
+    let mut used_workers = vec![];
+    for builder in master.get_builders() {
+        for (i, worker) in builder.get_workernames().iter().enumerate() {
+            if !worker_names.contains(worker) {
+                issues.push(CheckIssue::error(
+                    format!("{}.workers[{}]", builder_path(builder), i),
+                    format!("the worker '{}' is not defined", worker),
+                ));
+            }
+            used_workers.push(worker.clone());
+        }
+    }
+
+    // Every scheduler must trigger builders that exist, and depend on a scheduler that exists
+    let mut used_builders = vec![];
+    for scheduler in master.get_schedulers() {
+        for (i, builder) in scheduler.get_buildernames().iter().enumerate() {
+            if !builder_names.contains(builder) {
+                issues.push(CheckIssue::error(
+                    format!("schedulers.{}.builders[{}]", scheduler.get_name(), i),
+                    format!("the builder '{}' is not defined", builder),
+                ));
+            }
+            used_builders.push(builder.clone());
+        }
+
+        match scheduler.get_depends() {
+            Some(depends) => {
+                if !scheduler_names.contains(&depends) {
+                    issues.push(CheckIssue::error(
+                        format!("schedulers.{}.depends", scheduler.get_name()),
+                        format!("the scheduler '{}' is not defined", depends),
+                    ));
+                }
+            }
+            // Only schedulers that dont depend on another use their regexes
+            None => {
+                let path = format!("schedulers.{}.branch", scheduler.get_name());
+                issues.extend(check_regex(path, &scheduler.get_branch()));
+
+                for (i, trigger) in scheduler.get_file_triggers().iter().enumerate() {
+                    let path = format!("schedulers.{}.triggers[{}]", scheduler.get_name(), i);
+                    issues.extend(check_regex(path, trigger));
+                }
+            }
+        }
+    }
+
+    for worker in &worker_names {
+        if !used_workers.contains(worker) {
+            issues.push(CheckIssue::warning(
+                format!("workers.{}", worker),
+                String::from("this worker is not used by any builder"),
+            ));
+        }
+    }
+
+    for builder in master.get_builders() {
+        if !used_builders.contains(&builder.get_name()) {
+            issues.push(CheckIssue::warning(
+                builder_path(builder),
+                String::from("this builder is not triggered by any scheduler"),
+            ));
+        }
+    }
+
+    issues.extend(dependency_cycles(master));
+
+    // The builders of a matrix share the sections they were expanded from,
+    // so they can find the same problem more than once
+    let mut found = vec![];
+    issues.retain(|issue| {
+        let is_new = !found.contains(issue);
+        found.push(issue.clone());
+        is_new
+    });
+    issues
+}
+
+/// The YAML path of a builder, which is the builder it was expanded from for a matrix
+fn builder_path(builder: &Builder) -> String {
+    let name = builder
+        .get_matrix_name()
+        .unwrap_or_else(|| builder.get_name());
+    format!("builders.{}", name)
+}
+
+/// Checks a regex the way the generated master config compiles it, with python's `re` module.
+/// Without python, the `regex` crate is used instead, but it doesn't support everything python does,
+/// so a feature it doesn't support is only a warning.
+fn check_regex(path: String, regex: &str) -> Option<CheckIssue> {
+    match regex_error(regex) {
+        Ok(None) => None,
+        Ok(Some(e)) => Some(CheckIssue::error(
+            path,
+            format!("invalid regular expression: {}", e),
+        )),
+        Err(_) => match Regex::new(regex) {
+            Ok(_) => None,
+            Err(e) if e.to_string().contains("not supported") => Some(CheckIssue::warning(
+                path,
+                format!(
+                    "this regular expression can't be checked without python3: {}",
+                    e
+                ),
+            )),
+            Err(e) => Some(CheckIssue::error(
+                path,
+                format!("invalid regular expression: {}", e),
+            )),
+        },
+    }
+}
+
+/// Finds every cycle in the `depends` sections of the schedulers.
+/// Because a scheduler depends on at most one other scheduler,
+/// we can find a cycle by just following the chain of upstream schedulers.
+fn dependency_cycles(master: &MasterConfig) -> Vec<CheckIssue> {
+    let upstream = |name: &str| -> Option<String> {
+        master
+            .get_schedulers()
+            .iter()
+            .find(|s| s.get_name() == name)
+            .and_then(|s| s.get_depends())
+    };
+
+    let mut issues = vec![];
+    let mut reported: Vec<String> = vec![];
+    for scheduler in master.get_schedulers() {
+        let mut chain = vec![scheduler.get_name()];
+        while let Some(next) = upstream(chain.last().unwrap()) {
+            if let Some(start) = chain.iter().position(|name| *name == next) {
+                let mut cycle = chain[start..].to_vec();
+                // Only report each cycle once, from whichever scheduler we found it first
+                if !cycle.iter().any(|name| reported.contains(name)) {
+                    reported.extend(cycle.clone());
+                    let path = format!("schedulers.{}.depends", cycle[cycle.len() - 1]);
+                    cycle.push(next);
+                    issues.push(CheckIssue::error(
+                        path,
+                        format!(
+                            "the schedulers depend on each other in a cycle: {}",
+                            cycle.join(" -> ")
+                        ),
+                    ));
+                }
+                break;
+            }
+            chain.push(next);
+        }
+    }
+    issues
+}
@@ -0,0 +1,239 @@
+use rusty_yaml::Yaml;
+use std::fmt::{Display, Error, Formatter};
+
+/// This enum describes everything that can go wrong while converting
+/// a YAML configuration into the buildbot objects in this module.
+///
+/// The constructors for the buildbot objects don't stop at the first
+/// mistake they find. Instead, they collect every `ConfigError` they can
+/// and hand them all back, so the user can fix their YAML in one go.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    /// A required section was not declared.
+    /// The context describes where the section was expected,
+    /// for example "builder 'cargo-test'" or "master".
+    MissingSection { context: String, section: String },
+
+    /// A section was declared that we don't know, which is usually a typo.
+    /// The suggestion is the known section with the closest spelling, if one is close.
+    UnknownSection {
+        context: String,
+        section: String,
+        suggestion: Option<String>,
+    },
+
+    /// A section was declared, but its value can't be used.
+    BadValue {
+        context: String,
+        section: String,
+        value: String,
+        reason: String,
+    },
+
+    /// A reference like `${SMTP_PASSWORD}` in a section couldn't be resolved.
+    Unresolved {
+        context: String,
+        section: String,
+        reference: String,
+        reason: String,
+    },
+
+    /// The `version-control-system` of the merge request handler
+    /// is not one that we know how to handle.
+    UnknownVcs(String),
+
+    /// A line in the YAML has an odd number of double quotes.
+    UnmatchedQuotes { context: String, line: String },
+
+    /// Two workers, builders, or schedulers share the same name.
+    DuplicateName { kind: String, name: String },
+
+    /// Two of the files a master file is split across both define the same
+    /// worker, builder, scheduler, or top-level section.
+    DefinedTwice {
+        kind: String,
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    /// The authentication token for the version control system
+    /// could not be read, or it was empty.
+    MissingAuthToken { path: String, reason: String },
+
+    /// A configuration file couldn't be read, or isn't valid YAML, TOML or JSON
+    /// for the sections it should hold.
+    File { path: String, reason: String },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            ConfigError::MissingSection { context, section } => {
+                write!(f, "'{}' section not specified for {}", section, context)
+            }
+            ConfigError::UnknownSection {
+                context,
+                section,
+                suggestion,
+            } => {
+                write!(f, "unknown section '{}' in {}", section, context)?;
+                match suggestion {
+                    Some(suggestion) => write!(f, ", did you mean '{}'?", suggestion),
+                    None => Ok(()),
+                }
+            }
+            ConfigError::BadValue {
+                context,
+                section,
+                value,
+                reason,
+            } => write!(
+                f,
+                "invalid value '{}' for the '{}' section of {}: {}",
+                value, section, context, reason
+            ),
+            ConfigError::Unresolved {
+                context,
+                section,
+                reference,
+                reason,
+            } => write!(
+                f,
+                "could not resolve '{}' in the '{}' section of {}: {}",
+                reference, section, context, reason
+            ),
+            ConfigError::UnknownVcs(vcs) => write!(
+                f,
+                "unknown version control system '{}', expected one of 'github', 'gitlab', or 'none'",
+                vcs
+            ),
+            ConfigError::UnmatchedQuotes { context, line } => {
+                write!(f, "unmatched quotes in {} on the line '{}'", context, line)
+            }
+            ConfigError::DuplicateName { kind, name } => {
+                write!(f, "the {} '{}' is defined more than once", kind, name)
+            }
+            ConfigError::DefinedTwice {
+                kind,
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "the {} '{}' is defined in both '{}' and '{}'",
+                kind, name, first, second
+            ),
+            ConfigError::MissingAuthToken { path, reason } => write!(
+                f,
+                "could not get the authentication token from '{}': {}",
+                path, reason
+            ),
+            ConfigError::File { path, reason } => {
+                write!(f, "could not load the configuration file '{}': {}", path, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Returns a `MissingSection` error for each of `sections` that
+/// is not declared in `yaml`.
+pub(crate) fn require_sections(yaml: &Yaml, sections: &[&str], context: &str) -> Vec<ConfigError> {
+    sections
+        .iter()
+        .filter(|section| !yaml.has_section(section))
+        .map(|section| ConfigError::MissingSection {
+            context: context.to_string(),
+            section: section.to_string(),
+        })
+        .collect()
+}
+
+/// Returns an `UnknownSection` error for each of `sections` that isn't one of `known`
+pub(crate) fn unknown_sections<S: AsRef<str>>(
+    sections: &[S],
+    known: &[&str],
+    context: &str,
+) -> Vec<ConfigError> {
+    sections
+        .iter()
+        .map(|section| section.as_ref())
+        .filter(|section| !known.contains(section))
+        .map(|section| ConfigError::UnknownSection {
+            context: context.to_string(),
+            section: section.to_string(),
+            suggestion: closest(section, known),
+        })
+        .collect()
+}
+
+/// Drops the `MissingSection` errors for sections that an `UnknownSection` error
+/// suggests. When `poll-intreval` is declared, `poll-interval` isn't missing, it's misspelled.
+pub(crate) fn explain_typos(errors: Vec<ConfigError>) -> Vec<ConfigError> {
+    let typos: Vec<(String, String)> = errors
+        .iter()
+        .filter_map(|error| match error {
+            ConfigError::UnknownSection {
+                context,
+                suggestion: Some(suggestion),
+                ..
+            } => Some((context.clone(), suggestion.clone())),
+            _ => None,
+        })
+        .collect();
+
+    errors
+        .into_iter()
+        .filter(|error| match error {
+            ConfigError::MissingSection { context, section } => {
+                !typos.iter().any(|(c, s)| c == context && s == section)
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+/// Returns an `UnknownSection` error for each section of `yaml` that isn't one of `known`
+pub(crate) fn unknown_yaml_sections(
+    yaml: &Yaml,
+    known: &[&str],
+    context: &str,
+) -> Vec<ConfigError> {
+    unknown_sections(
+        &yaml.get_section_names().unwrap_or_default(),
+        known,
+        context,
+    )
+}
+
+/// The known section spelled the most like `section`, if it's close enough to be a typo.
+/// Swapped letters count as one mistake, so `poll-intreval` finds `poll-interval`.
+fn closest(section: &str, known: &[&str]) -> Option<String> {
+    let allowed = (section.chars().count() / 3).max(1);
+    known
+        .iter()
+        .map(|name| (strsim::damerau_levenshtein(section, name), name))
+        .filter(|(distance, _)| *distance <= allowed)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name.to_string())
+}
+
+/// A `MissingSection` error for a section of `context` that wasn't set
+pub(crate) fn missing(context: &str, section: &str) -> ConfigError {
+    ConfigError::MissingSection {
+        context: context.to_string(),
+        section: section.to_string(),
+    }
+}
+
+/// A `BadValue` error for a worker, builder or scheduler built with an empty name
+pub(crate) fn empty_name(context: &str) -> ConfigError {
+    ConfigError::BadValue {
+        context: context.to_string(),
+        section: String::from("name"),
+        value: String::new(),
+        reason: String::from("the name can't be empty"),
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{Cmd, Scheduler};
+use regex::Regex;
+use std::fmt::{Display, Error, Formatter};
+
+/// A change to a branch, the way a scheduler sees it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub branch: String,
+    /// The paths of the files that changed, relative to the root of the repository
+    pub files: Vec<String>,
+}
+
+impl Change {
+    pub fn new<S: ToString>(branch: S, files: Vec<S>) -> Self {
+        Self {
+            branch: branch.to_string(),
+            files: files.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    /// The files changed by a range of commits in the local git repository, like `main..HEAD`.
+    /// If `branch` is None, the branch that is checked out is used.
+    pub fn from_git_range(range: &str, branch: Option<String>) -> Result<Self, String> {
+        let branch = match branch {
+            Some(branch) => branch,
+            None => Cmd::new("git")
+                .arg("rev-parse")
+                .arg("--abbrev-ref")
+                .arg("HEAD")
+                .run()?
+                .stdout
+                .trim()
+                .to_string(),
+        };
+
+        let output = Cmd::new("git")
+            .arg("diff")
+            .arg("--name-only")
+            .arg(range)
+            .run()?;
+        Ok(Self {
+            branch,
+            files: output.stdout.lines().map(String::from).collect(),
+        })
+    }
+}
+
+/// Why a scheduler would or wouldn't trigger its builders for a change
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reason {
+    /// The branch of the change doesn't match the scheduler's `branch` regex
+    BranchMismatch { branch: String },
+    /// The branch matched, but none of the changed files match a trigger
+    NoTriggerMatched { branch: String },
+    /// The branch matched, and each of these (trigger, file) pairs matched
+    Triggered {
+        branch: String,
+        matches: Vec<(String, String)>,
+    },
+    /// The scheduler depends on another, and runs if and only if that one does
+    Depends { upstream: String, triggered: bool },
+    /// One of the scheduler's regexes can't be compiled here, like one that uses
+    /// python's look-ahead or backreferences, so we can't tell what it would do
+    Unsimulated { reason: String },
+}
+
+/// What a single scheduler would do with a change
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchedulerExplanation {
+    pub scheduler: String,
+    pub triggered: bool,
+    pub buildernames: Vec<String>,
+    pub reason: Reason,
+}
+
+/// What every scheduler would do with a change
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation {
+    pub change: Change,
+    pub schedulers: Vec<SchedulerExplanation>,
+}
+
+impl Explanation {
+    /// The names of the builders that would run, in the order they are first triggered
+    pub fn get_buildernames(&self) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        for scheduler in self.schedulers.iter().filter(|s| s.triggered) {
+            for name in &scheduler.buildernames {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        names
+    }
+}
+
+/// Compiles a regex so it only matches from the start of the text,
+/// like python's `re.match`, or the whole text, like `re.fullmatch`.
+fn anchored(regex: &str, whole: bool, context: &str) -> Result<Regex, String> {
+    let pattern = if whole {
+        format!(r"\A(?:{})\z", regex)
+    } else {
+        format!(r"\A(?:{})", regex)
+    };
+    Regex::new(&pattern).map_err(|e| {
+        // The last line of a parse error says what's wrong, under a copy of the pattern
+        let e = e.to_string();
+        let detail = e.lines().last().unwrap_or_default();
+        format!("{} {}", context, detail.trim_start_matches("error: "))
+    })
+}
+
+/// Works out which schedulers would trigger their builders for a change,
+/// the same way the generated master config decides at runtime.
+///
+/// The branch regex is matched against the start of the branch name, like buildbot's
+/// change filter, and the trigger regexes have to match a whole file name.
+/// A scheduler with `depends` runs when the scheduler it depends on runs,
+/// assuming that scheduler's builds pass.
+/// This assumes the change is whitelisted, so the scheduler passwords don't matter.
+/// A scheduler whose regexes can't be compiled here is reported as such, and never runs.
+pub fn explain(schedulers: &[Scheduler], change: &Change) -> Explanation {
+    let mut explanations = vec![];
+    for scheduler in schedulers {
+        let name = scheduler.get_name();
+        let reason = match scheduler.get_depends() {
+            // Whether this runs is decided once every scheduler without `depends` is known
+            Some(upstream) => Reason::Depends {
+                upstream,
+                triggered: false,
+            },
+            None => match match_change(scheduler, change) {
+                Ok(reason) => reason,
+                Err(reason) => Reason::Unsimulated { reason },
+            },
+        };
+
+        explanations.push(SchedulerExplanation {
+            scheduler: name,
+            triggered: matches!(reason, Reason::Triggered { .. }),
+            buildernames: scheduler.get_buildernames(),
+            reason,
+        });
+    }
+
+    // Follow the `depends` chains until nothing changes.
+    // Schedulers in a cycle never run, because nothing starts the cycle.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..explanations.len() {
+            let upstream_triggered = match &explanations[i].reason {
+                Reason::Depends {
+                    upstream,
+                    triggered: false,
+                } => explanations
+                    .iter()
+                    .any(|e| &e.scheduler == upstream && e.triggered),
+                _ => false,
+            };
+
+            if upstream_triggered {
+                let explanation = &mut explanations[i];
+                explanation.triggered = true;
+                if let Reason::Depends { triggered, .. } = &mut explanation.reason {
+                    *triggered = true;
+                }
+                changed = true;
+            }
+        }
+    }
+
+    Explanation {
+        change: change.clone(),
+        schedulers: explanations,
+    }
+}
+
+/// Matches a change against the branch and triggers of a scheduler without `depends`
+fn match_change(scheduler: &Scheduler, change: &Change) -> Result<Reason, String> {
+    let branch = scheduler.get_branch();
+    let branch_re = anchored(&branch, false, &format!("the branch `{}`:", branch))?;
+    if !branch_re.is_match(&change.branch) {
+        return Ok(Reason::BranchMismatch { branch });
+    }
+
+    let mut matches = vec![];
+    for trigger in scheduler.get_file_triggers() {
+        let trigger_re = anchored(&trigger, true, &format!("the trigger `{}`:", trigger))?;
+        for file in &change.files {
+            if trigger_re.is_match(file) {
+                matches.push((trigger.clone(), file.clone()));
+            }
+        }
+    }
+
+    if matches.is_empty() {
+        Ok(Reason::NoTriggerMatched { branch })
+    } else {
+        Ok(Reason::Triggered { branch, matches })
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        writeln!(
+            f,
+            "A change to {} file(s) on branch '{}':",
+            self.change.files.len(),
+            self.change.branch
+        )?;
+
+        for scheduler in &self.schedulers {
+            if scheduler.triggered {
+                writeln!(
+                    f,
+                    "  scheduler '{}' runs {}",
+                    scheduler.scheduler,
+                    scheduler.buildernames.join(", ")
+                )?;
+            } else if let Reason::Unsimulated { .. } = scheduler.reason {
+                writeln!(
+                    f,
+                    "  scheduler '{}' may run {}",
+                    scheduler.scheduler,
+                    scheduler.buildernames.join(", ")
+                )?;
+            } else {
+                writeln!(f, "  scheduler '{}' does nothing", scheduler.scheduler)?;
+            }
+
+            match &scheduler.reason {
+                Reason::BranchMismatch { branch } => {
+                    writeln!(f, "    the branch doesn't match `{}`", branch)?
+                }
+                Reason::NoTriggerMatched { branch } => {
+                    writeln!(f, "    the branch matches `{}`", branch)?;
+                    writeln!(f, "    no changed file matches a trigger")?;
+                }
+                Reason::Triggered { branch, matches } => {
+                    writeln!(f, "    the branch matches `{}`", branch)?;
+                    for (trigger, file) in matches {
+                        writeln!(f, "    the trigger `{}` matches '{}'", trigger, file)?;
+                    }
+                }
+                Reason::Depends {
+                    upstream,
+                    triggered: true,
+                } => writeln!(
+                    f,
+                    "    it depends on '{}', which runs (assuming its builds pass)",
+                    upstream
+                )?,
+                Reason::Depends { upstream, .. } => {
+                    writeln!(f, "    it depends on '{}', which doesn't run", upstream)?
+                }
+                Reason::Unsimulated { reason } => {
+                    writeln!(f, "    it can't be simulated: {}", reason)?
+                }
+            }
+        }
+
+        let builders = self.get_buildernames();
+        if builders.is_empty() {
+            write!(f, "No builders would run")
+        } else {
+            write!(f, "Builders that would run: {}", builders.join(", "))
+        }
+    }
+}
@@ -1,7 +1,27 @@
-use crate::{unmatched_quotes, unwrap};
+use super::error::{explain_typos, require_sections, unknown_yaml_sections};
+use super::masterconfig::{config_entry, secret};
+use crate::helper::python_ast::{render, Call, Expr, Stmt};
+use crate::{scalar, unmatched_quotes, unwrap, ConfigError};
 use rusty_yaml::Yaml;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::fmt::{Display, Error, Formatter};
-use std::process::exit;
+
+/// Every section the mail file can have. Anything else is reported as a typo.
+pub const MAIL_SECTIONS: &[&str] = &[
+    "extra-recipients",
+    "from-address",
+    "smtp-relay-host",
+    "smtp-port",
+    "lookup",
+    "smtp-password",
+];
+
+/// The name of the secret the SMTP password is kept in, so it isn't written in `master.cfg`
+pub const SMTP_PASSWORD_SECRET: &str = "smtp-password";
+
+/// Every section of the `extra-recipients` section of the mail file
+pub const RECIPIENT_SECTIONS: &[&str] = &["all", "failure", "success"];
 
 /// This object is responsible for building the `MailNotifier` object
 /// in the buildbot master config. It contains the information for
@@ -35,118 +55,153 @@ pub struct MailNotifier {
     smtp_password: String,
 }
 
+impl MailNotifier {
+    /// Creates a mail notifier that logs in to `smtp_relay_host` as `from_address`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        all_recipients: Vec<String>,
+        success_recipients: Vec<String>,
+        failure_recipients: Vec<String>,
+        from_address: String,
+        smtp_relay_host: String,
+        smtp_port: u16,
+        lookup: String,
+        smtp_password: String,
+    ) -> Self {
+        Self {
+            all_recipients,
+            success_recipients,
+            failure_recipients,
+            from_address: from_address.clone(),
+            smtp_relay_host,
+            smtp_port: smtp_port.to_string(),
+            smtp_user: from_address,
+            lookup,
+            smtp_password,
+        }
+    }
+
+    /// The secrets the mail notifiers log in with, by their names
+    pub fn get_secrets(&self) -> BTreeMap<String, String> {
+        let mut secrets = BTreeMap::new();
+        secrets.insert(SMTP_PASSWORD_SECRET.to_string(), self.smtp_password.clone());
+        secrets
+    }
+
+    /// The Python statements that add a mail notifier to the master config
+    /// for every kind of recipient
+    pub fn statements(&self) -> Vec<Stmt> {
+        let mut stmts = vec![];
+        for (variable, mode, recipients, description) in &[
+            ("all", "all", &self.all_recipients, "all info"),
+            ("failures", "failing", &self.failure_recipients, "failures"),
+            (
+                "successes",
+                "passing",
+                &self.success_recipients,
+                "successes",
+            ),
+        ] {
+            if !stmts.is_empty() {
+                stmts.push(Stmt::Blank);
+            }
+            stmts.push(Stmt::comment(format!(
+                "The mail notifier responsible for {}",
+                description
+            )));
+            stmts.push(Stmt::assign(
+                *variable,
+                Call::new("reporters.MailNotifier")
+                    .kwarg("fromaddr", Expr::lit(&self.from_address))
+                    .kwarg("sendToInterestedUsers", Expr::lit(true))
+                    .kwarg("extraRecipients", Expr::lit(*recipients))
+                    .kwarg("lookup", Expr::lit(&self.lookup))
+                    .kwarg("relayhost", Expr::lit(&self.smtp_relay_host))
+                    .kwarg("smtpPort", Expr::raw(&self.smtp_port))
+                    .kwarg("smtpUser", Expr::lit(&self.smtp_user))
+                    .kwarg("buildSetSummary", Expr::lit(true))
+                    .kwarg("mode", Expr::lit(*mode))
+                    .kwarg("smtpPassword", secret(SMTP_PASSWORD_SECRET)),
+            ));
+            stmts.push(Stmt::expr(
+                config_entry("services").method("append").arg(*variable),
+            ));
+        }
+        stmts
+    }
+}
+
 impl Display for MailNotifier {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        writeln!(
-            f,
-            r#"
-
-# The mail notifier responsible for all info
-all = reporters.MailNotifier(fromaddr="{from_address}",
-                            sendToInterestedUsers=True,
-                            extraRecipients={all_recipients},
-                            lookup="{lookup}",
-                            relayhost="{relay_host}", smtpPort={port},
-                            smtpUser="{user}", buildSetSummary=True,
-                            # addLogs=True,
-                            mode="all",
-                            smtpPassword="{password}")
-c['services'].append(all)
-
-
-# The mail notifier responsible for failures
-failures = reporters.MailNotifier(fromaddr="{from_address}",
-                            sendToInterestedUsers=True,
-                            extraRecipients={failure_recipients},
-                            lookup="{lookup}",
-                            relayhost="{relay_host}", smtpPort={port},
-                            smtpUser="{user}", buildSetSummary=True,
-                            # addLogs=True,
-                            mode="failing",
-                            smtpPassword="{password}")
-c['services'].append(failures)
-
-
-
-# The mail notifier responsible for successes
-successes = reporters.MailNotifier(fromaddr="{from_address}",
-                            sendToInterestedUsers=True,
-                            extraRecipients={success_recipients},
-                            lookup="{lookup}",
-                            relayhost="{relay_host}", smtpPort={port},
-                            smtpUser="{user}", buildSetSummary=True,
-                            # addLogs=True,
-                            mode="passing",
-                            smtpPassword="{password}")
-c['services'].append(successes)
-
-
-"#,
-            all_recipients = format!("{:?}", self.all_recipients),
-            success_recipients = format!("{:?}", self.success_recipients),
-            failure_recipients = format!("{:?}", self.failure_recipients),
-            from_address = self.from_address,
-            relay_host = self.smtp_relay_host,
-            password = self.smtp_password,
-            user = self.smtp_user,
-            port = self.smtp_port,
-            lookup = self.lookup,
-        )
+        write!(f, "{}", render(&self.statements()))
     }
 }
 
-impl From<Yaml> for MailNotifier {
-    fn from(yaml: Yaml) -> Self {
+impl TryFrom<Yaml> for MailNotifier {
+    type Error = Vec<ConfigError>;
+
+    fn try_from(yaml: Yaml) -> Result<Self, Self::Error> {
+        let context = "the mail notifier";
+        let mut errors = vec![];
+
         // Verify that the yaml file doesnt have unmatched quotes!
         if let Some(line) = unmatched_quotes(&yaml) {
-            error!(
-                "There was a problem creating the mail notifier: unmatched quotes in the line '{}'",
-                line.trim()
-            );
-            exit(1);
+            errors.push(ConfigError::UnmatchedQuotes {
+                context: context.to_string(),
+                line: line.trim().to_string(),
+            });
+            return Err(errors);
         }
 
-        // Confirm that the merge request handler has the required sections
-        for section in [
-            "extra-recipients",
-            "from-address",
-            "smtp-relay-host",
-            "smtp-port",
-            "lookup",
-            "smtp-password",
-        ]
-        .iter()
-        {
-            if !yaml.has_section(section) {
-                error!(
-                    "There was a problem creating the mail notifier: '{}' section not specified.",
-                    section
-                );
-                exit(1);
-            }
+        // Confirm that the mail notifier has the required sections
+        errors.extend(require_sections(
+            &yaml,
+            &[
+                "extra-recipients",
+                "from-address",
+                "smtp-relay-host",
+                "smtp-port",
+                "lookup",
+                "smtp-password",
+            ],
+            context,
+        ));
+        errors.extend(unknown_yaml_sections(&yaml, MAIL_SECTIONS, context));
+
+        if yaml.has_section("extra-recipients") {
+            let recipients = yaml.get_section("extra-recipients").unwrap();
+            let context = "the 'extra-recipients' subsection of the mail notifier";
+            errors.extend(require_sections(
+                &recipients,
+                &["all", "failure", "success"],
+                context,
+            ));
+            errors.extend(unknown_yaml_sections(
+                &recipients,
+                RECIPIENT_SECTIONS,
+                context,
+            ));
+        }
+
+        if !errors.is_empty() {
+            return Err(explain_typos(errors));
         }
 
         let extra_recipients = yaml.get_section("extra-recipients").unwrap();
 
-        for section in ["all", "failure", "success"].iter() {
-            if !extra_recipients.has_section(section) {
-                error!("There was a problem creating the mail notifier: '{}' section not specified in the 'extra-recipients' subsection.", section);
-                exit(1);
-            }
-        }
         let mut all_recipients = vec![];
         for recipient in extra_recipients.get_section("all").unwrap() {
-            all_recipients.push(recipient.to_string());
+            all_recipients.push(scalar(&recipient));
         }
 
         let mut success_recipients = vec![];
         for recipient in extra_recipients.get_section("success").unwrap() {
-            success_recipients.push(recipient.to_string());
+            success_recipients.push(scalar(&recipient));
         }
 
         let mut failure_recipients = vec![];
         for recipient in extra_recipients.get_section("failure").unwrap() {
-            failure_recipients.push(recipient.to_string());
+            failure_recipients.push(scalar(&recipient));
         }
 
         let from_address = unwrap(&yaml, "from-address");
@@ -155,16 +210,28 @@ impl From<Yaml> for MailNotifier {
         let smtp_password = unwrap(&yaml, "smtp-password");
         let lookup = unwrap(&yaml, "lookup");
 
-        Self {
+        // The port is written into the python as a number, so it had better be one
+        let smtp_port = match smtp_port.parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => {
+                return Err(vec![ConfigError::BadValue {
+                    context: context.to_string(),
+                    section: String::from("smtp-port"),
+                    value: smtp_port,
+                    reason: String::from("the port must be a number between 0 and 65535"),
+                }])
+            }
+        };
+
+        Ok(Self::new(
             all_recipients,
             success_recipients,
             failure_recipients,
-            from_address: from_address.clone(),
+            from_address,
             smtp_relay_host,
             smtp_port,
-            smtp_user: from_address,
             lookup,
             smtp_password,
-        }
+        ))
     }
 }
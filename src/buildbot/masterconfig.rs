@@ -1,10 +1,39 @@
 use crate::{
-    unmatched_quotes, unwrap, Builder, MailNotifier, MergeRequestHandler, Scheduler, Worker,
+    unmatched_quotes, unwrap, Builder, MailNotifier, MergeRequestHandler, Scheduler, State,
+    VersionControlSystem, Worker,
 };
 
+use super::error::{explain_typos, missing, require_sections, unknown_yaml_sections};
+use super::worker::DEFAULT_WORKER_PORT;
+use crate::helper::python::identifier;
+use crate::helper::python_ast::{render, Call, Expr, Stmt};
+use crate::ConfigError;
 use rusty_yaml::Yaml;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::fmt::{Display, Error, Formatter};
-use std::process::exit;
+use std::path::Path;
+
+/// Every section at the top of the master file. Anything else is reported as a typo.
+pub const MASTER_FILE_SECTIONS: &[&str] = &[
+    "requires",
+    "master",
+    "merge-request-handler",
+    "workers",
+    "builders",
+    "schedulers",
+];
+
+/// Every section of the `master` section of the master file
+pub const MASTER_SECTIONS: &[&str] = &[
+    "title",
+    "title-url",
+    "repo",
+    "webserver-ip",
+    "webserver-port",
+    "poll-interval",
+    "worker-port",
+];
 
 /// This struct represents the configuration file for the master.
 /// This file contains the Python code for the builders and the schedulers.
@@ -18,8 +47,11 @@ pub struct MasterConfig {
     title_url: String,
     git_repo: String,
     webserver_ip: String,
-    webserver_port: String,
-    poll_interval: String,
+    webserver_port: u16,
+    /// How many seconds to wait between polling the repository
+    poll_interval: u64,
+    /// The port the master listens on for workers
+    worker_port: u16,
     mail_notifier: Option<MailNotifier>,
     merge_request_handler: MergeRequestHandler,
     builders: Vec<Builder>,
@@ -29,6 +61,12 @@ pub struct MasterConfig {
 
 /// This is impl the for MasterConfig struct.
 impl MasterConfig {
+    /// Starts building a master config in Rust, instead of reading it from YAML.
+    /// Add the workers, builders, and schedulers, and call `build` to check them.
+    pub fn builder() -> MasterConfigBuilder {
+        MasterConfigBuilder::default()
+    }
+
     pub fn set_mail_notifier(&mut self, mail_notifier: MailNotifier) {
         self.mail_notifier = Some(mail_notifier);
     }
@@ -36,101 +74,582 @@ impl MasterConfig {
     pub fn get_workers(&self) -> Vec<Worker> {
         self.workers.clone()
     }
+
+    /// Gives each worker its password from the state, so that the passwords
+    /// stay the same between builds. Workers that don't have a saved password
+    /// yet keep the one they generated, and it is saved to the state.
+    pub fn sync_passwords(&mut self, state: &mut State) {
+        for worker in self.workers.iter_mut() {
+            match state.get_password(&worker.get_name()) {
+                Some(password) => worker.set_password(password),
+                None => state.set_password(&worker.get_name(), &worker.get_password()),
+            }
+        }
+    }
+
+    pub fn get_builders(&self) -> &[Builder] {
+        &self.builders
+    }
+
+    pub fn get_schedulers(&self) -> &[Scheduler] {
+        &self.schedulers
+    }
+
+    /// The tokens and passwords the master needs, by the names `master.cfg` reads them with.
+    /// These are written to files in the secrets directory instead of `master.cfg`.
+    pub fn get_secrets(&self) -> BTreeMap<String, String> {
+        let mut secrets = self.merge_request_handler.get_secrets();
+        if let Some(mail_notifier) = &self.mail_notifier {
+            secrets.extend(mail_notifier.get_secrets());
+        }
+        secrets
+    }
+
+    /// The Python statements of the `master.cfg` file, built from each of its sections
+    pub fn statements(&self) -> Vec<Stmt> {
+        let mut stmts = self.header();
+        stmts.extend(self.secret_statements());
+        stmts.extend(self.worker_statements());
+        stmts.extend(self.www_statements());
+        stmts.push(Stmt::Blank);
+
+        if let Some(mail_notifier) = &self.mail_notifier {
+            stmts.extend(mail_notifier.statements());
+            stmts.push(Stmt::Blank);
+        }
+
+        stmts.extend(self.merge_request_handler.statements());
+        stmts.push(Stmt::Blank);
+        stmts.extend(self.change_source_statements());
+        stmts.push(Stmt::Blank);
+
+        stmts.push(Stmt::assign(config_entry("schedulers"), Expr::list(vec![])));
+        stmts.push(Stmt::assign(config_entry("builders"), Expr::list(vec![])));
+        for scheduler in &self.schedulers {
+            stmts.push(Stmt::Blank);
+            stmts.extend(scheduler.statements());
+        }
+        for builder in &self.builders {
+            stmts.push(Stmt::Blank);
+            stmts.extend(builder.statements());
+        }
+        stmts.push(Stmt::Blank);
+
+        stmts.extend(self.project_statements());
+        stmts
+    }
+
+    /// The imports and the config dictionary at the top of the file
+    fn header(&self) -> Vec<Stmt> {
+        vec![
+            Stmt::comment("-*- python -*-\nex: set filetype=python:"),
+            Stmt::raw("import os"),
+            Stmt::raw("import re"),
+            Stmt::raw("import json"),
+            Stmt::raw("import requests as req"),
+            Stmt::raw("from dateutil.parser import parse as dateparse"),
+            Stmt::raw("from buildbot.plugins import *"),
+            Stmt::raw("from buildbot.www.hooks.github import GitHubEventHandler"),
+            Stmt::Blank,
+            Stmt::comment(
+                "This is a sample buildmaster config file. It must be installed as\n\
+                 'master.cfg' in your buildmaster's base directory.",
+            ),
+            Stmt::Blank,
+            Stmt::comment(
+                "This is the dictionary that the buildmaster pays attention to. We also use\n\
+                 a shorter alias to save typing.",
+            ),
+            Stmt::raw("c = BuildmasterConfig = {}"),
+            Stmt::Blank,
+        ]
+    }
+
+    /// Tells buildbot where to find the secrets that `util.Secret` reads,
+    /// if the master needs any
+    fn secret_statements(&self) -> Vec<Stmt> {
+        if self.get_secrets().is_empty() {
+            return vec![];
+        }
+        vec![
+            Stmt::raw("####### SECRETS"),
+            Stmt::Blank,
+            Stmt::comment(
+                "Tokens and passwords are kept in a file each in the secrets directory,\n\
+                 so that they are never written in this file.",
+            ),
+            Stmt::assign(
+                config_entry("secretsProviders"),
+                Expr::list(vec![Call::new("secrets.SecretInAFile")
+                    .kwarg(
+                        "dirname",
+                        Call::new("os.path.join")
+                            .arg("basedir")
+                            .arg(Expr::lit("secrets")),
+                    )
+                    .into()]),
+            ),
+            Stmt::Blank,
+        ]
+    }
+
+    /// The workers the master accepts, and the port they connect to
+    fn worker_statements(&self) -> Vec<Stmt> {
+        vec![
+            Stmt::raw("####### WORKERS"),
+            Stmt::Blank,
+            Stmt::comment(
+                "The 'workers' list defines the set of recognized workers. Each element is\n\
+                 a Worker object, specifying a unique worker name and password.  The same\n\
+                 worker name and password must be configured on the worker.",
+            ),
+            Stmt::assign(
+                config_entry("workers"),
+                Expr::list(self.workers.iter().map(Worker::to_expr)),
+            ),
+            Stmt::assign(
+                config_entry("protocols"),
+                Expr::Dict(vec![(
+                    Expr::lit("pb"),
+                    Expr::Dict(vec![(Expr::lit("port"), Expr::lit(self.worker_port))]),
+                )]),
+            ),
+            Stmt::Blank,
+        ]
+    }
+
+    /// The web gui, and the lists the other sections add to
+    fn www_statements(&self) -> Vec<Stmt> {
+        vec![
+            Stmt::assign(
+                config_entry("www"),
+                Call::new("dict")
+                    .kwarg("port", Expr::lit(self.webserver_port))
+                    .kwarg(
+                        "plugins",
+                        Call::new("dict")
+                            .kwarg("waterfall_view", Expr::Dict(vec![]))
+                            .kwarg("console_view", Expr::Dict(vec![]))
+                            .kwarg("grid_view", Expr::Dict(vec![])),
+                    ),
+            ),
+            Stmt::Blank,
+            Stmt::assign(config_entry("change_source"), Expr::list(vec![])),
+            Stmt::assign(config_entry("services"), Expr::list(vec![])),
+        ]
+    }
+
+    /// Polls every branch of the repository for new commits
+    fn change_source_statements(&self) -> Vec<Stmt> {
+        vec![Stmt::expr(
+            config_entry("change_source").method("append").arg(
+                Call::new("changes.GitPoller")
+                    .arg(Expr::lit(&self.git_repo))
+                    .kwarg("workdir", Expr::lit("gitpoller-workdir"))
+                    .kwarg("branches", Expr::lit(true))
+                    .kwarg("pollInterval", Expr::lit(self.poll_interval)),
+            ),
+        )]
+    }
+
+    /// The title and url of the web gui, and the database buildbot keeps its state in
+    fn project_statements(&self) -> Vec<Stmt> {
+        vec![
+            Stmt::assign(config_entry("title"), Expr::lit(&self.title)),
+            Stmt::assign(config_entry("titleURL"), Expr::lit(&self.title_url)),
+            Stmt::Blank,
+            Stmt::assign(
+                config_entry("buildbotURL"),
+                Expr::lit(format!(
+                    "http://{}:{}/",
+                    self.webserver_ip, self.webserver_port
+                )),
+            ),
+            Stmt::Blank,
+            Stmt::assign(
+                config_entry("db"),
+                Expr::Dict(vec![(
+                    Expr::lit("db_url"),
+                    Expr::lit("sqlite:///state.sqlite"),
+                )]),
+            ),
+        ]
+    }
+}
+
+/// `c[key]`, an entry in the dictionary that the buildmaster reads its config from
+pub(crate) fn config_entry(key: &str) -> Expr {
+    Expr::name("c").index(Expr::lit(key))
+}
+
+/// `util.Secret(name)`, which buildbot replaces with the secret named `name` when it's used
+pub(crate) fn secret(name: &str) -> Expr {
+    Call::new("util.Secret").arg(Expr::lit(name)).into()
+}
+
+/// Collects the names in `names` that appear more than once
+/// into `DuplicateName` errors for the given kind of object.
+fn duplicate_names(kind: &str, names: Vec<String>) -> Vec<ConfigError> {
+    let mut seen: Vec<String> = vec![];
+    let mut errors = vec![];
+    for name in names {
+        if seen.contains(&name) {
+            let error = ConfigError::DuplicateName {
+                kind: kind.to_string(),
+                name,
+            };
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
+        } else {
+            seen.push(name);
+        }
+    }
+    errors
+}
+
+/// This builds a MasterConfig with the same sections the YAML uses.
+/// Every mistake is collected and returned from `build`, like `MasterConfig::try_from` does.
+#[derive(Default)]
+pub struct MasterConfigBuilder {
+    title: Option<String>,
+    title_url: Option<String>,
+    git_repo: Option<String>,
+    webserver_ip: Option<String>,
+    webserver_port: Option<u16>,
+    poll_interval: Option<u64>,
+    worker_port: Option<u16>,
+    mail_notifier: Option<MailNotifier>,
+    merge_request_handler: Option<MergeRequestHandler>,
+    builders: Vec<Builder>,
+    schedulers: Vec<Scheduler>,
+    workers: Vec<Worker>,
+}
+
+impl MasterConfigBuilder {
+    /// The title of the web gui
+    pub fn title<S: ToString>(mut self, title: S) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// The url the title of the web gui links to
+    pub fn title_url<S: ToString>(mut self, url: S) -> Self {
+        self.title_url = Some(url.to_string());
+        self
+    }
+
+    /// The url of the repository to poll for new commits
+    pub fn repo<S: ToString>(mut self, url: S) -> Self {
+        self.git_repo = Some(url.to_string());
+        self
+    }
+
+    /// The address the web gui is served from
+    pub fn webserver_ip<S: ToString>(mut self, ip: S) -> Self {
+        self.webserver_ip = Some(ip.to_string());
+        self
+    }
+
+    /// The port the web gui is served on
+    pub fn webserver_port(mut self, port: u16) -> Self {
+        self.webserver_port = Some(port);
+        self
+    }
+
+    /// How many seconds to wait between polling the repository
+    pub fn poll_interval(mut self, seconds: u64) -> Self {
+        self.poll_interval = Some(seconds);
+        self
+    }
+
+    /// The port the master listens on for workers,
+    /// which is `DEFAULT_WORKER_PORT` unless this is set
+    pub fn worker_port(mut self, port: u16) -> Self {
+        self.worker_port = Some(port);
+        self
+    }
+
+    pub fn mail_notifier(mut self, mail_notifier: MailNotifier) -> Self {
+        self.mail_notifier = Some(mail_notifier);
+        self
+    }
+
+    /// How to handle merge requests.
+    /// Without one, merge requests aren't built and the repository is cloned with plain git.
+    pub fn merge_request_handler(mut self, handler: MergeRequestHandler) -> Self {
+        self.merge_request_handler = Some(handler);
+        self
+    }
+
+    pub fn worker(mut self, worker: Worker) -> Self {
+        self.workers.push(worker);
+        self
+    }
+
+    pub fn builder(mut self, builder: Builder) -> Self {
+        self.builders.push(builder);
+        self
+    }
+
+    pub fn scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.schedulers.push(scheduler);
+        self
+    }
+
+    /// Builds the master config, or returns everything wrong with it
+    pub fn build(self) -> Result<MasterConfig, Vec<ConfigError>> {
+        let mut errors = vec![];
+        // Name the sections the way the YAML does, so the errors read the same
+        let sections = [
+            ("title", self.title.is_none()),
+            ("title-url", self.title_url.is_none()),
+            ("repo", self.git_repo.is_none()),
+            ("webserver-ip", self.webserver_ip.is_none()),
+            ("webserver-port", self.webserver_port.is_none()),
+            ("poll-interval", self.poll_interval.is_none()),
+        ];
+        for (section, is_missing) in sections.iter() {
+            if *is_missing {
+                errors.push(missing("master", section));
+            }
+        }
+
+        // Schedulers become Python variables with their dashes replaced,
+        // so `a-b` and `a_b` would clobber each other.
+        errors.extend(duplicate_names(
+            "scheduler",
+            self.schedulers
+                .iter()
+                .map(|s| identifier(&s.get_name()))
+                .collect(),
+        ));
+        errors.extend(duplicate_names(
+            "builder",
+            self.builders.iter().map(Builder::get_name).collect(),
+        ));
+        errors.extend(duplicate_names(
+            "worker",
+            self.workers.iter().map(Worker::get_name).collect(),
+        ));
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Plain git never needs an authentication token, so this can't fail
+        let merge_request_handler = match self.merge_request_handler {
+            Some(handler) => handler,
+            None => MergeRequestHandler::new(
+                VersionControlSystem::Unsupported,
+                String::new(),
+                String::new(),
+                vec![],
+            )
+            .map_err(|e| vec![e])?,
+        };
+
+        // Workers connect to whichever port the master listens on
+        let worker_port = self.worker_port.unwrap_or(DEFAULT_WORKER_PORT);
+        let mut workers = self.workers;
+        for worker in workers.iter_mut() {
+            worker.set_master_port(worker_port);
+        }
+
+        // Builders clone the repository with the merge request handler's VCS
+        // unless they chose their own
+        let mut builders = self.builders;
+        for builder in builders.iter_mut() {
+            builder.set_default_vcs(merge_request_handler.get_vcs());
+        }
+
+        // A scheduler that triggers a builder with a matrix triggers every combination of it
+        let mut schedulers = self.schedulers;
+        for scheduler in schedulers.iter_mut() {
+            scheduler.expand_matrices(&builders);
+        }
+
+        Ok(MasterConfig {
+            title: self.title.unwrap_or_default(),
+            title_url: self.title_url.unwrap_or_default(),
+            git_repo: self.git_repo.unwrap_or_default(),
+            webserver_ip: self.webserver_ip.unwrap_or_default(),
+            webserver_port: self.webserver_port.unwrap_or_default(),
+            poll_interval: self.poll_interval.unwrap_or_default(),
+            worker_port,
+            mail_notifier: self.mail_notifier,
+            merge_request_handler,
+            builders,
+            schedulers,
+            workers,
+        })
+    }
 }
 
 /// This impl converts a Yaml file into a MasterConfig object.
 /// This is intended to take the entire input yaml file.
-impl From<Yaml> for MasterConfig {
-    fn from(yaml: Yaml) -> Self {
+///
+/// Every error in the workers, builders, schedulers and merge request handler
+/// is collected, so the returned list describes everything wrong with the file.
+///
+/// A Yaml doesn't know which file it came from, so the tokens are read
+/// relative to the current directory. `MasterConfig::from_yaml` reads them next to the file.
+impl TryFrom<Yaml> for MasterConfig {
+    type Error = Vec<ConfigError>;
+
+    fn try_from(yaml: Yaml) -> Result<Self, Self::Error> {
+        Self::from_yaml(yaml, Path::new(""))
+    }
+}
+
+impl MasterConfig {
+    /// Converts a YAML master file in the directory `dir`,
+    /// reading the merge request handler's tokens relative to `dir`
+    pub fn from_yaml(yaml: Yaml, dir: &Path) -> Result<Self, Vec<ConfigError>> {
         // Verify that the yaml file doesnt have unmatched quotes!
         if let Some(line) = unmatched_quotes(&yaml) {
-            error!("There was a problem creating the master configuration file: unmatched quotes in the line '{}'", line.trim());
-            exit(1);
+            return Err(vec![ConfigError::UnmatchedQuotes {
+                context: String::from("the master configuration"),
+                line: line.trim().to_string(),
+            }]);
         }
 
         // Verify that the yaml section contains all the necessary subsections
-        for section in [
-            "master",
-            "workers",
-            "builders",
-            "schedulers",
-            "merge-request-handler",
-        ]
-        .iter()
-        {
-            if !yaml.has_section(section) {
-                error!("There was an error creating the master configuration file: '{}' section was not declared", section);
-                exit(1);
-            }
+        let mut errors = require_sections(
+            &yaml,
+            &[
+                "master",
+                "workers",
+                "builders",
+                "schedulers",
+                "merge-request-handler",
+            ],
+            "the master configuration",
+        );
+        errors.extend(unknown_yaml_sections(
+            &yaml,
+            MASTER_FILE_SECTIONS,
+            "the master configuration",
+        ));
+
+        // Verify the master subsection contains all the proper data
+        if yaml.has_section("master") {
+            let master = yaml.get_section("master").unwrap();
+            errors.extend(require_sections(
+                &master,
+                &[
+                    "title",
+                    "title-url",
+                    "repo",
+                    "webserver-ip",
+                    "webserver-port",
+                    "poll-interval",
+                ],
+                "master",
+            ));
+            errors.extend(unknown_yaml_sections(&master, MASTER_SECTIONS, "master"));
+        }
+
+        if !errors.is_empty() {
+            return Err(explain_typos(errors));
         }
 
         // Get the master susbsection, the subsection holding the web gui and git information
         let master = yaml.get_section("master").unwrap();
-
-        // Verify the master subsection contains all the proper data
-        for section in [
-            "title",
-            "title-url",
-            "repo",
-            "webserver-ip",
-            "webserver-port",
-            "poll-interval",
-        ]
-        .iter()
-        {
-            if !master.has_section(section) {
-                error!("There was an error creating the master configuration file: The '{}' section is not specified for master", section);
-                exit(1);
-            }
+        let mut config = MasterConfig::builder()
+            .title(unwrap(&master, "title"))
+            .title_url(unwrap(&master, "title-url"))
+            .repo(unwrap(&master, "repo"))
+            .webserver_ip(unwrap(&master, "webserver-ip"));
+
+        match MergeRequestHandler::from_yaml(
+            yaml.get_section("merge-request-handler").unwrap(),
+            dir,
+        ) {
+            Ok(handler) => config = config.merge_request_handler(handler),
+            Err(e) => errors.extend(e),
         }
 
-        let merge_request_handler =
-            MergeRequestHandler::from(yaml.get_section("merge-request-handler").unwrap());
-
         // Get schedulers, builders, and workers from the yaml file.
         // Because we previously verified that each subsection exists,
         // we can unwrap the result without a problem.
-        let mut schedulers = vec![];
         for scheduler in yaml.get_section("schedulers").unwrap() {
-            schedulers.push(Scheduler::from(scheduler));
+            match Scheduler::try_from(scheduler) {
+                Ok(scheduler) => config = config.scheduler(scheduler),
+                Err(e) => errors.extend(e),
+            }
         }
 
-        // Because we previously verified that each subsection exists,
-        // we can unwrap the result without a problem.
-        let mut builders = vec![];
         for builder in yaml.get_section("builders").unwrap() {
-            builders.push(Builder::from(builder));
+            match Builder::expand(builder) {
+                Ok(builders) => {
+                    for builder in builders {
+                        config = config.builder(builder);
+                    }
+                }
+                Err(e) => errors.extend(e),
+            }
         }
 
-        // Because we previously verified that each subsection exists,
-        // we can unwrap the result without a problem.
-        let mut workers = vec![];
         for worker in yaml.get_section("workers").unwrap() {
-            workers.push(Worker::from(worker));
+            match Worker::try_from(worker) {
+                Ok(worker) => config = config.worker(worker),
+                Err(e) => errors.extend(e),
+            }
         }
 
-        // Get all the data from the master subsection
-        let title = unwrap(&master, "title");
-        let title_url = unwrap(&master, "title-url");
-        let git_repo = unwrap(&master, "repo");
-        let webserver_ip = unwrap(&master, "webserver-ip");
-        let webserver_port = unwrap(&master, "webserver-port");
-        let poll_interval = unwrap(&master, "poll-interval");
+        // These are written into the python as numbers, so they had better be numbers.
+        // A bad number is still given to the builder so it doesn't report it missing,
+        // but the error we push here stops the config from being returned.
+        if master.has_section("worker-port") {
+            let port = unwrap(&master, "worker-port");
+            match port.parse::<u16>() {
+                Ok(port) => config = config.worker_port(port),
+                Err(_) => errors.push(ConfigError::BadValue {
+                    context: String::from("master"),
+                    section: String::from("worker-port"),
+                    value: port,
+                    reason: String::from("the port must be a number between 0 and 65535"),
+                }),
+            }
+        }
 
-        // Return the whole master configuration file
+        let webserver_port = unwrap(&master, "webserver-port");
+        config = config.webserver_port(match webserver_port.parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => {
+                errors.push(ConfigError::BadValue {
+                    context: String::from("master"),
+                    section: String::from("webserver-port"),
+                    value: webserver_port,
+                    reason: String::from("the port must be a number between 0 and 65535"),
+                });
+                0
+            }
+        });
 
-        Self {
-            title,
-            title_url,
-            git_repo,
-            webserver_ip,
-            webserver_port,
-            poll_interval,
-            mail_notifier: None,
-            merge_request_handler,
-            builders,
-            schedulers,
-            workers,
+        let poll_interval = unwrap(&master, "poll-interval");
+        config = config.poll_interval(match poll_interval.parse::<u64>() {
+            Ok(seconds) => seconds,
+            Err(_) => {
+                errors.push(ConfigError::BadValue {
+                    context: String::from("master"),
+                    section: String::from("poll-interval"),
+                    value: poll_interval,
+                    reason: String::from("the poll interval must be a whole number of seconds"),
+                });
+                0
+            }
+        });
+
+        // The builder reports the duplicate names
+        match config.build() {
+            Ok(config) if errors.is_empty() => Ok(config),
+            Ok(_) => Err(errors),
+            Err(e) => {
+                errors.extend(e);
+                Err(errors)
+            }
         }
     }
 }
@@ -138,102 +657,6 @@ impl From<Yaml> for MasterConfig {
 /// Converts a MasterConfig instance into the Python master configuration file for buildbot
 impl Display for MasterConfig {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(
-            f,
-            r#"
-# -*- python -*-
-# ex: set filetype=python:
-import re
-import json
-import requests as req
-from dateutil.parser import parse as dateparse
-from buildbot.plugins import *
-from buildbot.www.hooks.github import GitHubEventHandler
-
-# This is a sample buildmaster config file. It must be installed as
-# 'master.cfg' in your buildmaster's base directory.
-
-# This is the dictionary that the buildmaster pays attention to. We also use
-# a shorter alias to save typing.
-c = BuildmasterConfig = {{}}
-
-####### WORKERS
-
-# The 'workers' list defines the set of recognized workers. Each element is
-# a Worker object, specifying a unique worker name and password.  The same
-# worker name and password must be configured on the worker.
-c['workers'] = [{worker_info}]
-c['protocols'] = {{'pb': {{'port': 9989}}}}
-
-
-c['www'] = dict(port={webserver_port},
-                plugins=dict(waterfall_view={{}}, console_view={{}}, grid_view={{}}))
-
-c['change_source'] = []
-c['services'] = []
-
-{mail_notifier}
-
-{merge_request_handler}
-
-c['change_source'].append(changes.GitPoller(
-        "{git_repo}",
-        workdir='gitpoller-workdir', branches=True, # poll all branches
-        pollInterval={poll_interval}))
-
-c['schedulers'] = []
-c['builders'] = []
-
-{schedulers}
-{builders}
-
-
-c['title'] = "{title}"
-c['titleURL'] = "{title_url}"
-
-c['buildbotURL'] = "http://{webserver_ip}:{webserver_port}/"
-
-c['db'] = {{
-    # This specifies what database buildbot uses to store its state.  You can leave
-    # this at its default for all but the largest installations.
-    'db_url' : "sqlite:///state.sqlite",
-}}
-        "#,
-            title = self.title,
-            title_url = self.title_url,
-            webserver_ip = self.webserver_ip,
-            webserver_port = self.webserver_port,
-            git_repo = self.git_repo,
-            merge_request_handler = self.merge_request_handler,
-            mail_notifier = match &self.mail_notifier {
-                Some(mn) => mn.to_string(),
-                None => "".to_string(),
-            },
-            worker_info = self
-                .workers
-                .iter()
-                .map(|w| {
-                    format!(
-                        "worker.Worker(\"{}\", \"{}\")",
-                        w.get_name(),
-                        w.get_password()
-                    )
-                })
-                .collect::<Vec<String>>()
-                .join(", "),
-            poll_interval = self.poll_interval,
-            schedulers = self
-                .schedulers
-                .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>()
-                .join("\n\n"),
-            builders = self
-                .builders
-                .iter()
-                .map(|b| b.to_string())
-                .collect::<Vec<String>>()
-                .join("\n\n"),
-        )
+        write!(f, "{}", render(&self.statements()))
     }
 }
@@ -1,22 +1,209 @@
-use crate::{unwrap, File};
+use super::error::{explain_typos, require_sections, unknown_yaml_sections};
+use super::masterconfig::{config_entry, secret};
+use crate::config::interpolate;
+use crate::helper::python_ast::{render, Call, Expr, Stmt};
+use crate::{scalar, unwrap, ConfigError, File};
 
 use rusty_yaml::Yaml;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::env;
 use std::fmt::{Display, Error, Formatter};
-use std::process::exit;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 /// A version control system is a system that allows programmers to manage
 /// changes on a product in development. A few examples include, but are not limited to,
 /// `GitHub`, `GitLab`, `Mercurial`.
+///
+/// `Unsupported` is used for repositories that are only accessed with plain git.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum VersionControlSystem {
     GitHub,
     GitLab,
     Unsupported,
 }
 
+/// Parses the name of a VCS as written in the YAML,
+/// either `github`, `gitlab`, or `none` for plain git.
+impl FromStr for VersionControlSystem {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(VersionControlSystem::GitHub),
+            "gitlab" => Ok(VersionControlSystem::GitLab),
+            "none" => Ok(VersionControlSystem::Unsupported),
+            other => Err(ConfigError::UnknownVcs(other.to_string())),
+        }
+    }
+}
+
 /// This is the path to the file containing the auth / api token
-/// for the version control system
+/// for the version control system, if the merge request handler doesn't say where it is
 pub const AUTH_TOKEN_PATH: &str = "auth.token";
 
+/// The name of the secret the token for polling the VCS is kept in,
+/// so it isn't written in `master.cfg`
+pub const POLL_TOKEN_SECRET: &str = "vcs-poll-token";
+
+/// The name of the secret the token for pushing build statuses to the VCS is kept in
+pub const STATUS_TOKEN_SECRET: &str = "vcs-status-token";
+
+/// Every section the merge request handler can have. Anything else is reported as a typo.
+pub const MERGE_REQUEST_HANDLER_SECTIONS: &[&str] = &[
+    "version-control-system",
+    "owner",
+    "repo-name",
+    "whitelist",
+    "token-file",
+    "token-env",
+    "poll-token-file",
+    "poll-token-env",
+    "status-token-file",
+    "status-token-env",
+];
+
+/// Where a token for the VCS is read from
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenSource {
+    /// A file holding the token. The file can hold a reference
+    /// like `${GITHUB_TOKEN}` instead of the token itself.
+    File(PathBuf),
+    /// An environment variable holding the token
+    Env(String),
+}
+
+/// The path of the file, or `$NAME` for an environment variable
+impl Display for TokenSource {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            TokenSource::File(path) => write!(f, "{}", path.display()),
+            TokenSource::Env(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+impl TokenSource {
+    /// The `auth.token` file in `dir`, which should be the directory of the config file
+    pub fn auth_token_in<P: AsRef<Path>>(dir: P) -> Self {
+        TokenSource::File(dir.as_ref().join(AUTH_TOKEN_PATH))
+    }
+
+    /// Reads the token, without the whitespace around it.
+    /// A token that can't be read, or is empty, is a `MissingAuthToken` error.
+    pub fn read(&self) -> Result<String, ConfigError> {
+        let missing = |reason: String| ConfigError::MissingAuthToken {
+            path: self.to_string(),
+            reason,
+        };
+
+        let token = match self {
+            TokenSource::File(path) => {
+                let contents = File::read(path).map_err(missing)?;
+                // The file can hold a reference like `${GITHUB_TOKEN}` instead of the token itself
+                let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                match interpolate(contents.trim(), dir) {
+                    Ok(token) => token,
+                    Err(unresolved) => {
+                        let reasons: Vec<String> = unresolved
+                            .iter()
+                            .map(|(reference, reason)| {
+                                format!("could not resolve '{}', {}", reference, reason)
+                            })
+                            .collect();
+                        return Err(missing(reasons.join("; ")));
+                    }
+                }
+            }
+            TokenSource::Env(name) => match env::var(name) {
+                Ok(token) => token,
+                Err(env::VarError::NotPresent) => {
+                    return Err(missing(String::from("the environment variable isn't set")))
+                }
+                Err(env::VarError::NotUnicode(_)) => {
+                    return Err(missing(String::from(
+                        "the environment variable isn't valid unicode",
+                    )))
+                }
+            },
+        };
+
+        match token.trim() {
+            "" => Err(missing(match self {
+                TokenSource::File(_) => {
+                    String::from("you didn't write your VCS's authentication token to it")
+                }
+                TokenSource::Env(_) => String::from("the environment variable is empty"),
+            })),
+            token => Ok(token.to_string()),
+        }
+    }
+}
+
+/// Where the merge request handler reads its tokens from: one to poll the VCS
+/// for merge requests, and one to push the status of each build back to it.
+/// These can be the same, and by default they are both `auth.token`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tokens {
+    pub poll: TokenSource,
+    pub status: TokenSource,
+}
+
+impl Tokens {
+    /// Reads both tokens from the `auth.token` file in `dir`
+    pub fn in_dir<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            poll: TokenSource::auth_token_in(&dir),
+            status: TokenSource::auth_token_in(&dir),
+        }
+    }
+
+    /// Picks the tokens from the `token-file` and `token-env` sections, which set both,
+    /// and the `poll-` and `status-` versions of them, which set one each.
+    /// `section` gets the value of a section, if it's there.
+    /// Without any of them, the tokens are read from `auth.token`.
+    ///
+    /// `dir` is the directory of the config file, and relative files are read from it.
+    pub(crate) fn from_sections<F: Fn(&str) -> Option<String>>(
+        section: F,
+        dir: &Path,
+        context: &str,
+    ) -> Result<Self, Vec<ConfigError>> {
+        let mut errors = vec![];
+        let mut source = |prefix: &str| {
+            let file = section(&format!("{}token-file", prefix));
+            let env = section(&format!("{}token-env", prefix));
+            match (file, env) {
+                (Some(file), None) => Some(TokenSource::File(dir.join(file))),
+                (None, Some(name)) => Some(TokenSource::Env(name)),
+                (Some(_), Some(name)) => {
+                    errors.push(ConfigError::BadValue {
+                        context: context.to_string(),
+                        section: format!("{}token-env", prefix),
+                        value: name,
+                        reason: format!(
+                            "`{}token-file` is set too, so the token would come from two places",
+                            prefix
+                        ),
+                    });
+                    None
+                }
+                (None, None) => None,
+            }
+        };
+
+        let both = source("").unwrap_or_else(|| TokenSource::auth_token_in(dir));
+        let poll = source("poll-").unwrap_or_else(|| both.clone());
+        let status = source("status-").unwrap_or(both);
+        if errors.is_empty() {
+            Ok(Self { poll, status })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
 /// The purpose of a continuous integration tool is to continuously confirm the
 /// validity and robustness of code. It follows then that you must check code BEFORE
 /// it is deployed. To do this, you must take the code that someone wants to merge into
@@ -29,6 +216,8 @@ pub struct MergeRequestHandler {
     /// VCS in the Python, instead of abstracting it in the Rust.
     /// The VCS, currently, must be one of:
     /// - github
+    /// - gitlab
+    /// - none, if you don't want to build merge requests
     vcs: VersionControlSystem,
     /// The username of the owner of the repository
     owner: String,
@@ -45,177 +234,339 @@ pub struct MergeRequestHandler {
     /// is `im_not_in_the_whitelist` then his code will not be run on our machines until his
     /// username is added to the whitelist, or a whitelisted user grants permission to test.
     whitelist: Vec<String>,
-    /// This is the authentication token for the VCS for write access to the repository
-    auth_token: String,
+    /// The token the master polls the VCS for merge requests with
+    poll_token: String,
+    /// The token the master pushes the status of each build to the VCS with.
+    /// This needs write access to the repository.
+    status_token: String,
     /// This field is not to be changed by the user because if youre using something other
     /// than git, youre doing it wrong :)
     repository_type: String,
 }
 
 impl MergeRequestHandler {
+    /// Creates a merge request handler that reads its tokens from `auth.token`
+    /// in the current directory
     pub fn new(
         vcs: VersionControlSystem,
         owner: String,
         repo_name: String,
         whitelist: Vec<String>,
-    ) -> Self {
-        let auth_token = match File::read(AUTH_TOKEN_PATH) {
-            Ok(s) => s.trim().to_string(),
-            Err(e) => {
-                error!(
-                    "Could not read authentication token from file '{}' because {}",
-                    AUTH_TOKEN_PATH, e
-                );
-                exit(1);
+    ) -> Result<Self, ConfigError> {
+        Self::with_tokens(vcs, owner, repo_name, whitelist, &Tokens::in_dir(""))
+    }
+
+    /// Creates a merge request handler that reads the tokens it needs from `tokens`.
+    /// GitLab only needs a token to push statuses, and plain git doesn't need any.
+    pub fn with_tokens(
+        vcs: VersionControlSystem,
+        owner: String,
+        repo_name: String,
+        whitelist: Vec<String>,
+        tokens: &Tokens,
+    ) -> Result<Self, ConfigError> {
+        let (poll_token, status_token) = match vcs {
+            VersionControlSystem::GitHub => {
+                let poll_token = tokens.poll.read()?;
+                let status_token = if tokens.status == tokens.poll {
+                    poll_token.clone()
+                } else {
+                    tokens.status.read()?
+                };
+                (poll_token, status_token)
             }
+            VersionControlSystem::GitLab => (String::new(), tokens.status.read()?),
+            VersionControlSystem::Unsupported => (String::new(), String::new()),
         };
 
-        if auth_token.is_empty() {
-            error!(
-                "You didn't write your VCS's authentication token to '{}'!",
-                AUTH_TOKEN_PATH
-            );
-            exit(0);
-        }
+        Ok(Self {
+            poll_token,
+            status_token,
+            ..Self::without_tokens(vcs, owner, repo_name, whitelist)
+        })
+    }
 
+    /// Creates a merge request handler without any tokens, for the subcommands
+    /// that never write them out, like `check`. Its `get_secrets` is empty.
+    pub fn without_tokens(
+        vcs: VersionControlSystem,
+        owner: String,
+        repo_name: String,
+        whitelist: Vec<String>,
+    ) -> Self {
         Self {
             vcs,
             owner,
             repo_name,
             whitelist,
-            auth_token,
+            poll_token: String::new(),
+            status_token: String::new(),
             repository_type: String::from("git"), // We dont support any other repo type.
         }
     }
+
+    /// Retrieves the version control system the repository is hosted on
+    pub fn get_vcs(&self) -> VersionControlSystem {
+        self.vcs
+    }
+
+    /// The secrets the master needs to talk to the VCS, by their names.
+    /// Plain git doesn't need any.
+    pub fn get_secrets(&self) -> BTreeMap<String, String> {
+        let mut secrets = BTreeMap::new();
+        if !self.poll_token.is_empty() {
+            secrets.insert(POLL_TOKEN_SECRET.to_string(), self.poll_token.clone());
+        }
+        if !self.status_token.is_empty() {
+            secrets.insert(STATUS_TOKEN_SECRET.to_string(), self.status_token.clone());
+        }
+        secrets
+    }
+
+    /// The Python statements that poll the VCS for merge requests, push the
+    /// status of builds back to it, and define the `is_whitelisted` function
+    /// the schedulers use to decide whether to build a merge request.
+    pub fn statements(&self) -> Vec<Stmt> {
+        match self.vcs {
+            VersionControlSystem::GitHub => {
+                let mut stmts = vec![
+                    Stmt::assign("whitelist_authors", Expr::lit(&self.whitelist)),
+                    Stmt::Blank,
+                    Stmt::try_except(
+                        vec![Stmt::expr(
+                            config_entry("change_source").method("append").arg(
+                                Call::new("changes.GitHubPullrequestPoller")
+                                    .kwarg("owner", Expr::lit(&self.owner))
+                                    .kwarg("repo", Expr::lit(&self.repo_name))
+                                    .kwarg("pollInterval", Expr::lit(120u32))
+                                    .kwarg("repository_type", Expr::lit(&self.repository_type))
+                                    .kwarg("github_property_whitelist", Expr::lit(vec!["*"]))
+                                    .kwarg("token", secret(POLL_TOKEN_SECRET)),
+                            ),
+                        )],
+                        vec![Stmt::expr(
+                            Call::new("print")
+                                .arg(Expr::lit("Could not create merge request handler:"))
+                                .arg("e"),
+                        )],
+                    ),
+                    Stmt::Blank,
+                ];
+                stmts.extend(status_push("reporters.GitHubStatusPush"));
+                stmts.extend(vec![Stmt::Blank, Stmt::Blank, github_is_whitelisted()]);
+                stmts
+            }
+            VersionControlSystem::GitLab => {
+                let mut stmts = vec![always_whitelisted(), Stmt::Blank];
+                stmts.extend(status_push("reporters.GitLabStatusPush"));
+                stmts
+            }
+            VersionControlSystem::Unsupported => vec![
+                Stmt::expr(Call::new("print").arg(Expr::lit(
+                    "We currently dont support building merge requests on your VCS.",
+                ))),
+                Stmt::Blank,
+                Stmt::comment("The schedulers still ask whether a change is whitelisted"),
+                always_whitelisted(),
+            ],
+        }
+    }
+}
+
+/// Adds a reporter that pushes the status of each build to the VCS
+fn status_push(reporter: &str) -> Vec<Stmt> {
+    vec![
+        Stmt::assign(
+            "context",
+            Call::new("util.Interpolate").arg(Expr::lit("%(prop:buildername)s")),
+        ),
+        Stmt::assign(
+            "status_service",
+            Call::new(reporter)
+                .kwarg("token", secret(STATUS_TOKEN_SECRET))
+                .kwarg("context", "context")
+                .kwarg("startDescription", Expr::lit("Build started."))
+                .kwarg("endDescription", Expr::lit("Build done.")),
+        ),
+        Stmt::expr(
+            config_entry("services")
+                .method("append")
+                .arg("status_service"),
+        ),
+    ]
+}
+
+/// An `is_whitelisted` function that lets every change be built
+fn always_whitelisted() -> Stmt {
+    Stmt::def(
+        "is_whitelisted",
+        &["props", "password"],
+        vec![Stmt::ret(Expr::lit(true))],
+    )
+}
+
+/// The `is_whitelisted` function for GitHub.
+/// A pull request is built if its author is whitelisted, or if a whitelisted
+/// user commented the scheduler's password on it.
+fn github_is_whitelisted() -> Stmt {
+    let props = |key: &str| Expr::name("props").index(Expr::lit(key));
+    let comment = |key: &str| Expr::name("comment").index(Expr::lit(key));
+    let login = comment("user").index(Expr::lit("login"));
+
+    Stmt::def(
+        "is_whitelisted",
+        &["props", "password"],
+        vec![
+            Stmt::for_(
+                "prop",
+                Expr::lit(vec![
+                    "github.number",
+                    "github.comments_url",
+                    "github.user.login",
+                ]),
+                vec![
+                    Stmt::comment("If these properties arent present, its not a pull request"),
+                    Stmt::if_(
+                        !Expr::from(Expr::name("props").method("hasProperty").arg("prop")),
+                        vec![Stmt::ret(Expr::lit(true))],
+                    ),
+                ],
+            ),
+            Stmt::Blank,
+            Stmt::comment("URL for comments info"),
+            Stmt::assign("comments_url", props("github.comments_url")),
+            Stmt::comment("The pull request number that we'll try to whitelist"),
+            Stmt::assign("pr_number", props("github.number")),
+            Stmt::comment("The author of the PR"),
+            Stmt::assign("author", props("github.user.login")),
+            Stmt::Blank,
+            Stmt::assign("resp", Call::new("req.get").arg("comments_url")),
+            Stmt::try_except(
+                vec![
+                    Stmt::comment("Try to convert to a JSON object so we can read the data"),
+                    Stmt::assign(
+                        "json_acceptable_string",
+                        Expr::name("resp.text")
+                            .method("replace")
+                            .arg(Expr::lit("'"))
+                            .arg(Expr::lit("\"")),
+                    ),
+                    Stmt::assign(
+                        "comments_json",
+                        Call::new("json.loads").arg("json_acceptable_string"),
+                    ),
+                    Stmt::Blank,
+                    Stmt::comment("Check each comment"),
+                    Stmt::for_(
+                        "comment",
+                        "comments_json",
+                        vec![
+                            Stmt::comment(
+                                "If the comment was made by an admin and matches the password",
+                            ),
+                            Stmt::if_(
+                                login.clone().op("in", Expr::name("whitelist_authors")).op(
+                                    "and",
+                                    Call::new("re.fullmatch")
+                                        .arg("password")
+                                        .arg(comment("body"))
+                                        .into(),
+                                ),
+                                vec![
+                                    Stmt::expr(
+                                        Call::new("print")
+                                            .arg(Expr::lit("ADMIN:"))
+                                            .arg(login)
+                                            .arg(Expr::lit("PASSWORD:"))
+                                            .arg(comment("body")),
+                                    ),
+                                    Stmt::expr(
+                                        Call::new("print")
+                                            .arg(Expr::lit("PR NUMBER"))
+                                            .arg("pr_number")
+                                            .arg(Expr::lit("IS GOOD TO TEST")),
+                                    ),
+                                    Stmt::ret(Expr::lit(true)),
+                                ],
+                            ),
+                        ],
+                    ),
+                ],
+                vec![
+                    Stmt::comment(
+                        "There was a problem converting to JSON, github returned bad data.\n\
+                         This is likely because we've queried GitHub too many times.",
+                    ),
+                    Stmt::expr(
+                        Call::new("print")
+                            .arg(Expr::lit("There was an error:"))
+                            .arg("e"),
+                    ),
+                    Stmt::comment("Write the returned webpage to BAD"),
+                    Stmt::expr(
+                        Expr::from(Call::new("open").arg(Expr::lit("BAD")).arg(Expr::lit("w")))
+                            .method("write")
+                            .arg("resp.text"),
+                    ),
+                ],
+            ),
+            Stmt::Blank,
+            Stmt::if_(
+                Expr::name("author").op("in", Expr::name("whitelist_authors")),
+                vec![
+                    Stmt::expr(Call::new("print").arg(Expr::lit("WHITELISTED AUTHOR"))),
+                    Stmt::ret(Expr::lit(true)),
+                ],
+            ),
+            Stmt::Blank,
+            Stmt::ret(Expr::lit(false)),
+        ],
+    )
 }
 
 /// This trait implementation tells rust how to convert a MergeRequestHandler object
 /// into the output python.
 impl Display for MergeRequestHandler {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        match &self.vcs {
-            VersionControlSystem::GitHub => writeln!(
-                f,
-                "whitelist_authors = {:?}
-
-try:
-    c['change_source'].append(changes.GitHubPullrequestPoller(
-            owner=\"{owner}\",
-            repo=\"{name}\",
-            # right now just poll every 60 seconds
-            # this will need to change in the future, but this is just for testing.
-            pollInterval=120,
-            repository_type=\"{repository_type}\",
-            github_property_whitelist=[\"*\"],
-            token=\"{token}\"))
-except Exception as e:
-    print(f\"Could not create merge request handler: {{str(e)}}\")
-
-
-context = util.Interpolate(\"%(prop:buildername)s\")
-github_status_service = reporters.GitHubStatusPush(token='{token}',
-                                context=context,
-                                startDescription='Build started.',
-                                endDescription='Build done.')
-
-c['services'].append(github_status_service)
-
-
-def is_whitelisted(props, password):
-    for prop in ['github.number', 'github.comments_url', 'github.user.login']:
-        # If these properties arent present, its not a pull request
-        if not (props.hasProperty(prop)):
-            return True
-    
-    # URL for comments info
-    comments_url = props['github.comments_url']
-
-    # The pull request number that we'll try to whitelist
-    pr_number = props['github.number']
-
-    # The author of the PR
-    author = props['github.user.login']
-
-    resp = req.get(comments_url)
-    try:
-        # Try to convert to a JSON object so we can read the data
-        json_acceptable_string = resp.text.replace(\"'\", \"\\\"\")
-        comments_json = json.loads(json_acceptable_string)
-
-
-        # Check each comment
-        for comment in comments_json:
-            # If the comment was made by an admin and matches the password
-            if comment['user']['login'] in whitelist_authors and re.fullmatch(password, comment['body']):
-                # If the pull request was not already in the whitelisted PRs, add it
-                print(\"ADMIN: \" + str(comment['user']['login']) + \" PASSWORD: \" + str(comment['body']))
-                print(f\"PR NUMBER {{pr_number}} IS GOOD TO TEST\")
-                return True
-    except Exception as e:
-        # There was a problem converting to JSON, github returned bad data
-        print(f\"There was an error: {{str(e)}}. If this error has anything to do with JSON, its likely that you've queried GitHub too many times.\")
-        # Write the returned webpage to BAD
-        open('BAD', 'w').write(resp.text)
-
-    
-    if author in whitelist_authors:
-        print(\"WHITELISTED AUTHOR\")
-        return True
-
-    return False
-",
-                self.whitelist,
-                token = self.auth_token.trim_matches('"'),
-                name = self.repo_name.trim_matches('"'),
-                owner = self.owner.trim_matches('"'),
-                repository_type = self.repository_type.trim_matches('"'),
+        write!(f, "{}", render(&self.statements()))
+    }
+}
 
-            ),
-            VersionControlSystem::GitLab => writeln!(
-                f,
-                "
-def is_whitelisted(props, password): return True
-
-context = util.Interpolate(\"%(prop:buildername)s\")
-gitlab_status_service = reporters.GitLabStatusPush(token='{token}',
-                                context=context,
-                                startDescription='Build started.',
-                                endDescription='Build done.')
-
-c['services'].append(gitlab_status_service)
-               
-",
-                token = self.auth_token.trim_matches('"'),
-            ),
-            VersionControlSystem::Unsupported => writeln!(
-                f,
-                "print('We currently dont support building merge requests on your VCS.')"
-            ),
-        }
+/// A Yaml doesn't know which file it came from,
+/// so this reads the tokens relative to the current directory.
+impl TryFrom<Yaml> for MergeRequestHandler {
+    type Error = Vec<ConfigError>;
+
+    fn try_from(yaml: Yaml) -> Result<Self, Self::Error> {
+        Self::from_yaml(yaml, Path::new(""))
     }
 }
 
-impl From<Yaml> for MergeRequestHandler {
-    fn from(yaml: Yaml) -> Self {
+impl MergeRequestHandler {
+    /// Converts the `merge-request-handler` section of a YAML file in the directory `dir`.
+    /// The tokens are read relative to `dir`, so this works from any directory.
+    pub fn from_yaml(yaml: Yaml, dir: &Path) -> Result<Self, Vec<ConfigError>> {
         // Confirm that the merge request handler has the required sections
-        for section in ["version-control-system", "owner", "repo-name", "whitelist"].iter() {
-            if !yaml.has_section(section) {
-                error!("There was an error creating the merge request handler: '{}' section not specified", section);
-                exit(1);
-            }
+        let context = "the merge request handler";
+        let mut errors = require_sections(
+            &yaml,
+            &["version-control-system", "owner", "repo-name", "whitelist"],
+            context,
+        );
+        errors.extend(unknown_yaml_sections(
+            &yaml,
+            MERGE_REQUEST_HANDLER_SECTIONS,
+            context,
+        ));
+        if !errors.is_empty() {
+            return Err(explain_typos(errors));
         }
         // Now that we've verified the required sections exist, continue
 
-        let vcs: VersionControlSystem = match unwrap(&yaml, "version-control-system").as_str() {
-            "github" => VersionControlSystem::GitHub,
-            "gitlab" => VersionControlSystem::GitLab,
-            _ => {
-                warn!(
-                    "We do not support building merge requests on your version control system yet!"
-                );
-                warn!(
-                    "We will proceed with the build. All other features should function as intended."
-                );
+        let vcs = match unwrap(&yaml, "version-control-system").parse() {
+            Ok(vcs) => vcs,
+            Err(e) => {
+                errors.push(e);
                 VersionControlSystem::Unsupported
             }
         };
@@ -230,16 +581,32 @@ impl From<Yaml> for MergeRequestHandler {
         // of the whitelisted authors
         let mut whitelist: Vec<String> = vec![];
         for author in yaml.get_section("whitelist").unwrap() {
-            whitelist.push(
-                author
-                    .to_string()
-                    .trim_matches('"')
-                    .trim_matches('\'')
-                    .to_string(),
-            );
+            whitelist.push(scalar(&author));
         }
 
+        let section = |name: &str| {
+            if yaml.has_section(name) {
+                Some(unwrap(&yaml, name))
+            } else {
+                None
+            }
+        };
+        let tokens = match Tokens::from_sections(section, dir, context) {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                errors.extend(e);
+                return Err(errors);
+            }
+        };
+
         // Return the constructed Self
-        Self::new(vcs, owner, repo_name, whitelist)
+        match Self::with_tokens(vcs, owner, repo_name, whitelist, &tokens) {
+            Ok(handler) if errors.is_empty() => Ok(handler),
+            Ok(_) => Err(errors),
+            Err(e) => {
+                errors.push(e);
+                Err(errors)
+            }
+        }
     }
 }
@@ -1,20 +1,48 @@
+mod error;
+pub use error::ConfigError;
+pub(crate) use error::{explain_typos, unknown_sections};
+
 mod step;
-pub use step::Step;
+pub use step::{CloneOptions, Step};
 
 mod builder;
-pub use builder::Builder;
+pub use builder::{Builder, BuilderBuilder, Matrix, BUILDER_SECTIONS, MATRIX_REFERENCE};
 
 mod scheduler;
-pub use scheduler::Scheduler;
+pub use scheduler::{Scheduler, SchedulerBuilder, SCHEDULER_SECTIONS};
 
 mod worker;
-pub use worker::Worker;
+pub use worker::{Worker, WorkerBuilder, WorkerOptions, DEFAULT_WORKER_PORT, WORKER_SECTIONS};
 
 mod merge;
-pub use merge::{MergeRequestHandler, AUTH_TOKEN_PATH};
+pub use merge::{
+    MergeRequestHandler, TokenSource, Tokens, VersionControlSystem, AUTH_TOKEN_PATH,
+    MERGE_REQUEST_HANDLER_SECTIONS, POLL_TOKEN_SECRET, STATUS_TOKEN_SECRET,
+};
+
+mod state;
+pub use state::{generate_password, State, STATE_PATH};
 
 mod masterconfig;
-pub use masterconfig::MasterConfig;
+pub use masterconfig::{MasterConfig, MasterConfigBuilder, MASTER_FILE_SECTIONS, MASTER_SECTIONS};
 
 mod mail;
-pub use mail::MailNotifier;
+pub use mail::{MailNotifier, MAIL_SECTIONS, RECIPIENT_SECTIONS, SMTP_PASSWORD_SECRET};
+
+mod runner;
+pub use runner::{RunReport, Runner, StepReport, StepStatus};
+
+mod explain;
+pub use explain::{explain, Change, Explanation, Reason, SchedulerExplanation};
+
+mod project;
+pub use project::{
+    generate, write_secrets, GeneratedProject, INSTALL_MAKEFILE, INSTALL_MAKEFILE_PATH,
+    INSTALL_SCRIPT, INSTALL_SCRIPT_PATH, MASTER_CFG_PATH, SECRETS_DIR_PATH,
+};
+
+mod plan;
+pub use plan::{plan, FilePlan, FileStatus, NameChanges, Plan};
+
+mod check;
+pub use check::{check, CheckIssue, Severity};
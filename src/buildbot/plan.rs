@@ -0,0 +1,243 @@
+use super::project::{
+    generate, is_secret, INSTALL_MAKEFILE_PATH, INSTALL_SCRIPT_PATH, MASTER_CFG_PATH,
+};
+use crate::MasterConfig;
+use regex::Regex;
+use similar::TextDiff;
+use std::fmt::{Display, Error, Formatter};
+use std::fs;
+use std::path::Path;
+
+/// What would happen to a generated file if the project was built
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileStatus {
+    /// The file doesn't exist yet
+    Added,
+    /// The file exists, but its contents would change
+    Modified,
+    /// The file already has the contents we'd write
+    Unchanged,
+}
+
+/// A generated file, and how it differs from the file on disk
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilePlan {
+    /// The path of the file, relative to the project
+    pub path: String,
+    pub status: FileStatus,
+    /// A unified diff from the file on disk to the generated file,
+    /// which is empty if the file is unchanged.
+    /// Secrets only get a line saying that they would change, to keep them out of the terminal,
+    /// and worker passwords are masked.
+    pub diff: String,
+}
+
+/// The names of the things that a build would add or remove
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NameChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl NameChanges {
+    fn new(old: Vec<String>, new: Vec<String>) -> Self {
+        Self {
+            added: new.iter().filter(|n| !old.contains(n)).cloned().collect(),
+            removed: old.iter().filter(|n| !new.contains(n)).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Everything that building a master config would change on disk
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub files: Vec<FilePlan>,
+    pub builders: NameChanges,
+    pub schedulers: NameChanges,
+    pub workers: NameChanges,
+}
+
+impl Plan {
+    /// Whether building would change anything at all
+    pub fn is_empty(&self) -> bool {
+        self.files.iter().all(|f| f.status == FileStatus::Unchanged)
+    }
+}
+
+/// Reads the string literals passed to a call in a generated `master.cfg`.
+/// `call` is a regex matching the start of the call, up to the literal we want.
+fn names(master_cfg: &str, call: &str) -> Vec<String> {
+    let re = Regex::new(&format!(r#"{}\s*"((?:[^"\\]|\\.)*)""#, call)).unwrap();
+    re.captures_iter(master_cfg)
+        .map(|c| c[1].replace("\\\"", "\"").replace("\\\\", "\\"))
+        .collect()
+}
+
+fn builder_names(master_cfg: &str) -> Vec<String> {
+    names(master_cfg, r"util\.BuilderConfig\(\s*name=")
+}
+
+fn scheduler_names(master_cfg: &str) -> Vec<String> {
+    names(
+        master_cfg,
+        r"schedulers\.(?:AnyBranchScheduler|Dependent)\(\s*name=",
+    )
+}
+
+fn worker_names(master_cfg: &str) -> Vec<String> {
+    names(master_cfg, r"worker\.Worker\(")
+}
+
+/// What worker passwords are replaced with in a diff
+const PASSWORD_MASK: &str = "\"<password>\"";
+
+/// Matches the worker passwords in a generated file: the second argument of
+/// `worker.Worker` in `master.cfg`, and the `passwd` variable in `buildbot.tac`.
+/// The first group is everything before the password, and the second is the password.
+fn password_regex() -> Regex {
+    Regex::new(r#"(?m)(worker\.Worker\(\s*"(?:[^"\\]|\\.)*",\s*|^passwd = )("(?:[^"\\]|\\.)*")"#)
+        .unwrap()
+}
+
+/// Replaces the worker passwords in a generated file with a placeholder
+fn mask_passwords(text: &str) -> String {
+    password_regex()
+        .replace_all(text, format!("${{1}}{}", PASSWORD_MASK).as_str())
+        .to_string()
+}
+
+fn passwords(text: &str) -> Vec<String> {
+    password_regex()
+        .captures_iter(text)
+        .map(|c| c[2].to_string())
+        .collect()
+}
+
+/// Compares a generated file with the file at `path` in `root`
+fn plan_file(root: &Path, path: &str, contents: &str) -> Result<FilePlan, String> {
+    let on_disk = root.join(path);
+    let old = if on_disk.exists() {
+        match fs::read_to_string(&on_disk) {
+            Ok(old) => Some(old),
+            Err(e) => {
+                return Err(format!(
+                    "Could not read file '{}' because {}",
+                    on_disk.display(),
+                    e
+                ))
+            }
+        }
+    } else {
+        None
+    };
+
+    let status = match &old {
+        None => FileStatus::Added,
+        Some(old) if old == contents => FileStatus::Unchanged,
+        Some(_) => FileStatus::Modified,
+    };
+
+    let diff = match status {
+        FileStatus::Unchanged => String::new(),
+        FileStatus::Added if is_secret(path, Path::new("")) => {
+            format!("secret '{}' would be created\n", path)
+        }
+        FileStatus::Modified if is_secret(path, Path::new("")) => {
+            format!("secret '{}' would change\n", path)
+        }
+        _ => masked_diff(path, old.as_deref(), contents),
+    };
+
+    Ok(FilePlan {
+        path: path.to_string(),
+        status,
+        diff,
+    })
+}
+
+/// Diffs a generated file with its passwords masked, so they don't end up in the terminal.
+/// A password that changes still gets a line saying so, since the masked lines look the same.
+fn masked_diff(path: &str, old: Option<&str>, new: &str) -> String {
+    let mut diff = TextDiff::from_lines(&mask_passwords(old.unwrap_or("")), &mask_passwords(new))
+        .unified_diff()
+        .context_radius(3)
+        .header(
+            &match old {
+                Some(_) => format!("a/{}", path),
+                None => String::from("/dev/null"),
+            },
+            &format!("b/{}", path),
+        )
+        .to_string();
+
+    if let Some(old) = old {
+        if passwords(old) != passwords(new) {
+            diff += &format!("worker passwords in '{}' would change\n", path);
+        }
+    }
+    diff
+}
+
+/// Renders the master config and each worker's `buildbot.tac` in memory,
+/// and compares them with the files in the project directory `root`, without writing anything.
+///
+/// The builders, schedulers and workers that would be added or removed are
+/// found by comparing the names in the generated and existing `master.cfg`.
+pub fn plan<P: AsRef<Path>>(master: &MasterConfig, root: P) -> Result<Plan, String> {
+    let root = root.as_ref();
+
+    // Only the build subcommands are planned, and they don't write the install scripts
+    let mut project = generate(master);
+    project.remove(INSTALL_SCRIPT_PATH);
+    project.remove(INSTALL_MAKEFILE_PATH);
+
+    let mut files = vec![];
+    for (path, contents) in project.get_files() {
+        files.push(plan_file(root, path, contents)?);
+    }
+
+    let master_cfg = project.get_file(MASTER_CFG_PATH).unwrap_or_default();
+    let old_cfg = fs::read_to_string(root.join(MASTER_CFG_PATH)).unwrap_or_default();
+    Ok(Plan {
+        files,
+        builders: NameChanges::new(builder_names(&old_cfg), builder_names(&master_cfg)),
+        schedulers: NameChanges::new(scheduler_names(&old_cfg), scheduler_names(&master_cfg)),
+        workers: NameChanges::new(worker_names(&old_cfg), worker_names(&master_cfg)),
+    })
+}
+
+/// Displays the diff of every file that would change, followed by a summary
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        for file in &self.files {
+            write!(f, "{}", file.diff)?;
+        }
+
+        let summary = [
+            ("builder", &self.builders),
+            ("scheduler", &self.schedulers),
+            ("worker", &self.workers),
+        ];
+        for (kind, changes) in summary.iter() {
+            for name in &changes.added {
+                writeln!(f, "+ {} '{}'", kind, name)?;
+            }
+            for name in &changes.removed {
+                writeln!(f, "- {} '{}'", kind, name)?;
+            }
+        }
+
+        let count = |status: FileStatus| self.files.iter().filter(|f| f.status == status).count();
+        write!(
+            f,
+            "{} file(s) would be created, {} would change, {} unchanged",
+            count(FileStatus::Added),
+            count(FileStatus::Modified),
+            count(FileStatus::Unchanged)
+        )
+    }
+}
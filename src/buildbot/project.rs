@@ -0,0 +1,173 @@
+use crate::{File, MasterConfig};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Where the master config is written, relative to the project
+pub const MASTER_CFG_PATH: &str = "master/master.cfg";
+
+/// The directory the master's secrets are written to, relative to the project.
+/// Each secret is a file named after it, that only the current user can read.
+pub const SECRETS_DIR_PATH: &str = "master/secrets";
+
+/// Where the shell script that installs buildbot is written, relative to the project
+pub const INSTALL_SCRIPT_PATH: &str = "install.sh";
+
+/// Where the makefile that installs buildbot is written, relative to the project
+pub const INSTALL_MAKEFILE_PATH: &str = "Makefile";
+
+/// A shell script that installs buildbot in a python virtual environment
+pub const INSTALL_SCRIPT: &str = "#!/bin/sh
+
+python3 -m venv venv 2>&1
+. venv/bin/activate
+
+python3 -m pip install -U pip >/dev/null
+python3 -m pip install txrequests treq 'buildbot[bundle]' >/dev/null
+python3 -m pip install buildbot-worker setuptools-trial >/dev/null
+";
+
+/// A makefile that installs buildbot in a python virtual environment with `make install`
+pub const INSTALL_MAKEFILE: &str = ".SILENT: install
+
+install:
+\tpython3 -m venv venv
+\t. venv/bin/activate; python3 -m pip install -U pip >/dev/null; python3 -m pip install txrequests treq 'buildbot[bundle]' >/dev/null;
+\t. venv/bin/activate; python3 -m pip install buildbot-worker setuptools-trial >/dev/null
+";
+
+/// Every file of a buildbot project, generated in memory.
+/// This maps the path of each file, relative to the project directory, to its contents.
+///
+/// This is for tools that want to write, archive, or ship the files themselves
+/// instead of having a `BuildSystem` write them and run buildbot.
+/// A worker with an absolute `working-dir` has an absolute path for its `buildbot.tac`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeneratedProject {
+    files: BTreeMap<String, String>,
+}
+
+impl GeneratedProject {
+    /// Adds a file to the project, replacing any file already at `path`
+    pub fn insert<P: ToString, S: ToString>(&mut self, path: P, contents: S) {
+        self.files.insert(path.to_string(), contents.to_string());
+    }
+
+    /// Removes a file from the project, and returns its contents
+    pub fn remove(&mut self, path: &str) -> Option<String> {
+        self.files.remove(path)
+    }
+
+    /// Retrieves the contents of the file at `path`
+    pub fn get_file(&self, path: &str) -> Option<String> {
+        self.files.get(path).cloned()
+    }
+
+    /// Retrieves the path of every file, in sorted order
+    pub fn get_paths(&self) -> Vec<String> {
+        self.files.keys().cloned().collect()
+    }
+
+    /// Retrieves every file, mapped from its path to its contents
+    pub fn get_files(&self) -> &BTreeMap<String, String> {
+        &self.files
+    }
+
+    /// Writes every file to the project directory `root`,
+    /// creating the directories leading to each of them
+    pub fn write_to<P: AsRef<Path>>(&self, root: P) -> Result<(), String> {
+        for (path, contents) in &self.files {
+            let path = root.as_ref().join(path);
+            if let Some(dir) = path.parent() {
+                if let Err(e) = fs::create_dir_all(dir) {
+                    return Err(format!(
+                        "Could not create directory '{}' because {}",
+                        dir.display(),
+                        e
+                    ));
+                }
+            }
+            if is_secret(&path, root.as_ref()) {
+                // `File::write_private` adds the newline back
+                File::write_private(&path, contents.strip_suffix('\n').unwrap_or(contents))?;
+                continue;
+            }
+            // Our contents already end with a newline, and `File::write` adds another,
+            // so write the files directly to keep them exactly as they were generated
+            if let Err(e) = fs::write(&path, contents) {
+                return Err(format!(
+                    "Could not write to file '{}' because {}",
+                    path.display(),
+                    e
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes each of the master's secrets to its own file in the secrets directory
+/// of the project `root`, which only the current user can read.
+/// Any other file in there is a secret the master doesn't have anymore, like the
+/// SMTP password after the mail file is dropped, so it's removed instead of left on disk.
+pub fn write_secrets<P: AsRef<Path>>(master: &MasterConfig, root: P) -> Result<(), String> {
+    let dir = root.as_ref().join(SECRETS_DIR_PATH);
+    let secrets = master.get_secrets();
+
+    // There's nothing to remove if the directory doesn't exist yet
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if secrets.contains_key(entry.file_name().to_string_lossy().as_ref()) {
+                continue;
+            }
+            if let Err(e) = fs::remove_file(&path) {
+                return Err(format!(
+                    "Could not remove the old secret '{}' because {}",
+                    path.display(),
+                    e
+                ));
+            }
+        }
+    }
+
+    for (name, secret) in secrets {
+        File::write_private(dir.join(name), secret)?;
+    }
+    Ok(())
+}
+
+/// Whether the file at `path` in the project directory `root` is one of the master's secrets
+pub(crate) fn is_secret<P: AsRef<Path>>(path: P, root: &Path) -> bool {
+    let path = path.as_ref();
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .starts_with(SECRETS_DIR_PATH)
+}
+
+/// Generates every file of the buildbot project for a master config:
+/// the `master.cfg`, its secrets, each worker's `buildbot.tac`, and the install scripts.
+///
+/// The files have the same contents the build subcommands would write.
+pub fn generate(master: &MasterConfig) -> GeneratedProject {
+    let mut project = GeneratedProject::default();
+    // `File::write` ends every file with a newline
+    project.insert(MASTER_CFG_PATH, format!("{}\n", master));
+    for (name, secret) in master.get_secrets() {
+        let path = Path::new(SECRETS_DIR_PATH).join(name);
+        project.insert(path.to_string_lossy(), format!("{}\n", secret));
+    }
+    for worker in master.get_workers() {
+        let path = Path::new(&worker.get_dir()).join("buildbot.tac");
+        project.insert(path.to_string_lossy(), format!("{}\n", worker));
+    }
+    project.insert(INSTALL_SCRIPT_PATH, format!("{}\n", INSTALL_SCRIPT));
+    project.insert(INSTALL_MAKEFILE_PATH, format!("{}\n", INSTALL_MAKEFILE));
+    project
+}
+
+impl From<&MasterConfig> for GeneratedProject {
+    fn from(master: &MasterConfig) -> Self {
+        generate(master)
+    }
+}
@@ -0,0 +1,212 @@
+use super::builder::START_DIR;
+use crate::{Builder, Cmd, Step};
+use std::fmt::{Display, Error, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Whether a step of a local run passed, failed, or never ran
+#[derive(Clone, Debug, PartialEq)]
+pub enum StepStatus {
+    Passed,
+    /// The step failed, with the reason why
+    Failed(String),
+    /// The step didn't run because an earlier step failed
+    Skipped,
+}
+
+/// What happened to one step of a local run
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepReport {
+    /// The command line of the step, or what it clones
+    pub name: String,
+    pub status: StepStatus,
+    /// How long the step took to run
+    pub duration: Duration,
+}
+
+/// What happened to every step of a builder that was run locally
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunReport {
+    pub builder: String,
+    pub steps: Vec<StepReport>,
+}
+
+impl RunReport {
+    /// Whether every step of the builder passed
+    pub fn success(&self) -> bool {
+        self.steps.iter().all(|s| s.status == StepStatus::Passed)
+    }
+}
+
+/// Displays a summary of the run with the status and time of each step
+impl Display for RunReport {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        writeln!(f, "Summary of builder '{}':", self.builder)?;
+        for step in &self.steps {
+            let (status, time) = match &step.status {
+                StepStatus::Passed => ("passed", format!("{:.1}s", step.duration.as_secs_f64())),
+                StepStatus::Failed(_) => ("failed", format!("{:.1}s", step.duration.as_secs_f64())),
+                StepStatus::Skipped => ("skipped", String::new()),
+            };
+            writeln!(f, "  {:<8}{:>8}  {}", status, time, step.name)?;
+        }
+        for step in &self.steps {
+            if let StepStatus::Failed(reason) = &step.status {
+                writeln!(f, "`{}` failed: {}", step.name, reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The Runner runs the steps of a builder on this machine, without buildbot.
+/// This lets you try out a builder's script without pushing a commit for the CI to run.
+///
+/// The steps run in order, in the same working directories buildbot would use,
+/// with their output printed as they run. The first step that fails stops the run.
+pub struct Runner {
+    /// The directory the repository is checked out in
+    checkout: PathBuf,
+    /// Whether to clone the builder's repository into the checkout before running
+    clone: bool,
+}
+
+impl Runner {
+    /// Run builders in a fresh clone of their repository in `dir`.
+    /// Anything already in `dir` is deleted before cloning.
+    pub fn clone_into<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            checkout: dir.as_ref().to_path_buf(),
+            clone: true,
+        }
+    }
+
+    /// Run builders in the repository already checked out in `dir`, without cloning it
+    pub fn in_checkout<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            checkout: dir.as_ref().to_path_buf(),
+            clone: false,
+        }
+    }
+
+    /// The directory a step runs in.
+    /// Builders track `cd` instructions relative to the directory buildbot checks
+    /// the repository out in, so that directory is swapped for our checkout.
+    pub fn workdir(&self, workdir: &Option<String>) -> PathBuf {
+        match workdir {
+            Some(workdir) => match Path::new(workdir).strip_prefix(START_DIR) {
+                Ok(relative) => self.checkout.join(relative),
+                // Absolute paths from `cd /somewhere` are used as they are
+                Err(_) => PathBuf::from(workdir),
+            },
+            None => self.checkout.clone(),
+        }
+    }
+
+    /// The name of a step in the run's summary
+    fn step_name(step: &Step) -> String {
+        match step {
+            Step::GitClone { url, .. }
+            | Step::GitHubClone { url, .. }
+            | Step::GitLabClone { url, .. } => format!("clone {}", url),
+            Step::Command { command, .. } => command.clone(),
+        }
+    }
+
+    /// The command that runs a step on this machine
+    pub fn command(&self, step: &Step) -> Cmd {
+        match step {
+            Step::GitClone { url, options }
+            | Step::GitHubClone { url, options }
+            | Step::GitLabClone { url, options } => {
+                let mut cmd = Cmd::new("git").arg("clone");
+                if let Some(depth) = options.depth {
+                    cmd = cmd.arg("--depth").arg(depth);
+                }
+                if let Some(branch) = &options.branch {
+                    cmd = cmd.arg("--branch").arg(branch);
+                }
+                if options.submodules {
+                    cmd = cmd.arg("--recurse-submodules");
+                }
+                cmd.arg(url).arg(self.checkout.display())
+            }
+            Step::Command { workdir, .. } => {
+                let argv = step.argv();
+                let mut cmd = Cmd::new(&argv[0]);
+                for arg in &argv[1..] {
+                    cmd = cmd.arg(arg);
+                }
+                cmd.cwd(self.workdir(workdir).display())
+            }
+        }
+    }
+
+    /// Runs a single step, printing its output as it runs
+    fn run_step(&self, step: &Step) -> Result<(), String> {
+        if let Step::Command { .. } = step {
+            if step.argv().is_empty() {
+                return Ok(());
+            }
+        } else if self.checkout.exists() {
+            if let Err(e) = fs::remove_dir_all(&self.checkout) {
+                return Err(format!(
+                    "Could not remove the old checkout '{}' because {}",
+                    self.checkout.display(),
+                    e
+                ));
+            }
+        }
+
+        let output = self.command(step).stream().output()?;
+        match output.status.code() {
+            Some(0) => Ok(()),
+            Some(code) => Err(format!("exited with status {}", code)),
+            None => Err(String::from("killed by a signal")),
+        }
+    }
+
+    /// Runs each step of the builder in order, and stops at the first one that fails
+    pub fn run(&self, builder: &Builder) -> RunReport {
+        let steps = if self.clone {
+            builder.get_steps()
+        } else {
+            builder.get_steps().into_iter().skip(1).collect()
+        };
+
+        let mut reports = vec![];
+        let mut failed = false;
+        for step in &steps {
+            let name = Self::step_name(step);
+            if failed {
+                reports.push(StepReport {
+                    name,
+                    status: StepStatus::Skipped,
+                    duration: Duration::from_secs(0),
+                });
+                continue;
+            }
+
+            info!("Running `{}`...", name);
+            let start = Instant::now();
+            let status = match self.run_step(step) {
+                Ok(()) => StepStatus::Passed,
+                Err(e) => {
+                    failed = true;
+                    StepStatus::Failed(e)
+                }
+            };
+            reports.push(StepReport {
+                name,
+                status,
+                duration: start.elapsed(),
+            });
+        }
+
+        RunReport {
+            builder: builder.get_name(),
+            steps: reports,
+        }
+    }
+}
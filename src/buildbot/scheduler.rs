@@ -1,7 +1,14 @@
-use crate::unwrap;
+use super::error::{empty_name, explain_typos, missing, require_sections, unknown_yaml_sections};
+use super::masterconfig::config_entry;
+use crate::helper::python::identifier;
+use crate::helper::python_ast::{render, Call, Expr, Stmt};
+use crate::{scalar, unwrap, Builder, ConfigError, PyRegex};
 use rusty_yaml::Yaml;
+use std::convert::TryFrom;
 use std::fmt::{Display, Error, Formatter};
-use std::process::exit;
+
+/// Every section a scheduler can have. Anything else is reported as a typo.
+pub const SCHEDULER_SECTIONS: &[&str] = &["depends", "branch", "triggers", "password", "builders"];
 
 /// The scheduler struct controls when a builder is run. This is done when certain requirements specified
 /// by the scheduler are fulfilled. For example, you could define a scheduler that would trigger one or
@@ -48,6 +55,20 @@ pub struct Scheduler {
 }
 
 impl Scheduler {
+    /// Starts building a scheduler named `name` in Rust, instead of reading it from YAML.
+    /// The scheduler needs at least one builder, and either `depends`,
+    /// or a `branch` and `password` and its triggers.
+    pub fn builder<S: ToString>(name: S) -> SchedulerBuilder {
+        SchedulerBuilder {
+            name: name.to_string(),
+            depends: None,
+            password: None,
+            branch: None,
+            file_triggers: vec![],
+            buildernames: vec![],
+        }
+    }
+
     /// Create new scheduler
     fn new<S>(
         name: S,
@@ -65,129 +86,301 @@ impl Scheduler {
             depends,
             password: password.to_string(),
             branch: branch.to_string(),
-            file_triggers: file_triggers
-                .iter()
-                .map(|s| {
-                    s.to_string()
-                        .trim()
-                        .trim_start_matches('\"')
-                        .trim_end_matches('\"')
-                        .to_string()
-                })
-                .collect(),
-            buildernames: buildernames
+            file_triggers: file_triggers.iter().map(|s| s.to_string()).collect(),
+            buildernames: buildernames.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// This method returns the name of the scheduler
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// This method returns the name of the scheduler this one depends on, if any
+    pub fn get_depends(&self) -> Option<String> {
+        self.depends.clone()
+    }
+
+    /// This method returns the regex that matches the branches this scheduler operates on
+    pub fn get_branch(&self) -> String {
+        self.branch.clone()
+    }
+
+    /// This method returns the regexes that match the files that trigger this scheduler
+    pub fn get_file_triggers(&self) -> Vec<String> {
+        self.file_triggers.clone()
+    }
+
+    /// This method returns the names of the builders this scheduler triggers
+    pub fn get_buildernames(&self) -> Vec<String> {
+        self.buildernames.clone()
+    }
+
+    /// Replaces the name of each builder with a matrix that this scheduler triggers
+    /// with the name of every builder its matrix was expanded into
+    pub(crate) fn expand_matrices(&mut self, builders: &[Builder]) {
+        let mut buildernames = vec![];
+        for name in &self.buildernames {
+            let expanded: Vec<String> = builders
                 .iter()
-                .map(|s| {
-                    s.to_string()
-                        .trim()
-                        .trim_start_matches('\"')
-                        .trim_end_matches('\"')
-                        .to_string()
-                })
-                .collect(),
+                .filter(|b| b.get_matrix_name().as_ref() == Some(name))
+                .map(Builder::get_name)
+                .collect();
+            if expanded.is_empty() {
+                buildernames.push(name.clone());
+            } else {
+                buildernames.extend(expanded);
+            }
         }
+        self.buildernames = buildernames;
+    }
+
+    /// The Python statements that add this scheduler to the master config.
+    /// A scheduler without `depends` also gets a force scheduler,
+    /// so its builders can be started by hand from the web gui.
+    pub fn statements(&self) -> Vec<Stmt> {
+        let name = identifier(&self.name);
+        let depends = match &self.depends {
+            Some(depends) => depends,
+            None => return self.branch_statements(),
+        };
+        vec![
+            Stmt::assign(
+                name.as_str(),
+                Call::new("schedulers.Dependent")
+                    .kwarg("name", Expr::lit(&name))
+                    .kwarg("upstream", Expr::name(identifier(depends)))
+                    .kwarg("builderNames", Expr::lit(&self.buildernames)),
+            ),
+            add_scheduler(Expr::name(&name)),
+        ]
     }
+
+    /// The statements for a scheduler that watches a branch for changes to files
+    /// that match its triggers
+    fn branch_statements(&self) -> Vec<Stmt> {
+        let name = identifier(&self.name);
+        let triggers = format!("{}_triggers", name);
+
+        // Picks the builders to run for a change, or none if the change
+        // doesnt touch a file matching one of the triggers
+        let renderer = Stmt::Def {
+            decorators: vec![Expr::name("util.renderer")],
+            name: triggers.clone(),
+            params: vec![String::from("props")],
+            body: vec![
+                Stmt::assign("builders", Expr::lit(&self.buildernames)),
+                Stmt::assign(
+                    "triggers",
+                    Expr::list(self.file_triggers.iter().map(|t| Expr::lit(PyRegex(t)))),
+                ),
+                Stmt::Blank,
+                Stmt::if_(
+                    !Expr::from(
+                        Call::new("is_whitelisted")
+                            .arg("props")
+                            .arg(Expr::lit(PyRegex(&self.password))),
+                    ),
+                    vec![
+                        Stmt::expr(Call::new("print").arg(Expr::lit("NOT WHITELISTED!!!"))),
+                        Stmt::ret(Expr::list(vec![])),
+                    ],
+                ),
+                Stmt::Blank,
+                Stmt::for_(
+                    "f",
+                    Expr::name("props.files"),
+                    vec![Stmt::for_(
+                        "regex",
+                        "triggers",
+                        vec![
+                            Stmt::expr(Call::new("print").arg(Expr::lit("FILE:   ")).arg("f")),
+                            Stmt::expr(Call::new("print").arg(Expr::lit("TRIGGER:")).arg("regex")),
+                            Stmt::if_(
+                                Call::new("re.fullmatch")
+                                    .arg("regex")
+                                    .arg(Call::new("str").arg("f")),
+                                vec![Stmt::ret("builders")],
+                            ),
+                        ],
+                    )],
+                ),
+                Stmt::Blank,
+                Stmt::ret(Expr::list(vec![])),
+            ],
+        };
+
+        vec![
+            renderer,
+            Stmt::Blank,
+            Stmt::Blank,
+            Stmt::assign(
+                name.as_str(),
+                Call::new("schedulers.AnyBranchScheduler")
+                    .kwarg("name", Expr::lit(&name))
+                    .kwarg(
+                        "change_filter",
+                        Call::new("util.ChangeFilter")
+                            .kwarg("branch_re", Expr::lit(PyRegex(&self.branch))),
+                    )
+                    .kwarg("builderNames", Expr::name(&triggers)),
+            ),
+            add_scheduler(Expr::name(&name)),
+            add_scheduler(
+                Call::new("schedulers.ForceScheduler")
+                    .kwarg("name", Expr::lit(format!("force_{}", name)))
+                    .kwarg("builderNames", Expr::lit(&self.buildernames))
+                    .into(),
+            ),
+        ]
+    }
+}
+
+/// `c['schedulers'].append(scheduler)`
+fn add_scheduler(scheduler: Expr) -> Stmt {
+    Stmt::expr(config_entry("schedulers").method("append").arg(scheduler))
 }
 
 impl Display for Scheduler {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        match &self.depends {
-            Some(depends) => write!(
-                f,
-                "   
-{name} = schedulers.Dependent(name=\"{name}\",
-                               upstream={depends},
-                               builderNames={buildernames})
-c['schedulers'].append({name})
-
-    ",
-                name = self.name.replace("-", "_"),
-                depends = depends.replace("-", "_"),
-                buildernames = format!("{:?}", self.buildernames)
-            ),
-            None => writeln!(
-                f,
-                "
-@util.renderer
-def {name}_triggers(props):
-    builders = {buildernames}
-
-    triggers = {triggers}
-
-    if not is_whitelisted(props, \"{password}\"):
-        print('NOT WHITELISTED!!!')
-        return []
-
-    for f in props.files:
-        for regex in triggers:
-            print(\"FILE:   \", f)
-            print(\"TRIGGER:\", regex)
-            if re.fullmatch(regex, str(f)):
-                return builders
-
-    return []
-
-
-{name} = schedulers.AnyBranchScheduler(name=\"{name}\",
-    change_filter=util.ChangeFilter(branch_re=\"{branch}\"),
-    builderNames={name}_triggers)
-
-c['schedulers'].append({name})
-
-c['schedulers'].append(schedulers.ForceScheduler(name=\"force_{name}\",
-    builderNames={buildernames}))
-",
-                name = self.name.replace("-", "_"),
-                password = self.password.trim_matches('"'),
-                branch = self.branch.trim_start_matches('\"').trim_end_matches('\"'),
-                triggers = format!("{:?}", self.file_triggers)
-                    .replace("\\\"", "")
-                    .replace("\\\\\\\\", "\\\\"),
-                buildernames = format!("{:?}", self.buildernames)
-            ),
-        }
+        write!(f, "{}", render(&self.statements()))
     }
 }
 
-impl From<Yaml> for Scheduler {
-    fn from(yaml: Yaml) -> Self {
-        let name = yaml.get_name();
-        let depends: Option<String>;
-        let branch: String;
-        let password: String;
-        let mut triggers = vec![];
-        let mut builders = vec![];
-
-        if !yaml.has_section("builders") {
-            error!("There was an error creating a scheduler: The 'builders' section is not specified for '{}'", name);
-            exit(1);
+/// This builds a Scheduler with the same sections the YAML uses.
+/// Every mistake is collected and returned from `build`, like `Scheduler::try_from` does.
+#[derive(Clone, Debug)]
+pub struct SchedulerBuilder {
+    name: String,
+    depends: Option<String>,
+    password: Option<String>,
+    branch: Option<String>,
+    file_triggers: Vec<String>,
+    buildernames: Vec<String>,
+}
+
+impl SchedulerBuilder {
+    /// Only run after the scheduler named `scheduler` runs and its builds pass.
+    /// This replaces the `branch`, `triggers`, and `password` of the scheduler.
+    pub fn depends<S: ToString>(mut self, scheduler: S) -> Self {
+        self.depends = Some(scheduler.to_string());
+        self
+    }
+
+    /// The regex that matches the branches this scheduler watches
+    pub fn branch<S: ToString>(mut self, regex: S) -> Self {
+        self.branch = Some(regex.to_string());
+        self
+    }
+
+    /// Adds a regex that matches the files that trigger this scheduler
+    pub fn trigger<S: ToString>(mut self, regex: S) -> Self {
+        self.file_triggers.push(regex.to_string());
+        self
+    }
+
+    /// The regex a whitelisted user comments to mark a merge request for testing
+    pub fn password<S: ToString>(mut self, regex: S) -> Self {
+        self.password = Some(regex.to_string());
+        self
+    }
+
+    /// Adds a builder for this scheduler to trigger
+    pub fn builder<S: ToString>(mut self, name: S) -> Self {
+        self.buildernames.push(name.to_string());
+        self
+    }
+
+    /// Builds the scheduler, or returns everything wrong with it
+    pub fn build(self) -> Result<Scheduler, Vec<ConfigError>> {
+        let context = format!("scheduler '{}'", self.name);
+        let mut errors = vec![];
+
+        if self.name.is_empty() {
+            errors.push(empty_name(&context));
+        }
+        if self.buildernames.is_empty() {
+            errors.push(missing(&context, "builders"));
         }
 
-        if yaml.has_section("depends") {
-            depends = Some(unwrap(&yaml, "depends"));
-            branch = String::from("");
-            password = String::from("");
+        if self.depends.is_some() {
+            if self.branch.is_some() || self.password.is_some() || !self.file_triggers.is_empty() {
+                errors.push(ConfigError::BadValue {
+                    context,
+                    section: String::from("depends"),
+                    value: self.depends.clone().unwrap_or_default(),
+                    reason: String::from(
+                        "a scheduler that depends on another can't have a branch, triggers, or password",
+                    ),
+                });
+            }
         } else {
-            for section in ["branch", "password", "triggers"].iter() {
-                if !yaml.has_section(section) {
-                    error!("There was an error creating a scheduler: The '{}' section is not specified for '{}'", section, name);
-                    exit(1);
-                }
+            if self.branch.is_none() {
+                errors.push(missing(&context, "branch"));
+            }
+            if self.password.is_none() {
+                errors.push(missing(&context, "password"));
             }
+            if self.file_triggers.is_empty() {
+                errors.push(missing(&context, "triggers"));
+            }
+        }
 
-            depends = None;
-            branch = unwrap(&yaml, "branch");
-            password = unwrap(&yaml, "password");
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Scheduler::new(
+            self.name,
+            self.depends,
+            self.password.unwrap_or_default(),
+            self.branch.unwrap_or_default(),
+            self.file_triggers,
+            self.buildernames,
+        ))
+    }
+}
 
+impl TryFrom<Yaml> for Scheduler {
+    type Error = Vec<ConfigError>;
+
+    fn try_from(yaml: Yaml) -> Result<Self, Self::Error> {
+        let name = yaml.get_name();
+        let context = format!("scheduler '{}'", name);
+
+        // A scheduler that depends on another only needs the `builders` section,
+        // otherwise we also need to know when to trigger the builders.
+        let mut errors = if yaml.has_section("depends") {
+            require_sections(&yaml, &["builders"], &context)
+        } else {
+            require_sections(
+                &yaml,
+                &["builders", "branch", "password", "triggers"],
+                &context,
+            )
+        };
+        errors.extend(unknown_yaml_sections(&yaml, SCHEDULER_SECTIONS, &context));
+        if !errors.is_empty() {
+            return Err(explain_typos(errors));
+        }
+
+        let mut scheduler = Scheduler::builder(name);
+        if yaml.has_section("depends") {
+            // The `branch`, `triggers`, and `password` sections are ignored
+            scheduler = scheduler.depends(unwrap(&yaml, "depends"));
+        } else {
+            scheduler = scheduler
+                .branch(unwrap(&yaml, "branch"))
+                .password(unwrap(&yaml, "password"));
             for trigger in yaml.get_section("triggers").unwrap() {
-                triggers.push(trigger.to_string());
+                scheduler = scheduler.trigger(scalar(&trigger));
             }
         }
 
         for builder in yaml.get_section("builders").unwrap() {
-            builders.push(builder.to_string());
+            scheduler = scheduler.builder(scalar(&builder));
         }
 
-        Scheduler::new(name, depends, password, branch, triggers, builders)
+        scheduler.build()
     }
 }
@@ -0,0 +1,121 @@
+use crate::File;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Error, Formatter};
+use std::path::Path;
+
+/// This is the path to the file where rusty-ci keeps the things it generated
+/// that must stay the same between builds, like the worker passwords.
+pub const STATE_PATH: &str = ".rusty-ci/state";
+
+/// The number of characters in a generated worker password
+const PASSWORD_LENGTH: usize = 30;
+
+/// Generates a new random password for a worker
+pub fn generate_password() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(PASSWORD_LENGTH)
+        .collect()
+}
+
+/// The state rusty-ci keeps between builds.
+///
+/// The master and every worker must agree on a worker's password.
+/// If we made a new one on every build, any worker whose `buildbot.tac`
+/// wasn't rewritten, like a worker on another machine, would be locked out.
+/// So the first password generated for each worker is saved here,
+/// and reused until it is deliberately rotated.
+///
+/// The file has a line `password.<worker name> = <password>` for each worker.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct State {
+    passwords: BTreeMap<String, String>,
+}
+
+impl State {
+    /// Reads the state from a file.
+    /// If the file doesn't exist yet, nothing has been built, so the state is empty.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        if !path.as_ref().exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = File::read(&path)?;
+        contents.parse().map_err(|e| {
+            format!(
+                "Could not read the state file '{}' because {}",
+                path.as_ref().display(),
+                e
+            )
+        })
+    }
+
+    /// Writes the state to a file that only the current user can read
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        File::write_private(path, self)
+    }
+
+    /// Retrieves the saved password of a worker
+    pub fn get_password(&self, worker: &str) -> Option<String> {
+        self.passwords.get(worker).cloned()
+    }
+
+    /// Saves the password of a worker
+    pub fn set_password(&mut self, worker: &str, password: &str) {
+        self.passwords
+            .insert(worker.to_string(), password.to_string());
+    }
+
+    /// Replaces the password of a worker with a new random one, and returns it.
+    /// This returns None if the worker doesn't have a password yet.
+    pub fn rotate_password(&mut self, worker: &str) -> Option<String> {
+        let password = self.passwords.get_mut(worker)?;
+        *password = generate_password();
+        Some(password.clone())
+    }
+}
+
+impl std::str::FromStr for State {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut state = Self::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Passwords never contain spaces, so the last ` = ` separates the worker's name
+            match line.rsplit_once(" = ") {
+                Some((key, password)) if key.starts_with("password.") => {
+                    state.set_password(&key["password.".len()..], password)
+                }
+                _ => {
+                    return Err(format!(
+                        "line {} is not a worker password: `{}`",
+                        i + 1,
+                        line
+                    ))
+                }
+            }
+        }
+        Ok(state)
+    }
+}
+
+impl Display for State {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        writeln!(
+            f,
+            "# This file was generated by rusty-ci, and holds the worker passwords."
+        )?;
+        write!(f, "# Keep it private, and don't edit it by hand.")?;
+        for (worker, password) in &self.passwords {
+            write!(f, "\npassword.{} = {}", worker, password)?;
+        }
+        Ok(())
+    }
+}
@@ -1,40 +1,191 @@
+use crate::helper::python_ast::{Call, Expr};
+use crate::shell_words;
 use std::fmt::{Display, Error, Formatter};
 
+/// The git checkout methods buildbot supports when cloning in `full` mode
+pub const CLONE_METHODS: &[&str] = &["clobber", "fresh", "clean", "copy"];
+
+/// These are the options for how a builder clones its repository.
+/// Each builder can override them in its YAML section with the
+/// `branch`, `shallow`, `depth`, `submodules`, and `method` keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CloneOptions {
+    /// The branch to always check out.
+    /// If this is None, the branch and revision of the change that
+    /// triggered the build are checked out, falling back on master.
+    pub branch: Option<String>,
+    /// How many commits of history to clone.
+    /// If this is None, the whole history is cloned.
+    pub depth: Option<u32>,
+    /// Whether or not to update the submodules of the repository
+    pub submodules: bool,
+    /// The buildbot checkout method, one of `CLONE_METHODS`
+    pub method: String,
+}
+
+impl Default for CloneOptions {
+    fn default() -> Self {
+        Self {
+            branch: None,
+            depth: None,
+            submodules: true,
+            method: String::from("clobber"),
+        }
+    }
+}
+
+impl CloneOptions {
+    /// The keyword arguments shared by every buildbot clone step
+    pub fn kwargs(&self) -> Vec<(String, Expr)> {
+        let mut kwargs = vec![(String::from("mode"), Expr::lit("full"))];
+        match &self.branch {
+            // A fixed branch must ignore the branch of the change that triggered the build
+            Some(branch) => {
+                kwargs.push((String::from("branch"), Expr::lit(branch)));
+                kwargs.push((String::from("alwaysUseLatest"), Expr::lit(true)));
+            }
+            // Otherwise, buildbot checks out the revision from the triggering change
+            None => kwargs.push((
+                String::from("branch"),
+                Call::new("util.Property")
+                    .arg(Expr::lit("branch"))
+                    .kwarg("default", Expr::lit("master"))
+                    .into(),
+            )),
+        }
+        kwargs.push((String::from("method"), Expr::lit(&self.method)));
+        kwargs.push((
+            String::from("shallow"),
+            match self.depth {
+                Some(depth) => Expr::lit(depth),
+                None => Expr::lit(false),
+            },
+        ));
+        kwargs.push((String::from("submodules"), Expr::lit(self.submodules)));
+        kwargs
+    }
+}
+
+/// Converts the options into the keyword arguments shared by every buildbot clone step
+impl Display for CloneOptions {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let kwargs = self
+            .kwargs()
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>();
+        write!(f, "{}", kwargs.join(", "))
+    }
+}
+
 /// This describes a step executed by a builder in buildbot
 /// This is used by the Builder struct to construct the Builder config file
 #[derive(Clone, Debug)]
 pub enum Step {
     /// Represents a git clone operation
     GitClone {
-        url: String, // The repo to clone
+        url: String,           // The repo to clone
+        options: CloneOptions, // How to clone it
+    },
+
+    /// Represents a git clone operation targetting github.
+    /// This also knows how to check out the code of pull requests
+    GitHubClone {
+        url: String,           // The repo to clone
+        options: CloneOptions, // How to clone it
     },
 
     /// Represents a git clone operation targetting gitlab.
     /// The reason we need to know specifically about if the clone
     /// is from gitlab is because this also handles merge requests
     GitLabClone {
-        url: String, // The repo to clone
+        url: String,           // The repo to clone
+        options: CloneOptions, // How to clone it
     },
 
     /// Represents a command line command
     Command {
         command: String,         // The command to run
         workdir: Option<String>, // The optional workdir
+        shell: bool,             // Run the command with `/bin/sh -c` instead of as an argv list
     },
 }
 
 /// Implementation of step struct
 impl Step {
-    /// Construct a command line step
+    /// Construct a command line step.
+    /// The command is split into an argv list like a shell would split it,
+    /// but pipes, redirects and the like are passed to the program as arguments.
     pub fn command<S: Display>(command: S, workdir: Option<S>) -> Self {
         Step::Command {
             command: command.to_string(),
-            workdir: workdir.and_then(|s| Some(s.to_string())),
+            workdir: workdir.map(|s| s.to_string()),
+            shell: false,
         }
     }
 
+    /// Construct a command line step that is run by `/bin/sh -c`,
+    /// so the command can use pipes, redirects, `&&`, env assignments, and so on.
+    pub fn shell_command<S: Display>(command: S, workdir: Option<S>) -> Self {
+        Step::Command {
+            command: command.to_string(),
+            workdir: workdir.map(|s| s.to_string()),
+            shell: true,
+        }
+    }
+
+    /// This returns the argv list that buildbot runs for a command step
+    pub fn argv(&self) -> Vec<String> {
+        match self {
+            Step::Command {
+                command,
+                shell: true,
+                ..
+            } => vec![String::from("/bin/sh"), String::from("-c"), command.clone()],
+            // Builders verify their commands split properly before constructing the step,
+            // so we only fall back on splitting at whitespace for hand made steps.
+            Step::Command { command, .. } => shell_words(command)
+                .unwrap_or_else(|_| command.split_whitespace().map(String::from).collect()),
+            _ => vec![],
+        }
+    }
+
+    /// Converts the step into the buildbot step object passed to `addStep`
+    pub fn to_expr(&self) -> Expr {
+        let step = match self {
+            // Used by buildbot to get the updated repository
+            Step::GitClone { url, options } => Call::new("steps.Git")
+                .kwarg("repourl", Expr::lit(url))
+                .kwargs(options.kwargs()),
+
+            // Used by buildbot to get the updated repository from github
+            Step::GitHubClone { url, options } => Call::new("steps.GitHub")
+                .kwarg("repourl", Expr::lit(url))
+                .kwargs(options.kwargs()),
+
+            // Used by buildbot to get the updated repository from gitlab
+            Step::GitLabClone { url, options } => Call::new("steps.GitLab")
+                .kwarg("repourl", Expr::lit(url))
+                .kwargs(options.kwargs()),
+
+            // Command with provided work directory
+            Step::Command {
+                workdir: Some(workdir),
+                ..
+            } => Call::new("steps.ShellCommand")
+                .kwarg("command", Expr::lit(self.argv()))
+                .kwarg("workdir", Expr::lit(workdir)),
+
+            // Command without provided work directory
+            Step::Command { workdir: None, .. } => {
+                Call::new("steps.ShellCommand").kwarg("command", Expr::lit(self.argv()))
+            }
+        };
+        step.into()
+    }
+
     /// Construct a git clone step
-    pub fn git_clone<S: Display>(url: S) -> Self {
+    pub fn git_clone<S: Display>(url: S, options: CloneOptions) -> Self {
         Step::GitClone {
             url: url
                 .to_string()
@@ -42,12 +193,25 @@ impl Step {
                 .trim_start_matches('\"')
                 .trim_end_matches('\"')
                 .to_string(),
-            // branch: branch.to_string(),
+            options,
+        }
+    }
+
+    /// Construct a github clone step
+    pub fn github_clone<S: Display>(url: S, options: CloneOptions) -> Self {
+        Step::GitHubClone {
+            url: url
+                .to_string()
+                .trim()
+                .trim_start_matches('\"')
+                .trim_end_matches('\"')
+                .to_string(),
+            options,
         }
     }
 
     /// Construct a gitlab clone step
-    pub fn gitlab_clone<S: Display>(url: S) -> Self {
+    pub fn gitlab_clone<S: Display>(url: S, options: CloneOptions) -> Self {
         Step::GitLabClone {
             url: url
                 .to_string()
@@ -55,7 +219,7 @@ impl Step {
                 .trim_start_matches('\"')
                 .trim_end_matches('\"')
                 .to_string(),
-            // branch: branch.to_string(),
+            options,
         }
     }
 }
@@ -63,32 +227,6 @@ impl Step {
 /// Convert Step to String / Allow string formatting for Step
 impl Display for Step {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        match self {
-            // Used by buildbot to get the updated repository
-            Step::GitClone { url } => write!(f,
-                "steps.Git(repourl=\"{}\", mode=\"full\", branch=\"master\", method=\"clobber\", shallow=False, submodules=True)", url),
-
-            // Used by buildbot to get the updated repository
-            Step::GitLabClone { url } => write!(f,
-                "steps.GitLab(repourl=\"{}\", mode=\"full\", branch=\"master\", method=\"clobber\", shallow=False, submodules=True)", url),
-
-            // Command with provided work directory
-            Step::Command {command, workdir: Some(workdir)} => write!(
-                f, "steps.ShellCommand(command={command:?}, workdir=\"{workdir}\")",
-                command = command
-                    .split_whitespace()
-                    .map(String::from)
-                    .collect::<Vec<String>>(),
-                workdir = workdir
-            ),
-
-            // Command without provided work directory
-            Step::Command {command, workdir: None} => write!(
-                f, "steps.ShellCommand(command={:?})",
-                command.split_whitespace()
-                    .map(String::from)
-                    .collect::<Vec<String>>()
-            ),
-        }
+        write!(f, "{}", self.to_expr())
     }
 }
@@ -1,9 +1,63 @@
-use crate::unwrap;
-use rand::distributions::Alphanumeric;
-use rand::{thread_rng, Rng};
+use super::error::{empty_name, explain_typos, missing, require_sections, unknown_yaml_sections};
+use super::state::generate_password;
+use crate::helper::python_ast::{render, Call, Expr, Stmt};
+use crate::{unwrap, ConfigError};
 use rusty_yaml::Yaml;
+use std::convert::TryFrom;
 use std::fmt::{Display, Error, Formatter};
-use std::process::exit;
+
+/// The port the master listens on for workers, unless `master.worker-port` says otherwise
+pub const DEFAULT_WORKER_PORT: u16 = 9989;
+
+/// The `allow-shutdown` methods buildbot workers support
+pub const SHUTDOWN_METHODS: &[&str] = &["signal", "file"];
+
+/// Every section a worker can have. Anything else is reported as a typo.
+pub const WORKER_SECTIONS: &[&str] = &[
+    "master-ip",
+    "working-dir",
+    "keepalive",
+    "max-delay",
+    "umask",
+    "numcpus",
+    "max-retries",
+    "allow-shutdown",
+];
+
+/// These are the options for how a worker connects to the master.
+/// Each worker can override them in its YAML section with the
+/// `keepalive`, `max-delay`, `umask`, `numcpus`, `max-retries`,
+/// and `allow-shutdown` keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkerOptions {
+    /// How often, in seconds, the worker pings the master to keep the connection alive
+    pub keepalive: u32,
+    /// The longest time, in seconds, the worker waits between attempts to reconnect
+    pub max_delay: u32,
+    /// The umask of the worker process, or None to inherit it
+    pub umask: Option<u32>,
+    /// The number of CPUs the worker reports, or None to detect it
+    pub numcpus: Option<u32>,
+    /// How many times the worker tries to reconnect before giving up,
+    /// or None to keep trying forever
+    pub max_retries: Option<u32>,
+    /// How the master can ask the worker to shut down, one of `SHUTDOWN_METHODS`,
+    /// or None to not allow it
+    pub allow_shutdown: Option<String>,
+}
+
+impl Default for WorkerOptions {
+    fn default() -> Self {
+        Self {
+            keepalive: 600,
+            max_delay: 300,
+            umask: None,
+            numcpus: None,
+            max_retries: None,
+            allow_shutdown: None,
+        }
+    }
+}
 
 /// This struct holds the information that is used to build the worker `buildbot.tac` file
 /// Each worker has:
@@ -12,26 +66,64 @@ use std::process::exit;
 /// - a working directory name that the bot will be created in
 /// - the host address of the master bot, the ip
 /// - the port of the master bot
+/// - the options for its connection to the master
 #[derive(Clone)]
 pub struct Worker {
     name: String,
     dir: String,
     password: String,
     masterhost: String,
-    masterport: String,
+    masterport: u16,
+    options: WorkerOptions,
 }
 
 impl Worker {
-    fn new<S: ToString>(name: S, dir: S, password: S, masterhost: S, masterport: S) -> Self {
+    /// Starts building a worker named `name` in Rust, instead of reading it from YAML.
+    /// `working_dir` and `master_ip` must be set before calling `build`.
+    pub fn builder<S: ToString>(name: S) -> WorkerBuilder {
+        WorkerBuilder {
+            name: name.to_string(),
+            dir: None,
+            masterhost: None,
+            password: None,
+            options: WorkerOptions::default(),
+        }
+    }
+
+    fn new<S: ToString>(
+        name: S,
+        dir: S,
+        password: S,
+        masterhost: S,
+        masterport: u16,
+        options: WorkerOptions,
+    ) -> Self {
         Self {
             name: name.to_string(),
             dir: dir.to_string(),
             password: password.to_string(),
             masterhost: masterhost.to_string(),
-            masterport: masterport.to_string(),
+            masterport,
+            options,
         }
     }
 
+    /// Sets the port the worker connects to the master on.
+    /// The master config sets this from its `worker-port` section.
+    pub fn set_master_port(&mut self, port: u16) {
+        self.masterport = port;
+    }
+
+    /// Retrieves the port the worker connects to the master on
+    pub fn get_master_port(&self) -> u16 {
+        self.masterport
+    }
+
+    /// Retrieves the options for the worker's connection to the master
+    pub fn get_options(&self) -> WorkerOptions {
+        self.options.clone()
+    }
+
     /// Retrieves the name field of the struct
     pub fn get_name(&self) -> String {
         self.name.clone()
@@ -46,32 +138,338 @@ impl Worker {
     pub fn get_password(&self) -> String {
         self.password.clone()
     }
+
+    /// Sets the password the worker uses to log in to the master
+    pub fn set_password<S: ToString>(&mut self, password: S) {
+        self.password = password.to_string();
+    }
+
+    /// The `worker.Worker` object the master uses to recognize this worker
+    pub fn to_expr(&self) -> Expr {
+        Call::new("worker.Worker")
+            .arg(Expr::lit(&self.name))
+            .arg(Expr::lit(&self.password))
+            .into()
+    }
+
+    /// The Python statements of the worker's `buildbot.tac` file
+    pub fn statements(&self) -> Vec<Stmt> {
+        vec![
+            Stmt::raw("import os"),
+            Stmt::Blank,
+            Stmt::raw("from buildbot_worker.bot import Worker"),
+            Stmt::raw("from twisted.application import service"),
+            Stmt::Blank,
+            Stmt::assign("basedir", Expr::lit(".")),
+            Stmt::assign("rotateLength", Expr::lit(10_000_000u32)),
+            Stmt::assign("maxRotatedFiles", Expr::lit(10u32)),
+            Stmt::Blank,
+            Stmt::comment(
+                "if this is a relocatable tac file, get the directory containing the TAC",
+            ),
+            Stmt::if_(
+                Expr::name("basedir").op("==", Expr::lit(".")),
+                vec![
+                    Stmt::raw("import os.path"),
+                    Stmt::assign(
+                        "basedir",
+                        Call::new("os.path.abspath")
+                            .arg(Call::new("os.path.dirname").arg("__file__")),
+                    ),
+                ],
+            ),
+            Stmt::Blank,
+            Stmt::comment(
+                "note: this line is matched against to check that this is a worker\n\
+                 directory; do not edit it.",
+            ),
+            Stmt::assign(
+                "application",
+                Call::new("service.Application").arg(Expr::lit("buildbot-worker")),
+            ),
+            Stmt::Blank,
+            Stmt::raw("from twisted.python.logfile import LogFile"),
+            Stmt::raw("from twisted.python.log import ILogObserver, FileLogObserver"),
+            Stmt::assign(
+                "logfile",
+                Call::new("LogFile.fromFullPath")
+                    .arg(
+                        Call::new("os.path.join")
+                            .arg("basedir")
+                            .arg(Expr::lit("twistd.log")),
+                    )
+                    .kwarg("rotateLength", "rotateLength")
+                    .kwarg("maxRotatedFiles", "maxRotatedFiles"),
+            ),
+            Stmt::expr(
+                Expr::name("application")
+                    .method("setComponent")
+                    .arg("ILogObserver")
+                    .arg(Expr::from(Call::new("FileLogObserver").arg("logfile")).attr("emit")),
+            ),
+            Stmt::Blank,
+            Stmt::assign("buildmaster_host", Expr::lit(&self.masterhost)),
+            Stmt::assign("port", Expr::lit(self.masterport)),
+            Stmt::assign("workername", Expr::lit(&self.name)),
+            Stmt::assign("passwd", Expr::lit(&self.password)),
+            Stmt::assign("keepalive", Expr::lit(self.options.keepalive)),
+            Stmt::assign(
+                "umask",
+                match self.options.umask {
+                    Some(umask) => Expr::raw(format!("0o{:03o}", umask)),
+                    None => Expr::lit(None::<u32>),
+                },
+            ),
+            Stmt::assign("maxdelay", Expr::lit(self.options.max_delay)),
+            Stmt::assign("numcpus", Expr::lit(self.options.numcpus)),
+            Stmt::assign("allow_shutdown", Expr::lit(&self.options.allow_shutdown)),
+            Stmt::assign("maxretries", Expr::lit(self.options.max_retries)),
+            Stmt::Blank,
+            Stmt::assign(
+                "s",
+                Call::new("Worker")
+                    .arg("buildmaster_host")
+                    .arg("port")
+                    .arg("workername")
+                    .arg("passwd")
+                    .arg("basedir")
+                    .arg("keepalive")
+                    .kwarg("umask", "umask")
+                    .kwarg("maxdelay", "maxdelay")
+                    .kwarg("numcpus", "numcpus")
+                    .kwarg("allow_shutdown", "allow_shutdown")
+                    .kwarg("maxRetries", "maxretries"),
+            ),
+            Stmt::expr(
+                Expr::name("s")
+                    .method("setServiceParent")
+                    .arg("application"),
+            ),
+        ]
+    }
+}
+
+/// Reads an optional whole number section of a worker
+fn number(yaml: &Yaml, section: &str, context: &str, errors: &mut Vec<ConfigError>) -> Option<u32> {
+    if !yaml.has_section(section) {
+        return None;
+    }
+
+    let value = unwrap(yaml, section);
+    match value.parse::<u32>() {
+        Ok(n) => Some(n),
+        Err(_) => {
+            errors.push(ConfigError::BadValue {
+                context: context.to_string(),
+                section: section.to_string(),
+                value,
+                reason: String::from("expected a whole number"),
+            });
+            None
+        }
+    }
+}
+
+/// Reads the optional sections of a worker that control its connection to the master
+fn worker_options(yaml: &Yaml, context: &str) -> Result<WorkerOptions, Vec<ConfigError>> {
+    let mut options = WorkerOptions::default();
+    let mut errors = vec![];
+
+    if let Some(keepalive) = number(yaml, "keepalive", context, &mut errors) {
+        options.keepalive = keepalive;
+    }
+    if let Some(max_delay) = number(yaml, "max-delay", context, &mut errors) {
+        options.max_delay = max_delay;
+    }
+    options.numcpus = number(yaml, "numcpus", context, &mut errors);
+    options.max_retries = number(yaml, "max-retries", context, &mut errors);
+
+    // Umasks are written in octal, like `022`.
+    // YAML reads an unquoted `022` as the number 22, which has the same digits.
+    if yaml.has_section("umask") {
+        let umask = unwrap(yaml, "umask");
+        match u32::from_str_radix(umask.trim_start_matches("0o"), 8) {
+            Ok(n) if n <= 0o777 => options.umask = Some(n),
+            _ => errors.push(ConfigError::BadValue {
+                context: context.to_string(),
+                section: String::from("umask"),
+                value: umask,
+                reason: String::from("the umask must be an octal number like 022"),
+            }),
+        }
+    }
+
+    if yaml.has_section("allow-shutdown") {
+        let method = unwrap(yaml, "allow-shutdown");
+        if SHUTDOWN_METHODS.contains(&method.as_str()) {
+            options.allow_shutdown = Some(method);
+        } else {
+            errors.push(ConfigError::BadValue {
+                context: context.to_string(),
+                section: String::from("allow-shutdown"),
+                value: method,
+                reason: format!(
+                    "the shutdown method must be one of {}",
+                    SHUTDOWN_METHODS.join(", ")
+                ),
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(options)
+    } else {
+        Err(errors)
+    }
+}
+
+/// This builds a Worker with the same sections the YAML uses.
+/// Every mistake is collected and returned from `build`, like `Worker::try_from` does.
+#[derive(Clone, Debug)]
+pub struct WorkerBuilder {
+    name: String,
+    dir: Option<String>,
+    masterhost: Option<String>,
+    password: Option<String>,
+    options: WorkerOptions,
+}
+
+impl WorkerBuilder {
+    /// The path of the worker's working directory, the `working-dir` section
+    pub fn working_dir<S: ToString>(mut self, dir: S) -> Self {
+        self.dir = Some(dir.to_string());
+        self
+    }
+
+    /// The host address of the master, the `master-ip` section
+    pub fn master_ip<S: ToString>(mut self, host: S) -> Self {
+        self.masterhost = Some(host.to_string());
+        self
+    }
+
+    /// The password the worker logs in to the master with.
+    /// If this isn't set, a random password is generated.
+    pub fn password<S: ToString>(mut self, password: S) -> Self {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    /// Replaces every option for the worker's connection to the master
+    pub fn options(mut self, options: WorkerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn keepalive(mut self, seconds: u32) -> Self {
+        self.options.keepalive = seconds;
+        self
+    }
+
+    pub fn max_delay(mut self, seconds: u32) -> Self {
+        self.options.max_delay = seconds;
+        self
+    }
+
+    pub fn umask(mut self, umask: u32) -> Self {
+        self.options.umask = Some(umask);
+        self
+    }
+
+    pub fn numcpus(mut self, numcpus: u32) -> Self {
+        self.options.numcpus = Some(numcpus);
+        self
+    }
+
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.options.max_retries = Some(retries);
+        self
+    }
+
+    /// How the master can shut down the worker, one of `SHUTDOWN_METHODS`
+    pub fn allow_shutdown<S: ToString>(mut self, method: S) -> Self {
+        self.options.allow_shutdown = Some(method.to_string());
+        self
+    }
+
+    /// Builds the worker, or returns everything wrong with it
+    pub fn build(self) -> Result<Worker, Vec<ConfigError>> {
+        let context = format!("worker '{}'", self.name);
+        let mut errors = vec![];
+
+        if self.name.is_empty() {
+            errors.push(empty_name(&context));
+        }
+        if self.dir.is_none() {
+            errors.push(missing(&context, "working-dir"));
+        }
+        if self.masterhost.is_none() {
+            errors.push(missing(&context, "master-ip"));
+        }
+
+        if let Some(umask) = self.options.umask {
+            if umask > 0o777 {
+                errors.push(ConfigError::BadValue {
+                    context: context.clone(),
+                    section: String::from("umask"),
+                    value: format!("{:o}", umask),
+                    reason: String::from("the umask must be an octal number like 022"),
+                });
+            }
+        }
+        if let Some(method) = &self.options.allow_shutdown {
+            if !SHUTDOWN_METHODS.contains(&method.as_str()) {
+                errors.push(ConfigError::BadValue {
+                    context,
+                    section: String::from("allow-shutdown"),
+                    value: method.clone(),
+                    reason: format!(
+                        "the shutdown method must be one of {}",
+                        SHUTDOWN_METHODS.join(", ")
+                    ),
+                });
+            }
+        }
+
+        match (self.dir, self.masterhost) {
+            (Some(dir), Some(masterhost)) if errors.is_empty() => Ok(Worker::new(
+                self.name,
+                dir,
+                // The master config replaces this with the saved password, if there is one
+                self.password.unwrap_or_else(generate_password),
+                masterhost,
+                // The master config sets the real port from its `worker-port` section
+                DEFAULT_WORKER_PORT,
+                self.options,
+            )),
+            _ => Err(errors),
+        }
+    }
 }
 
 /// Convert a Yaml section to a Worker
 ///
-/// The worker requires that the yaml section has the following subsections:
-/// `masterhost`, `masterport`, `password`, and `basedir`.
-/// Masterhost holds the host address of the master bot,
-/// Masterport hold the host port of the master bot.
-/// Basedir holds the path of the working directory of the bot
-impl From<Yaml> for Worker {
-    fn from(yaml: Yaml) -> Self {
+/// The worker requires that the yaml section has the `master-ip` and `working-dir` subsections.
+/// `master-ip` holds the host address of the master bot, and `working-dir`
+/// holds the path of the working directory of the bot.
+/// The connection to the master can be tuned with the optional subsections read by `worker_options`.
+impl TryFrom<Yaml> for Worker {
+    type Error = Vec<ConfigError>;
+
+    fn try_from(yaml: Yaml) -> Result<Self, Self::Error> {
         let name = yaml.get_name();
+        let context = format!("worker '{}'", name);
 
-        for section in ["master-ip", "working-dir"].iter() {
-            if !yaml.has_section(section) {
-                error!("There was an error creating a worker: The '{}' section is not specified for '{}'", section, name);
-                exit(1);
-            }
+        let mut errors = require_sections(&yaml, &["master-ip", "working-dir"], &context);
+        errors.extend(unknown_yaml_sections(&yaml, WORKER_SECTIONS, &context));
+        if !errors.is_empty() {
+            return Err(explain_typos(errors));
         }
 
-        let password: String = thread_rng().sample_iter(&Alphanumeric).take(30).collect();
-        let basedir = unwrap(&yaml, "working-dir");
-        let masterhost = unwrap(&yaml, "master-ip");
-
-        // Now, instead of getting the master port from the Yaml object, we just use 9989.
-        Self::new(name, basedir, password, masterhost, String::from("9989"))
+        Worker::builder(name)
+            .working_dir(unwrap(&yaml, "working-dir"))
+            .master_ip(unwrap(&yaml, "master-ip"))
+            .options(worker_options(&yaml, &context)?)
+            .build()
     }
 }
 
@@ -79,55 +477,6 @@ impl From<Yaml> for Worker {
 /// This returns the Python `buildbot.tac` file for an individual worker.
 impl Display for Worker {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        writeln!(
-            f,
-            r#"import os
-
-from buildbot_worker.bot import Worker
-from twisted.application import service
-
-basedir = '.'
-rotateLength = 10000000
-maxRotatedFiles = 10
-
-# if this is a relocatable tac file, get the directory containing the TAC
-if basedir == '.':
-    import os.path
-    basedir = os.path.abspath(os.path.dirname(__file__))
-
-# note: this line is matched against to check that this is a worker
-# directory; do not edit it.
-application = service.Application('buildbot-worker')
-
-from twisted.python.logfile import LogFile
-from twisted.python.log import ILogObserver, FileLogObserver
-logfile = LogFile.fromFullPath(
-    os.path.join(basedir, "twistd.log"), rotateLength=rotateLength,
-    maxRotatedFiles=maxRotatedFiles)
-application.setComponent(ILogObserver, FileLogObserver(logfile).emit)
-
-buildmaster_host = '{masterhost}'
-port = {masterport}
-workername = '{name}'
-passwd = '{password}'
-keepalive = 600
-umask = None
-maxdelay = 300
-numcpus = None
-allow_shutdown = None
-maxretries = None
-
-s = Worker(buildmaster_host, port, workername, passwd, basedir,
-           keepalive, umask=umask, maxdelay=maxdelay,
-           numcpus=numcpus, allow_shutdown=allow_shutdown,
-           maxRetries=maxretries)
-s.setServiceParent(application)
-
-"#,
-            name = self.name,
-            password = self.password,
-            masterhost = self.masterhost,
-            masterport = self.masterport
-        )
+        write!(f, "{}", render(&self.statements()))
     }
 }
@@ -1,5 +1,5 @@
 use crate::buildsystem::BuildSystem;
-use crate::{yes_or_no, File, AUTH_TOKEN_PATH};
+use crate::{yes_or_no, File, AUTH_TOKEN_PATH, INSTALL_SCRIPT, INSTALL_SCRIPT_PATH};
 use std::process::exit;
 
 /// This is the best build system rusty-ci supports right now.
@@ -19,18 +19,7 @@ impl BuildSystem for Bash {
             exit(0);
         }
         info!("Writing install file to `./install.sh`");
-        File::write(
-            "install.sh",
-            "#!/bin/sh
-
-python3 -m venv venv 2>&1
-. venv/bin/activate
-
-python3 -m pip install -U pip >/dev/null
-python3 -m pip install txrequests treq 'buildbot[bundle]' >/dev/null
-python3 -m pip install buildbot-worker setuptools-trial >/dev/null
-",
-        )?;
+        File::write(INSTALL_SCRIPT_PATH, INSTALL_SCRIPT)?;
         info!("Successfully wrote install file");
         warn!("To install dependencies run `install.sh`");
         warn!("Before building from a YAML file, be sure to run `. venv/bin/activate`");
@@ -1,5 +1,5 @@
 use crate::{yes_or_no, File};
-use crate::{BuildSystem, AUTH_TOKEN_PATH};
+use crate::{BuildSystem, AUTH_TOKEN_PATH, INSTALL_MAKEFILE, INSTALL_MAKEFILE_PATH};
 use std::process::exit;
 
 #[derive(Default)]
@@ -15,13 +15,7 @@ impl BuildSystem for Makefile {
             exit(0);
         }
         info!("Writing install file to `./Makefile`");
-        File::write("Makefile", ".SILENT: install
-
-install:
-\tpython3 -m venv venv
-\t. venv/bin/activate; python3 -m pip install -U pip >/dev/null; python3 -m pip install txrequests treq 'buildbot[bundle]' >/dev/null;
-\t. venv/bin/activate; python3 -m pip install buildbot-worker setuptools-trial >/dev/null
-")?;
+        File::write(INSTALL_MAKEFILE_PATH, INSTALL_MAKEFILE)?;
         info!("Successfully wrote Makefile");
         warn!("To install dependencies run `make install`");
         info!(
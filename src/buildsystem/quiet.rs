@@ -1,5 +1,5 @@
 use crate::buildsystem::BuildSystem;
-use crate::{Cmd, File, MasterConfig, Worker, AUTH_TOKEN_PATH};
+use crate::{File, MasterConfig, Worker, AUTH_TOKEN_PATH, INSTALL_SCRIPT, INSTALL_SCRIPT_PATH};
 
 /// This struct is identical to the Bash buildsystem,
 /// except that it does not confirm anything with the user at all.
@@ -20,6 +20,7 @@ impl BuildSystem for Quiet {
         self.create_workers(&workers)?;
         info!("Writing to master/master.cfg...");
         self.write_master_config(&master)?;
+        self.write_secrets(&master)?;
         info!("Writing to worker configs...");
         self.write_worker_configs(&workers)?;
         info!("Reconfiguring master...");
@@ -30,18 +31,7 @@ impl BuildSystem for Quiet {
     /// Writes install script to `install.sh` for user to run
     fn install(&mut self) -> Result<(), String> {
         info!("Writing install file to `./install.sh`");
-        File::write(
-            "install.sh",
-            "#!/bin/sh
-
-python3 -m venv venv 2>&1
-. venv/bin/activate
-
-python3 -m pip install -U pip >/dev/null
-python3 -m pip install txrequests treq 'buildbot[bundle]' >/dev/null
-python3 -m pip install buildbot-worker setuptools-trial >/dev/null
-",
-        )?;
+        File::write(INSTALL_SCRIPT_PATH, INSTALL_SCRIPT)?;
         info!("Successfully wrote install file");
         warn!("To install dependencies run `install.sh`");
         warn!("Before building from a YAML file, be sure to run `. venv/bin/activate`");
@@ -63,21 +53,19 @@ python3 -m pip install buildbot-worker setuptools-trial >/dev/null
         self.create_workers(&workers)?;
         info!("Writing to master/master.cfg...");
         self.write_master_config(&master)?;
+        self.write_secrets(&master)?;
         info!("Writing to worker configs...");
         self.write_worker_configs(&workers)?;
         info!("Next, run the `start` subcommand to execute the master and the workers");
         Ok(())
     }
 
-    /// This kills the buildbot workers and all instances of python and python3
-    fn stop(&mut self) -> Result<(), String> {
-        info!("Killing python...");
-        Cmd::new("killall").arg("python").run();
-        info!("Killing python3...");
-        Cmd::new("killall").arg("python3").run();
-        info!("Killing workers...");
-        Cmd::new("killall").arg("buildbot-worker").run();
-        Ok(())
+    /// This stops the master if `master` is true, and each of `workers`
+    fn stop(&mut self, master: bool, workers: &[Worker]) -> Result<(), String> {
+        if master {
+            self.stop_master()?;
+        }
+        self.stop_workers(workers)
     }
 
     /// This starts the master and the workers
@@ -1,4 +1,7 @@
-use crate::{yes_or_no, Cmd, File, MasterConfig, Worker, AUTH_TOKEN_PATH};
+use crate::helper::process::{stop_dir, Stopped, STOP_TIMEOUT};
+use crate::{
+    yes_or_no, Cmd, File, MasterConfig, Worker, AUTH_TOKEN_PATH, MASTER_CFG_PATH, SECRETS_DIR_PATH,
+};
 
 use std::path::PathBuf;
 use std::process::exit;
@@ -54,6 +57,7 @@ pub trait BuildSystem {
         self.create_workers(&workers)?;
         info!("Writing to master/master.cfg...");
         self.write_master_config(&master)?;
+        self.write_secrets(&master)?;
         info!("Writing to worker configs...");
         self.write_worker_configs(&workers)?;
         info!("Reconfiguring master...");
@@ -77,6 +81,7 @@ pub trait BuildSystem {
         self.create_workers(&workers)?;
         info!("Writing to master/master.cfg...");
         self.write_master_config(&master)?;
+        self.write_secrets(&master)?;
         info!("Writing to worker configs...");
         self.write_worker_configs(&workers)?;
         info!("Next, run the `start` subcommand to execute the master and the workers");
@@ -96,26 +101,41 @@ pub trait BuildSystem {
         Ok(())
     }
 
-    /// This kills the buildbot workers and all instances of python and python3
-    fn stop(&mut self) -> Result<(), String> {
-        if !yes_or_no("Are you sure you want to kill rusty-ci? This will also kill all python processes! (y/n) ") {
+    /// This stops the master if `master` is true, and each of `workers`.
+    /// Only the processes in their `twistd.pid` files are stopped.
+    fn stop(&mut self, master: bool, workers: &[Worker]) -> Result<(), String> {
+        if !yes_or_no("Are you sure you want to stop rusty-ci? (y/n) ") {
             error!("You weren't sure!");
             exit(0);
         }
 
-        info!("Killing python...");
-        Cmd::new("killall").arg("python").run();
-        info!("Killing python3...");
-        Cmd::new("killall").arg("python3").run();
-        info!("Killing workers...");
-        Cmd::new("killall").arg("buildbot-worker").run();
+        if master {
+            self.stop_master()?;
+        }
+        self.stop_workers(workers)
+    }
+
+    /// This method is used by the `stop` and `start` methods to shut down the master
+    fn stop_master(&mut self) -> Result<(), String> {
+        info!("Stopping master...");
+        report_stopped("master", stop_dir("master", STOP_TIMEOUT)?);
+        Ok(())
+    }
+
+    /// This method is used by the `stop` and `start` methods to shut down the workers
+    fn stop_workers(&mut self, workers: &[Worker]) -> Result<(), String> {
+        for worker in workers {
+            info!("Stopping worker \"{}\"...", worker.get_name());
+            let stopped = stop_dir(worker.get_dir(), STOP_TIMEOUT)?;
+            report_stopped(&format!("worker \"{}\"", worker.get_name()), stopped);
+        }
         Ok(())
     }
 
     /// This method is used by the `rebuild` method to update the master without killing it
     fn reconfigure_master(&mut self) -> Result<(), String> {
         let buildbot = |sub_command| -> Result<(), String> {
-            Cmd::new("buildbot").arg(sub_command).arg("master").run();
+            Cmd::new("buildbot").arg(sub_command).arg("master").run()?;
             Ok(())
         };
 
@@ -127,12 +147,11 @@ pub trait BuildSystem {
     /// This method is used by the `start` method to spin up the master
     fn start_master(&mut self) -> Result<(), String> {
         let buildbot = |sub_command| -> Result<(), String> {
-            Cmd::new("buildbot").arg(sub_command).arg("master").run();
+            Cmd::new("buildbot").arg(sub_command).arg("master").run()?;
             Ok(())
         };
 
-        buildbot("stop")?;
-        buildbot("reconfig")?;
+        self.stop_master()?;
         buildbot("cleanupdb")?;
         buildbot("start")?;
         Ok(())
@@ -141,10 +160,11 @@ pub trait BuildSystem {
     /// This method is used by the `start` method to spin up the workers
     fn start_workers(&mut self, workers: &[Worker]) -> Result<(), String> {
         let start_worker = |dir| -> Result<(), String> {
-            Cmd::new("buildbot-worker").arg("restart").arg(&dir).run();
+            Cmd::new("buildbot-worker").arg("start").arg(&dir).run()?;
             Ok(())
         };
 
+        self.stop_workers(workers)?;
         for worker in workers {
             start_worker(worker.get_dir())?;
         }
@@ -161,7 +181,7 @@ pub trait BuildSystem {
                 .arg("localhost")
                 .arg(&dir)
                 .arg("pass")
-                .run();
+                .run()?;
 
             Ok(())
         };
@@ -197,18 +217,28 @@ pub trait BuildSystem {
         Cmd::new("buildbot")
             .arg("create-master")
             .arg("master")
-            .run();
+            .run()?;
         Ok(())
     }
 
     /// Writes the master configuration file
     fn write_master_config(&mut self, master: &MasterConfig) -> Result<(), String> {
-        match File::write("master/master.cfg", master.to_string()) {
+        match File::write(MASTER_CFG_PATH, master.to_string()) {
             Err(e) => Err(e + "\nDid you enter your venv by running `. venv/bin/activate`?"),
             Ok(()) => Ok(()),
         }
     }
 
+    /// Writes each of the master's secrets to its own file in the secrets directory,
+    /// which only the current user can read, for `master.cfg` to read them from.
+    /// The secrets the master doesn't have anymore are removed.
+    fn write_secrets(&mut self, master: &MasterConfig) -> Result<(), String> {
+        if !master.get_secrets().is_empty() {
+            info!("Writing secrets to {}...", SECRETS_DIR_PATH);
+        }
+        crate::write_secrets(master, "")
+    }
+
     /// This method installs Python.
     /// You probably do need to overload this, I dont know
     /// for sure if it completely works. The bash impl of the buildsystem
@@ -219,7 +249,7 @@ pub trait BuildSystem {
                 .arg("install")
                 .arg(package)
                 .arg("-y")
-                .run();
+                .run()?;
             Ok(())
         };
 
@@ -239,7 +269,7 @@ pub trait BuildSystem {
             .arg("install")
             .arg("-U")
             .arg("pip")
-            .run();
+            .run()?;
 
         Cmd::new("python3")
             .arg("-m")
@@ -247,15 +277,15 @@ pub trait BuildSystem {
             .arg("install")
             .arg("-U")
             .arg("buildbot[bundle]")
-            .run();
+            .run()?;
 
         Cmd::new("python3")
             .arg("-m")
             .arg("pip")
             .arg("install")
             .arg("-U")
-            .arg("txrequest")
-            .run();
+            .arg("txrequests")
+            .run()?;
 
         Cmd::new("python3")
             .arg("-m")
@@ -263,7 +293,7 @@ pub trait BuildSystem {
             .arg("install")
             .arg("-U")
             .arg("treq")
-            .run();
+            .run()?;
 
         Cmd::new("python3")
             .arg("-m")
@@ -271,8 +301,28 @@ pub trait BuildSystem {
             .arg("install")
             .arg("buildbot-worker")
             .arg("setuptools-trial")
-            .run();
+            .run()?;
 
         Ok(())
     }
 }
+
+/// Tells the user what happened when a master or worker was stopped
+fn report_stopped(what: &str, stopped: Stopped) {
+    match stopped {
+        Stopped::NotRunning => {
+            info!("The {} wasn't running", what);
+        }
+        Stopped::Gracefully(pid) => {
+            info!("Stopped the {} (pid {})", what, pid);
+        }
+        Stopped::Killed(pid) => {
+            warn!(
+                "The {} (pid {}) didn't stop within {} seconds, so it was killed",
+                what,
+                pid,
+                STOP_TIMEOUT.as_secs()
+            );
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use super::source::{Source, Span};
+use crate::ConfigError;
+use std::fmt::{Display, Error, Formatter};
+
+/// The file a diagnostic is in, and where it points in it
+#[derive(Clone, Debug, PartialEq)]
+struct Place {
+    path: String,
+    location: Option<Location>,
+}
+
+/// Where a diagnostic points in a configuration file
+#[derive(Clone, Debug, PartialEq)]
+struct Location {
+    line: usize,
+    column: usize,
+    /// The line of the file the span starts on
+    snippet: String,
+    /// How many characters of the snippet to underline
+    width: usize,
+}
+
+/// A configuration error, with the file and the line it was found on.
+/// This is displayed like rustc displays its errors: the message,
+/// the offending line with the mistake underlined, and a hint to fix it.
+///
+/// Not every error can be placed on a line. A section that is missing
+/// from the whole file, for example, only points at the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    error: ConfigError,
+    place: Option<Box<Place>>,
+}
+
+impl Diagnostic {
+    /// An error that points at `span` in `source`
+    pub(crate) fn at(error: ConfigError, source: &Source, span: Span) -> Self {
+        let (line, column) = source.line_and_column(span.start);
+        let snippet = source.line_of(span.start);
+        // Only the first line of a span is shown
+        let rest = snippet.chars().count().saturating_sub(column - 1);
+        let text = source.get_text();
+        let width = text
+            .get(span.start..span.end.min(text.len()))
+            .map(|s| s.lines().next().unwrap_or("").chars().count())
+            .unwrap_or(0)
+            .clamp(1, rest.max(1));
+        Self {
+            error,
+            place: Some(Box::new(Place {
+                path: source.get_path(),
+                location: Some(Location {
+                    line,
+                    column,
+                    snippet,
+                    width,
+                }),
+            })),
+        }
+    }
+
+    /// An error in `source` that can't be placed on a line
+    pub(crate) fn in_file(error: ConfigError, source: &Source) -> Self {
+        Self {
+            error,
+            place: Some(Box::new(Place {
+                path: source.get_path(),
+                location: None,
+            })),
+        }
+    }
+
+    /// Finds where `error` was made in `source`, using the context and section
+    /// that the buildbot constructors describe their errors with.
+    pub fn locate(error: ConfigError, source: &Source) -> Self {
+        match span_of(&error, source) {
+            Some(span) => Self::at(error, source, span),
+            None => Self::in_file(error, source),
+        }
+    }
+
+    /// The error this diagnostic describes
+    pub fn get_error(&self) -> ConfigError {
+        self.error.clone()
+    }
+
+    /// The path of the file the error was found in
+    pub fn get_path(&self) -> Option<String> {
+        self.place.as_ref().map(|p| p.path.clone())
+    }
+
+    fn location(&self) -> Option<&Location> {
+        self.place.as_ref().and_then(|p| p.location.as_ref())
+    }
+
+    /// The 1-based line the error was found on
+    pub fn get_line(&self) -> Option<usize> {
+        self.location().map(|l| l.line)
+    }
+
+    /// The 1-based column the error was found at
+    pub fn get_column(&self) -> Option<usize> {
+        self.location().map(|l| l.column)
+    }
+
+    /// What to write next to the underlined mistake.
+    /// The parsers' messages are too long for it, so file errors only get underlined.
+    fn label(&self) -> Option<String> {
+        match &self.error {
+            ConfigError::MissingSection { section, .. } => Some(format!("missing `{}`", section)),
+            ConfigError::UnknownSection { .. } => Some(String::from("unknown section")),
+            ConfigError::BadValue { reason, .. } => Some(reason.clone()),
+            ConfigError::Unresolved { .. } => Some(String::from("this can't be resolved")),
+            ConfigError::UnknownVcs(_) => Some(String::from("unknown version control system")),
+            ConfigError::UnmatchedQuotes { .. } => {
+                Some(String::from("this line has an unmatched quote"))
+            }
+            ConfigError::DuplicateName { name, .. } | ConfigError::DefinedTwice { name, .. } => {
+                Some(format!("'{}' is defined again here", name))
+            }
+            ConfigError::MissingAuthToken { reason, .. } => Some(reason.clone()),
+            ConfigError::File { .. } => None,
+        }
+    }
+
+    /// A hint for fixing the mistake
+    pub fn get_hint(&self) -> Option<String> {
+        match &self.error {
+            ConfigError::MissingSection { context, section } => {
+                Some(format!("add a `{}` section to {}", section, context))
+            }
+            ConfigError::Unresolved { reference, .. } if !reference.starts_with("${file:") => {
+                Some(String::from(
+                    "if this is meant for the shell running a script, write `$${` instead of `${`",
+                ))
+            }
+            ConfigError::UnknownVcs(_) => Some(String::from(
+                "use `github`, `gitlab`, or `none` for plain git",
+            )),
+            ConfigError::UnmatchedQuotes { .. } => Some(String::from(
+                "close the quote, or put the whole value in single quotes",
+            )),
+            ConfigError::DuplicateName { kind, name } => {
+                Some(format!("rename one of the {}s named '{}'", kind, name))
+            }
+            ConfigError::DefinedTwice { kind, first, .. } if kind == "section" => {
+                Some(format!("remove it from this file or '{}'", first))
+            }
+            ConfigError::DefinedTwice { first, .. } => Some(format!(
+                "rename this one, or remove it from this file or '{}'",
+                first
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigError> for Diagnostic {
+    fn from(error: ConfigError) -> Self {
+        Self { error, place: None }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "error: {}", self.error)?;
+        let gutter = match self.location() {
+            Some(l) => l.line.to_string().len(),
+            None => 1,
+        };
+        let pad = " ".repeat(gutter);
+
+        match (self.get_path(), self.location()) {
+            (Some(path), Some(l)) => {
+                write!(f, "\n{}--> {}:{}:{}", pad, path, l.line, l.column)?;
+                write!(f, "\n{} |", pad)?;
+                write!(f, "\n{} | {}", l.line, l.snippet)?;
+                write!(
+                    f,
+                    "\n{} | {}{}",
+                    pad,
+                    indent_like(&l.snippet, l.column - 1),
+                    "^".repeat(l.width)
+                )?;
+                if let Some(label) = self.label() {
+                    write!(f, " {}", label)?;
+                }
+            }
+            (Some(path), None) => write!(f, "\n{}--> {}", pad, path)?,
+            _ => {}
+        }
+
+        if let Some(hint) = self.get_hint() {
+            write!(f, "\n{} |\n{} = help: {}", pad, pad, hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Whitespace as wide as the first `chars` characters of `line`, keeping its tabs
+fn indent_like(line: &str, chars: usize) -> String {
+    line.chars()
+        .take(chars)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+/// The path of keys to the object an error context like "builder 'cargo-test'"
+/// describes, or `None` if it isn't in this file.
+fn context_path(context: &str) -> Option<Vec<String>> {
+    match context {
+        "master" => Some(vec![String::from("master")]),
+        "the merge request handler" => Some(vec![String::from("merge-request-handler")]),
+        "the 'extra-recipients' subsection of the mail notifier" => {
+            Some(vec![String::from("extra-recipients")])
+        }
+        "the master configuration" | "the mail notifier" => Some(vec![]),
+        _ => {
+            let (kind, name) = context.split_at(context.find(" '")?);
+            let name = name[2..].strip_suffix('\'')?;
+            match kind {
+                "worker" | "builder" | "scheduler" => {
+                    Some(vec![format!("{}s", kind), name.to_string()])
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Finds the span a configuration error should point at
+fn span_of(error: &ConfigError, source: &Source) -> Option<Span> {
+    match error {
+        // Point at the object that is missing the section
+        ConfigError::MissingSection { context, .. } => {
+            let path = context_path(context)?;
+            if path.is_empty() {
+                None
+            } else {
+                source.key_span(&path)
+            }
+        }
+        ConfigError::UnknownSection {
+            context, section, ..
+        } => {
+            let mut path = context_path(context)?;
+            path.push(section.clone());
+            source.key_span(&path)
+        }
+        ConfigError::BadValue {
+            context,
+            section,
+            value,
+            ..
+        } => {
+            let mut path = context_path(context)?;
+            path.push(section.clone());
+            source
+                .find_in(&path, value)
+                .or_else(|| source.value_span(&path))
+        }
+        ConfigError::Unresolved {
+            context,
+            section,
+            reference,
+            ..
+        } => {
+            let mut path = context_path(context)?;
+            path.push(section.clone());
+            source
+                .find_in(&path, reference)
+                .or_else(|| source.value_span(&path))
+        }
+        ConfigError::UnknownVcs(vcs) => {
+            let handler = ["merge-request-handler", "version-control-system"];
+            // A builder can choose its own VCS too
+            source.find_in(&handler, vcs).or_else(|| {
+                source
+                    .get_children(&["builders"])
+                    .iter()
+                    .find_map(|name| source.find_in(&["builders", name, "vcs"], vcs))
+            })
+        }
+        ConfigError::UnmatchedQuotes { line, .. } => source.find_line(line),
+        ConfigError::DuplicateName { kind, name } => {
+            source.key_span(&[format!("{}s", kind), name.clone()])
+        }
+        ConfigError::DefinedTwice { kind, name, .. } => source.key_span(&defined_path(kind, name)),
+        ConfigError::MissingAuthToken { .. } | ConfigError::File { .. } => None,
+    }
+}
+
+/// The path of keys to a section that `DefinedTwice` names
+fn defined_path(kind: &str, name: &str) -> Vec<String> {
+    match kind {
+        "worker" | "builder" | "scheduler" => vec![format!("{}s", kind), name.to_string()],
+        _ => vec![name.to_string()],
+    }
+}
+
+/// Picks which of the files a configuration was merged from an error was made in.
+/// That's the file that defines the object the error is about, if one does,
+/// or else the first file the error can be placed in.
+pub(crate) fn source_of<'a>(error: &ConfigError, sources: &'a [Source]) -> Option<&'a Source> {
+    let path = match error {
+        ConfigError::DefinedTwice { second, .. } => {
+            return sources.iter().find(|source| source.get_path() == *second)
+        }
+        ConfigError::MissingSection { context, .. }
+        | ConfigError::UnknownSection { context, .. }
+        | ConfigError::BadValue { context, .. }
+        | ConfigError::Unresolved { context, .. }
+        | ConfigError::UnmatchedQuotes { context, .. } => context_path(context),
+        ConfigError::DuplicateName { kind, name } => Some(defined_path(kind, name)),
+        _ => None,
+    };
+
+    path.filter(|path| !path.is_empty())
+        .and_then(|path| sources.iter().find(|source| source.contains(&path)))
+        .or_else(|| {
+            sources
+                .iter()
+                .find(|source| span_of(error, source).is_some())
+        })
+        .or_else(|| sources.first())
+}
@@ -0,0 +1,341 @@
+use super::diagnostic::Diagnostic;
+use super::interpolate::{Interpolate, Resolver};
+use super::model::{MasterFile, Sections};
+use super::source::{Source, Span};
+use crate::ConfigError;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file that each section of a merged master file came from, by its path of keys
+type Origins = HashMap<Vec<String>, String>;
+
+/// Where an include was written, to point at it if the file it names can't be read.
+/// The master file that was loaded wasn't included by anything.
+type At<'a> = Option<(&'a Source, Option<Span>)>;
+
+/// Reads a master file and merges every file it includes into it.
+///
+/// The sections of an included file come after the sections of the file that
+/// includes it, in the order the files are listed, so the builders of the
+/// first file still show up first in the web gui.
+pub(crate) fn load(path: &Path) -> Result<MasterFile, Vec<Diagnostic>> {
+    let mut loader = Loader::default();
+    match loader.master(path, None) {
+        Some((mut file, _)) if loader.errors.is_empty() => {
+            file.set_sources(loader.sources);
+            Ok(file)
+        }
+        _ => Err(loader.errors),
+    }
+}
+
+#[derive(Default)]
+struct Loader {
+    /// Every file read so far, in the order they were read
+    sources: Vec<Source>,
+    /// The files being read, to catch a file that includes itself
+    stack: Vec<PathBuf>,
+    errors: Vec<Diagnostic>,
+}
+
+impl Loader {
+    fn fail(&mut self, error: ConfigError, at: At) {
+        self.errors.push(match at {
+            Some((source, Some(span))) => Diagnostic::at(error, source, span),
+            Some((source, None)) => Diagnostic::in_file(error, source),
+            None => Diagnostic::from(error),
+        });
+    }
+
+    /// Reads and parses a file, with its sections under `root`, and resolves the
+    /// references in it. `context` describes its sections, like `Interpolate` needs.
+    /// If this succeeds, the file is on the stack until the caller pops it.
+    fn read<T: DeserializeOwned + Interpolate>(
+        &mut self,
+        path: &Path,
+        root: &[&str],
+        context: &str,
+        at: At,
+    ) -> Option<(T, Source)> {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.stack.contains(&canonical) {
+            let error = ConfigError::File {
+                path: path.display().to_string(),
+                reason: String::from("the file is already being included, so it includes itself"),
+            };
+            self.fail(error, at);
+            return None;
+        }
+
+        let source = match Source::read(path) {
+            Ok(source) => source.with_root(root),
+            // Point at the include, rather than the file that isn't there
+            Err(e) if at.is_some() => {
+                self.fail(e.get_error(), at);
+                return None;
+            }
+            Err(e) => {
+                self.errors.push(e);
+                return None;
+            }
+        };
+        self.sources.push(source.clone());
+        match source.parse::<T>() {
+            Ok(mut value) => {
+                let mut resolver = Resolver::new(path);
+                value.interpolate(context, &mut resolver);
+                for error in resolver.get_errors() {
+                    self.errors.push(Diagnostic::locate(error, &source));
+                }
+                self.stack.push(canonical);
+                Some((value, source))
+            }
+            Err(e) => {
+                self.errors.push(e);
+                None
+            }
+        }
+    }
+
+    /// Reads a master file and everything it includes
+    fn master(&mut self, path: &Path, at: At) -> Option<(MasterFile, Origins)> {
+        let (mut file, source) =
+            self.read::<MasterFile>(path, &[], "the master configuration", at)?;
+        let mut origins = Origins::new();
+        let sections = [
+            ("requires", file.requires.is_some()),
+            ("master", file.master.is_some()),
+            (
+                "merge-request-handler",
+                file.merge_request_handler.is_some(),
+            ),
+        ];
+        for (section, _) in sections.iter().filter(|(_, is_set)| *is_set) {
+            origins.insert(vec![section.to_string()], source.get_path());
+        }
+
+        file.workers = self.group(file.workers.take(), "workers", &source, &mut origins);
+        file.builders = self.group(file.builders.take(), "builders", &source, &mut origins);
+        file.schedulers = self.group(file.schedulers.take(), "schedulers", &source, &mut origins);
+
+        let includes = file.include.take().unwrap_or_default();
+        for (path, span) in self.expand(&includes, &source, &["include"]) {
+            if let Some((included, from)) = self.master(&path, Some((&source, span))) {
+                self.merge(&mut file, &mut origins, included, &from);
+            }
+        }
+
+        self.stack.pop();
+        Some((file, origins))
+    }
+
+    /// Records where the sections of a group like `builders` came from,
+    /// and merges in the files of sections that it includes
+    fn group<T: DeserializeOwned + Interpolate>(
+        &mut self,
+        sections: Option<Sections<T>>,
+        group: &str,
+        source: &Source,
+        origins: &mut Origins,
+    ) -> Option<Sections<T>> {
+        let mut sections = sections?;
+        for name in sections.get_names() {
+            origins.insert(vec![group.to_string(), name], source.get_path());
+        }
+
+        let includes = sections.take_includes();
+        let mut sections = Some(sections);
+        for (path, span) in self.expand(&includes, source, &[group, "include"]) {
+            let at = Some((source, span));
+            let kind = group.trim_end_matches('s');
+            if let Some((included, included_source)) = self.read(&path, &[group], kind, at) {
+                let mut from = Origins::new();
+                let included = self.group(Some(included), group, &included_source, &mut from);
+                self.stack.pop();
+                sections = self.merge_group(sections, included, group, origins, &from);
+            }
+        }
+        sections
+    }
+
+    /// The files named by the paths and globs of an include, which are
+    /// relative to the file the include is in, and where each one was written
+    fn expand(
+        &mut self,
+        includes: &[String],
+        source: &Source,
+        path: &[&str],
+    ) -> Vec<(PathBuf, Option<Span>)> {
+        let dir = Path::new(&source.get_path())
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut files: Vec<(PathBuf, Option<Span>)> = vec![];
+        for include in includes {
+            let span = source
+                .find_in(path, include)
+                .or_else(|| source.key_span(path));
+            let pattern = dir.join(include);
+            if !include.contains(['*', '?', '[']) {
+                files.push((pattern, span));
+                continue;
+            }
+
+            let matches: Vec<PathBuf> = match glob::glob(&pattern.to_string_lossy()) {
+                Ok(paths) => paths.filter_map(Result::ok).collect(),
+                Err(e) => {
+                    let error = ConfigError::File {
+                        path: include.clone(),
+                        reason: format!("this isn't a valid glob, {}", e.msg),
+                    };
+                    self.fail(error, Some((source, span)));
+                    continue;
+                }
+            };
+            if matches.is_empty() {
+                let error = ConfigError::File {
+                    path: include.clone(),
+                    reason: String::from("no files match this glob"),
+                };
+                self.fail(error, Some((source, span)));
+            }
+            files.extend(matches.into_iter().map(|file| (file, span)));
+        }
+
+        // A file that two globs match is only merged in once
+        let mut seen = vec![];
+        files.retain(|(file, _)| {
+            let is_new = !seen.contains(file);
+            seen.push(file.clone());
+            is_new
+        });
+        files
+    }
+
+    /// Merges an included master file into the file that included it
+    fn merge(
+        &mut self,
+        file: &mut MasterFile,
+        origins: &mut Origins,
+        included: MasterFile,
+        from: &Origins,
+    ) {
+        self.merge_section(
+            &mut file.requires,
+            included.requires,
+            "requires",
+            origins,
+            from,
+        );
+        self.merge_section(&mut file.master, included.master, "master", origins, from);
+        self.merge_section(
+            &mut file.merge_request_handler,
+            included.merge_request_handler,
+            "merge-request-handler",
+            origins,
+            from,
+        );
+
+        file.workers = self.merge_group(
+            file.workers.take(),
+            included.workers,
+            "workers",
+            origins,
+            from,
+        );
+        file.builders = self.merge_group(
+            file.builders.take(),
+            included.builders,
+            "builders",
+            origins,
+            from,
+        );
+        file.schedulers = self.merge_group(
+            file.schedulers.take(),
+            included.schedulers,
+            "schedulers",
+            origins,
+            from,
+        );
+        file.unknown.extend(included.unknown);
+    }
+
+    /// Merges a top-level section like `master`, which only one file can have
+    fn merge_section<T>(
+        &mut self,
+        section: &mut Option<T>,
+        included: Option<T>,
+        name: &str,
+        origins: &mut Origins,
+        from: &Origins,
+    ) {
+        if included.is_none() {
+            return;
+        }
+        let path = vec![name.to_string()];
+        if section.is_some() {
+            self.defined_twice("section", name, &path, origins, from);
+        } else {
+            *section = included;
+            if let Some(origin) = from.get(&path) {
+                origins.insert(path, origin.clone());
+            }
+        }
+    }
+
+    /// Adds the sections of an included group after the sections of `sections`
+    fn merge_group<T>(
+        &mut self,
+        sections: Option<Sections<T>>,
+        included: Option<Sections<T>>,
+        group: &str,
+        origins: &mut Origins,
+        from: &Origins,
+    ) -> Option<Sections<T>> {
+        let included = match included {
+            Some(included) => included,
+            None => return sections,
+        };
+
+        let mut sections = sections.unwrap_or_default();
+        let kind = group.trim_end_matches('s');
+        for (name, section) in included {
+            let path = vec![group.to_string(), name.clone()];
+            if sections.get(&name).is_some() {
+                self.defined_twice(kind, &name, &path, origins, from);
+            } else {
+                if let Some(origin) = from.get(&path) {
+                    origins.insert(path, origin.clone());
+                }
+                sections.push(name, section);
+            }
+        }
+        Some(sections)
+    }
+
+    /// Reports a section that two files define, pointing at the second one
+    fn defined_twice(
+        &mut self,
+        kind: &str,
+        name: &str,
+        path: &[String],
+        origins: &Origins,
+        from: &Origins,
+    ) {
+        let second = from.get(path).cloned().unwrap_or_default();
+        let error = ConfigError::DefinedTwice {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            first: origins.get(path).cloned().unwrap_or_default(),
+            second: second.clone(),
+        };
+        let diagnostic = match self.sources.iter().find(|s| s.get_path() == second) {
+            Some(source) => Diagnostic::locate(error, source),
+            None => Diagnostic::from(error),
+        };
+        self.errors.push(diagnostic);
+    }
+}
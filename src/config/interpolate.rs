@@ -0,0 +1,171 @@
+use crate::{ConfigError, MATRIX_REFERENCE};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Replaces the references in a value with what they refer to:
+/// - `${NAME}` is the environment variable `NAME`
+/// - `${NAME:-default}` is `NAME`, or `default` if it isn't set or is empty
+/// - `${file:path}` is the contents of a file, without the newline at the end,
+///   where `path` is relative to `dir`
+///
+/// `$${` is a literal `${`, for the variables of a builder's script.
+/// `${matrix.axis}` is left alone for the builder to fill in from its matrix.
+/// If any reference can't be resolved, this returns each of them with the reason why.
+pub(crate) fn interpolate(value: &str, dir: &Path) -> Result<String, Vec<(String, String)>> {
+    let mut result = String::new();
+    let mut errors = vec![];
+    let mut rest = value;
+
+    while let Some(i) = rest.find('$') {
+        result.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if let Some(after) = after.strip_prefix("${") {
+            result.push_str("${");
+            rest = after;
+        } else if after.starts_with(&MATRIX_REFERENCE[1..]) {
+            result.push('$');
+            rest = after;
+        } else if let Some(after) = after.strip_prefix('{') {
+            match after.find('}') {
+                Some(end) => {
+                    let reference = &rest[i..i + end + 3];
+                    match resolve(&after[..end], dir) {
+                        Ok(value) => result.push_str(&value),
+                        Err(reason) => errors.push((reference.to_string(), reason)),
+                    }
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    let reason = String::from("the reference is never closed with a '}'");
+                    errors.push((rest[i..].to_string(), reason));
+                    rest = "";
+                }
+            }
+        } else {
+            result.push('$');
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+
+    if errors.is_empty() {
+        Ok(result)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Finds the value of the inside of a reference
+fn resolve(reference: &str, dir: &Path) -> Result<String, String> {
+    if let Some(path) = reference.strip_prefix("file:") {
+        let path = dir.join(path);
+        return match fs::read_to_string(&path) {
+            Ok(contents) => Ok(contents.trim_end_matches(['\n', '\r']).to_string()),
+            Err(e) => Err(format!("could not read '{}': {}", path.display(), e)),
+        };
+    }
+
+    let (name, default) = match reference.find(":-") {
+        Some(i) => (&reference[..i], Some(&reference[i + 2..])),
+        None => (reference, None),
+    };
+    let is_name = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.chars().next().is_some_and(|c| !c.is_ascii_digit());
+    if !is_name {
+        return Err(format!(
+            "'{}' isn't the name of an environment variable, or `file:` and a path",
+            name
+        ));
+    }
+
+    match (env::var(name), default) {
+        (Ok(value), Some(default)) if value.is_empty() => Ok(default.to_string()),
+        (Ok(value), _) => Ok(value),
+        (Err(env::VarError::NotPresent), Some(default)) => Ok(default.to_string()),
+        (Err(env::VarError::NotPresent), None) => {
+            Err(format!("the environment variable '{}' isn't set", name))
+        }
+        (Err(env::VarError::NotUnicode(_)), _) => Err(format!(
+            "the environment variable '{}' isn't valid unicode",
+            name
+        )),
+    }
+}
+
+/// Resolves the references in the text sections of one configuration file,
+/// and keeps an error for each reference it couldn't resolve
+pub(crate) struct Resolver {
+    /// The directory of the file, which `${file:path}` is relative to
+    dir: PathBuf,
+    errors: Vec<ConfigError>,
+}
+
+impl Resolver {
+    pub(crate) fn new<P: AsRef<Path>>(file: P) -> Self {
+        Self {
+            dir: file
+                .as_ref()
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            errors: vec![],
+        }
+    }
+
+    /// Resolves the references in a text section
+    pub(crate) fn text(&mut self, value: &mut Option<String>, context: &str, section: &str) {
+        if let Some(value) = value {
+            match interpolate(value, &self.dir) {
+                Ok(resolved) => *value = resolved,
+                Err(unresolved) => {
+                    self.errors
+                        .extend(unresolved.into_iter().map(|(reference, reason)| {
+                            ConfigError::Unresolved {
+                                context: context.to_string(),
+                                section: section.to_string(),
+                                reference,
+                                reason,
+                            }
+                        }))
+                }
+            }
+        }
+    }
+
+    /// Resolves the references in a section that names a file,
+    /// and makes a relative path relative to the directory of this file.
+    /// The result is absolute, so it means the same thing wherever it's used from.
+    pub(crate) fn path(&mut self, value: &mut Option<String>, context: &str, section: &str) {
+        self.text(value, context, section);
+        if let Some(value) = value {
+            if Path::new(value).is_relative() {
+                let path = self.dir.join(&value);
+                let path = env::current_dir().map_or(path.clone(), |cwd| cwd.join(path));
+                *value = path.display().to_string();
+            }
+        }
+    }
+
+    /// Resolves the references in every item of a list section
+    pub(crate) fn list(&mut self, values: &mut Option<Vec<String>>, context: &str, section: &str) {
+        for value in values.iter_mut().flatten() {
+            let mut item = Some(std::mem::take(value));
+            self.text(&mut item, context, section);
+            *value = item.unwrap_or_default();
+        }
+    }
+
+    /// The references that couldn't be resolved
+    pub(crate) fn get_errors(&self) -> Vec<ConfigError> {
+        self.errors.clone()
+    }
+}
+
+/// Configuration sections whose text can hold references
+pub(crate) trait Interpolate {
+    /// Resolves the references in every text section.
+    /// `context` describes these sections in errors, like "builder 'cargo-test'",
+    /// or the kind of section for a group of them, like "builder".
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver);
+}
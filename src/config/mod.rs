@@ -0,0 +1,62 @@
+mod de;
+mod diagnostic;
+mod include;
+mod interpolate;
+pub use diagnostic::Diagnostic;
+pub(crate) use interpolate::interpolate;
+mod source;
+pub use source::{Source, Span};
+
+mod model;
+pub use model::{
+    BuilderSection, MailFile, MasterFile, MasterSection, MergeRequestHandlerSection, Recipients,
+    SchedulerSection, Sections, Unknown, WorkerSection,
+};
+
+use crate::ConfigError;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// The formats a configuration file can be written in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    /// Picks the format of a file from its extension.
+    /// A file without an extension is read as YAML, like rusty-ci always has.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") | None => Ok(Format::Yaml),
+            Some("toml") => Ok(Format::Toml),
+            Some("json") => Ok(Format::Json),
+            Some(other) => Err(ConfigError::File {
+                path: path.display().to_string(),
+                reason: format!(
+                    "unknown extension '.{}', expected '.yaml', '.yml', '.toml' or '.json'",
+                    other
+                ),
+            }),
+        }
+    }
+
+    /// Deserializes the contents of a file written in this format.
+    /// `path` is only used to describe where an error came from.
+    /// Use a `Source` instead to find out where an error is in the contents.
+    pub fn parse<T: DeserializeOwned>(self, contents: &str, path: &str) -> Result<T, ConfigError> {
+        Source::new(path, self, contents)
+            .parse()
+            .map_err(|e: Diagnostic| e.get_error())
+    }
+}
+
+/// Reads a configuration file, in the format its extension names
+pub fn load<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, ConfigError> {
+    Source::read(path)
+        .and_then(|source| source.parse())
+        .map_err(|e| e.get_error())
+}
@@ -0,0 +1,1239 @@
+use super::de::{list, text, List, Pairs};
+use super::diagnostic::source_of;
+use super::interpolate::{Interpolate, Resolver};
+use super::{include, Diagnostic, Format, Source};
+use crate::buildbot::{
+    explain_typos, unknown_sections, BUILDER_SECTIONS, MAIL_SECTIONS, MASTER_FILE_SECTIONS,
+    MASTER_SECTIONS, MERGE_REQUEST_HANDLER_SECTIONS, RECIPIENT_SECTIONS, SCHEDULER_SECTIONS,
+    WORKER_SECTIONS,
+};
+use crate::{
+    Builder, BuilderBuilder, ConfigError, MailNotifier, MasterConfig, Matrix, MergeRequestHandler,
+    Scheduler, Tokens, Worker,
+};
+use serde::de::{Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Sections that are named by their key, like the builders of a master file,
+/// kept in the order they were written.
+/// The order matters: the builders show up in the web gui in this order.
+///
+/// An `include` key isn't a section. It lists more files of sections,
+/// which are merged in when the master file is loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sections<T> {
+    sections: Vec<(String, T)>,
+    includes: Vec<String>,
+}
+
+impl<T> Sections<T> {
+    /// Retrieves the section named `name`
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.sections
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, section)| section)
+    }
+
+    /// Retrieves the name of every section, in order
+    pub fn get_names(&self) -> Vec<String> {
+        self.sections.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Retrieves the paths and globs in the `include` key, if there is one
+    pub fn get_includes(&self) -> Vec<String> {
+        self.includes.clone()
+    }
+
+    /// Iterates over the name and contents of every section, in order
+    pub fn iter(&self) -> std::slice::Iter<'_, (String, T)> {
+        self.sections.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// Takes the `include` key out, once the files in it are merged in
+    pub(crate) fn take_includes(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.includes)
+    }
+
+    /// Adds a section after the others
+    pub(crate) fn push(&mut self, name: String, section: T) {
+        self.sections.push((name, section));
+    }
+}
+
+impl<T> Default for Sections<T> {
+    fn default() -> Self {
+        Self::from(vec![])
+    }
+}
+
+impl<T> From<Vec<(String, T)>> for Sections<T> {
+    fn from(sections: Vec<(String, T)>) -> Self {
+        Self {
+            sections,
+            includes: vec![],
+        }
+    }
+}
+
+impl<T> IntoIterator for Sections<T> {
+    type Item = (String, T);
+    type IntoIter = std::vec::IntoIter<(String, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sections.into_iter()
+    }
+}
+
+/// Reads a map into a list, so the order of the keys is kept
+struct SectionsVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for SectionsVisitor<T> {
+    type Value = Sections<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of names to sections")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut sections = Sections::default();
+        while let Some(name) = map.next_key::<String>()? {
+            if name == "include" {
+                let List(paths) = map.next_value()?;
+                sections.includes.extend(paths);
+            } else {
+                sections.push(name, map.next_value()?);
+            }
+        }
+        Ok(sections)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Sections<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(SectionsVisitor(PhantomData))
+    }
+}
+
+impl<T: Serialize> Serialize for Sections<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        if !self.includes.is_empty() {
+            map.serialize_entry("include", &self.includes)?;
+        }
+        for (name, section) in &self.sections {
+            map.serialize_entry(name, section)?;
+        }
+        map.end()
+    }
+}
+
+/// The names of the sections we don't know, which are reported as typos.
+/// They're left out when a file is written back out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Unknown(Vec<String>);
+
+impl Unknown {
+    /// Retrieves the name of every unknown section, in order
+    pub fn get_names(&self) -> Vec<String> {
+        self.0.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds the unknown sections of a file merged into this one
+    pub(crate) fn extend(&mut self, other: Unknown) {
+        self.0.extend(other.0);
+    }
+}
+
+struct UnknownVisitor;
+
+impl<'de> Visitor<'de> for UnknownVisitor {
+    type Value = Unknown;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of sections")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut names = vec![];
+        while let Some((name, IgnoredAny)) = map.next_entry()? {
+            names.push(name);
+        }
+        Ok(Unknown(names))
+    }
+}
+
+impl<'de> Deserialize<'de> for Unknown {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(UnknownVisitor)
+    }
+}
+
+impl Serialize for Unknown {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_map(Some(0))?.end()
+    }
+}
+
+/// Removes the sections that aren't set, which TOML can't write at all
+fn strip_nulls(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        serde_yaml::Value::Sequence(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// A `MissingSection` error for each of `sections` whose value is None
+fn missing(context: &str, sections: &[(&str, bool)]) -> Vec<ConfigError> {
+    sections
+        .iter()
+        .filter(|(_, is_missing)| *is_missing)
+        .map(|(section, _)| ConfigError::MissingSection {
+            context: context.to_string(),
+            section: section.to_string(),
+        })
+        .collect()
+}
+
+/// Why a port that isn't one is a bad value
+const PORT: &str = "the port must be a number between 0 and 65535";
+
+/// Parses a number section, pushing a `BadValue` error if it isn't one.
+/// A bad number still gives a value, so the section isn't reported missing as well.
+fn number<T: FromStr + Default>(
+    value: &str,
+    context: &str,
+    section: &str,
+    reason: &str,
+    errors: &mut Vec<ConfigError>,
+) -> T {
+    value.parse().unwrap_or_else(|_| {
+        errors.push(ConfigError::BadValue {
+            context: context.to_string(),
+            section: section.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+        });
+        T::default()
+    })
+}
+
+/// Writes a number section that was read as text back out as a number, if it is one
+fn as_number<S: Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value.as_ref().map(|text| text.parse::<u64>()) {
+        Some(Ok(number)) => serializer.serialize_u64(number),
+        _ => value.serialize(serializer),
+    }
+}
+
+/// Adds the errors found before building an object to the errors of building it,
+/// leaving out the sections it reports missing that were only misspelled
+fn with_errors<T>(
+    result: Result<T, Vec<ConfigError>>,
+    mut errors: Vec<ConfigError>,
+) -> Result<T, Vec<ConfigError>> {
+    match result {
+        Ok(value) if errors.is_empty() => Ok(value),
+        Ok(_) => Err(explain_typos(errors)),
+        Err(e) => {
+            errors.extend(e);
+            Err(explain_typos(errors))
+        }
+    }
+}
+
+/// The master file, which holds everything but the mail notifier.
+/// Every section is optional here so that converting it into a `MasterConfig`
+/// can report all of the missing sections at once.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MasterFile {
+    /// More master files to merge into this one, as paths or globs
+    /// relative to this file. `MasterFile::load` merges them in.
+    #[serde(default, deserialize_with = "list")]
+    pub include: Option<Vec<String>>,
+    /// The oldest version of rusty-ci that can build this file
+    #[serde(default, deserialize_with = "text")]
+    pub requires: Option<String>,
+    pub master: Option<MasterSection>,
+    pub merge_request_handler: Option<MergeRequestHandlerSection>,
+    pub workers: Option<Sections<WorkerSection>>,
+    pub builders: Option<Sections<BuilderSection>>,
+    pub schedulers: Option<Sections<SchedulerSection>>,
+    /// The sections we don't know, which are reported as typos
+    #[serde(flatten)]
+    pub unknown: Unknown,
+    /// The files this was read from, to point at the mistakes in them.
+    /// The file that was loaded comes first, then the files it includes.
+    #[serde(skip)]
+    sources: Vec<Source>,
+}
+
+/// The `master` section, with the web gui and the repository to poll
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MasterSection {
+    #[serde(default, deserialize_with = "text")]
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub title_url: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub repo: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub webserver_ip: Option<String>,
+    /// The numbers are read as text, so that a quoted number still works
+    /// and a bad one is reported where it's written
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub webserver_port: Option<String>,
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub poll_interval: Option<String>,
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub worker_port: Option<String>,
+    /// The sections we don't know, which are reported as typos
+    #[serde(flatten)]
+    pub unknown: Unknown,
+}
+
+/// The `merge-request-handler` section
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MergeRequestHandlerSection {
+    #[serde(default, deserialize_with = "text")]
+    pub version_control_system: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub owner: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub repo_name: Option<String>,
+    #[serde(default, deserialize_with = "list")]
+    pub whitelist: Option<Vec<String>>,
+    /// A file with the token for both polling and pushing statuses,
+    /// relative to the file this section is written in
+    #[serde(default, deserialize_with = "text")]
+    pub token_file: Option<String>,
+    /// An environment variable with the token for both polling and pushing statuses
+    #[serde(default, deserialize_with = "text")]
+    pub token_env: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub poll_token_file: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub poll_token_env: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub status_token_file: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub status_token_env: Option<String>,
+    /// The sections we don't know, which are reported as typos
+    #[serde(flatten)]
+    pub unknown: Unknown,
+}
+
+/// A section of `workers`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WorkerSection {
+    #[serde(default, deserialize_with = "text")]
+    pub master_ip: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub working_dir: Option<String>,
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub keepalive: Option<String>,
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub max_delay: Option<String>,
+    /// An octal umask, like `022`
+    #[serde(default, deserialize_with = "text")]
+    pub umask: Option<String>,
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub numcpus: Option<String>,
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub max_retries: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub allow_shutdown: Option<String>,
+    /// The sections we don't know, which are reported as typos
+    #[serde(flatten)]
+    pub unknown: Unknown,
+}
+
+/// A section of `builders`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuilderSection {
+    #[serde(default, deserialize_with = "list")]
+    pub workers: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "list")]
+    pub script: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "text")]
+    pub repo: Option<String>,
+    pub shell: Option<bool>,
+    #[serde(default, deserialize_with = "text")]
+    pub vcs: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub branch: Option<String>,
+    pub shallow: Option<bool>,
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub depth: Option<String>,
+    pub submodules: Option<bool>,
+    #[serde(default, deserialize_with = "text")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<MatrixSection>,
+    /// The sections we don't know, which are reported as typos
+    #[serde(flatten)]
+    pub unknown: Unknown,
+}
+
+/// The `matrix` section of a builder: each axis and its values, in the order
+/// they're written, and the combinations in `exclude` and `include`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatrixSection {
+    pub axes: Vec<(String, Vec<String>)>,
+    pub exclude: Vec<Vec<(String, String)>>,
+    pub include: Vec<Vec<(String, String)>>,
+}
+
+impl From<MatrixSection> for Matrix {
+    fn from(section: MatrixSection) -> Self {
+        let mut matrix = Matrix::default();
+        for (axis, values) in &section.axes {
+            let values: Vec<&str> = values.iter().map(String::as_str).collect();
+            matrix = matrix.axis(axis.as_str(), &values);
+        }
+        for combination in &section.exclude {
+            matrix = matrix.exclude(combination);
+        }
+        for combination in &section.include {
+            matrix = matrix.include(combination);
+        }
+        matrix
+    }
+}
+
+struct MatrixVisitor;
+
+impl<'de> Visitor<'de> for MatrixVisitor {
+    type Value = MatrixSection;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of axes to their values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut matrix = MatrixSection::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "exclude" | "include" => {
+                    let combinations = map
+                        .next_value::<Vec<Pairs>>()?
+                        .into_iter()
+                        .map(|Pairs(pairs)| pairs);
+                    if key == "exclude" {
+                        matrix.exclude.extend(combinations);
+                    } else {
+                        matrix.include.extend(combinations);
+                    }
+                }
+                _ => {
+                    let List(values) = map.next_value()?;
+                    matrix.axes.push((key, values));
+                }
+            }
+        }
+        Ok(matrix)
+    }
+}
+
+impl<'de> Deserialize<'de> for MatrixSection {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MatrixVisitor)
+    }
+}
+
+/// A combination of a matrix, written as a map
+struct Combination<'a>(&'a [(String, String)]);
+
+impl Serialize for Combination<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (axis, value) in self.0 {
+            map.serialize_entry(axis, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for MatrixSection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for (axis, values) in &self.axes {
+            map.serialize_entry(axis, values)?;
+        }
+        for (key, combinations) in &[("exclude", &self.exclude), ("include", &self.include)] {
+            if !combinations.is_empty() {
+                let combinations: Vec<Combination> =
+                    combinations.iter().map(|c| Combination(c)).collect();
+                map.serialize_entry(key, &combinations)?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// A section of `schedulers`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SchedulerSection {
+    #[serde(default, deserialize_with = "text")]
+    pub depends: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub branch: Option<String>,
+    #[serde(default, deserialize_with = "list")]
+    pub triggers: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "text")]
+    pub password: Option<String>,
+    #[serde(default, deserialize_with = "list")]
+    pub builders: Option<Vec<String>>,
+    /// The sections we don't know, which are reported as typos
+    #[serde(flatten)]
+    pub unknown: Unknown,
+}
+
+/// The mail file, which holds the mail notifier
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MailFile {
+    pub extra_recipients: Option<Recipients>,
+    #[serde(default, deserialize_with = "text")]
+    pub from_address: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub smtp_relay_host: Option<String>,
+    #[serde(default, deserialize_with = "text", serialize_with = "as_number")]
+    pub smtp_port: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub lookup: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pub smtp_password: Option<String>,
+    /// The sections we don't know, which are reported as typos
+    #[serde(flatten)]
+    pub unknown: Unknown,
+    /// The file this was read from, to point at the mistakes in it
+    #[serde(skip)]
+    source: Option<Source>,
+}
+
+/// The `extra-recipients` section of the mail file
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Recipients {
+    #[serde(default, deserialize_with = "list")]
+    pub all: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "list")]
+    pub failure: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "list")]
+    pub success: Option<Vec<String>>,
+    /// The sections we don't know, which are reported as typos
+    #[serde(flatten)]
+    pub unknown: Unknown,
+}
+
+impl MasterFile {
+    /// Reads a master file written in YAML, TOML or JSON, and merges in every file it includes.
+    /// References like `${SMTP_PASSWORD}` in each file are resolved as it's read.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Vec<Diagnostic>> {
+        include::load(path.as_ref())
+    }
+
+    /// The file this was loaded from, if it was loaded from one
+    pub fn get_source(&self) -> Option<Source> {
+        self.sources.first().cloned()
+    }
+
+    /// The file this was loaded from, followed by every file it included
+    pub fn get_sources(&self) -> Vec<Source> {
+        self.sources.clone()
+    }
+
+    pub(crate) fn set_sources(&mut self, sources: Vec<Source>) {
+        self.sources = sources;
+    }
+
+    /// Points each error at the place in the file it was made,
+    /// if this was loaded from a file
+    pub fn diagnose(&self, errors: Vec<ConfigError>) -> Vec<Diagnostic> {
+        errors
+            .into_iter()
+            .map(|error| match source_of(&error, &self.sources) {
+                Some(source) => Diagnostic::locate(error, source),
+                None => Diagnostic::from(error),
+            })
+            .collect()
+    }
+
+    /// Writes this file back out in `format`, leaving out the sections it doesn't set.
+    /// After `load`, this is the whole configuration with its includes merged in.
+    pub fn render(&self, format: Format) -> Result<String, String> {
+        let mut value = serde_yaml::to_value(self).map_err(|e| e.to_string())?;
+        strip_nulls(&mut value);
+        match format {
+            Format::Yaml => serde_yaml::to_string(&value).map_err(|e| e.to_string()),
+            Format::Toml => toml::to_string(&value).map_err(|e| e.to_string()),
+            Format::Json => serde_json::to_string_pretty(&value).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Converts just the workers, for the subcommands that don't need the whole config
+    pub fn to_workers(&self) -> Result<Vec<Worker>, Vec<ConfigError>> {
+        let workers = match &self.workers {
+            Some(workers) => workers,
+            None => return Err(missing("the master configuration", &[("workers", true)])),
+        };
+
+        let mut errors = vec![];
+        let mut result = vec![];
+        for (name, worker) in workers.iter() {
+            match worker.to_worker(name) {
+                Ok(worker) => result.push(worker),
+                Err(e) => errors.extend(e),
+            }
+        }
+        with_errors(Ok(result), errors)
+    }
+
+    /// Converts just the builders, for the subcommands that don't need the whole config
+    pub fn to_builders(&self) -> Result<Vec<Builder>, Vec<ConfigError>> {
+        let builders = match &self.builders {
+            Some(builders) => builders,
+            None => return Err(missing("the master configuration", &[("builders", true)])),
+        };
+
+        let mut errors = vec![];
+        let mut result = vec![];
+        for (name, builder) in builders.iter() {
+            match builder.to_builders(name) {
+                Ok(builders) => result.extend(builders),
+                Err(e) => errors.extend(e),
+            }
+        }
+        with_errors(Ok(result), errors)
+    }
+
+    /// Converts just the schedulers, for the subcommands that don't need the whole config
+    pub fn to_schedulers(&self) -> Result<Vec<Scheduler>, Vec<ConfigError>> {
+        let schedulers = match &self.schedulers {
+            Some(schedulers) => schedulers,
+            None => return Err(missing("the master configuration", &[("schedulers", true)])),
+        };
+
+        let mut errors = vec![];
+        let mut result = vec![];
+        for (name, scheduler) in schedulers.iter() {
+            match scheduler.to_scheduler(name) {
+                Ok(scheduler) => result.push(scheduler),
+                Err(e) => errors.extend(e),
+            }
+        }
+        with_errors(Ok(result), errors)
+    }
+
+    /// Converts the schedulers, with each builder with a matrix they trigger replaced
+    /// by the builders it expands into, like `MasterConfig::build` does.
+    /// This is for the subcommands that need the builder names the master really uses.
+    pub fn to_expanded_schedulers(&self) -> Result<Vec<Scheduler>, Vec<ConfigError>> {
+        let mut errors = vec![];
+        let schedulers = self.to_schedulers().map_err(|e| errors.extend(e));
+        let builders = self.to_builders().map_err(|e| errors.extend(e));
+        match (schedulers, builders) {
+            (Ok(mut schedulers), Ok(builders)) => {
+                for scheduler in schedulers.iter_mut() {
+                    scheduler.expand_matrices(&builders);
+                }
+                Ok(schedulers)
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+impl WorkerSection {
+    pub fn to_worker(&self, name: &str) -> Result<Worker, Vec<ConfigError>> {
+        let context = format!("worker '{}'", name);
+        let mut errors = unknown_sections(&self.unknown.get_names(), WORKER_SECTIONS, &context);
+        let mut worker = Worker::builder(name);
+
+        if let Some(dir) = &self.working_dir {
+            worker = worker.working_dir(dir);
+        }
+        if let Some(host) = &self.master_ip {
+            worker = worker.master_ip(host);
+        }
+        const WHOLE: &str = "expected a whole number";
+        if let Some(keepalive) = &self.keepalive {
+            worker = worker.keepalive(number(keepalive, &context, "keepalive", WHOLE, &mut errors));
+        }
+        if let Some(max_delay) = &self.max_delay {
+            worker = worker.max_delay(number(max_delay, &context, "max-delay", WHOLE, &mut errors));
+        }
+        if let Some(numcpus) = &self.numcpus {
+            worker = worker.numcpus(number(numcpus, &context, "numcpus", WHOLE, &mut errors));
+        }
+        if let Some(max_retries) = &self.max_retries {
+            let max_retries = number(max_retries, &context, "max-retries", WHOLE, &mut errors);
+            worker = worker.max_retries(max_retries);
+        }
+        if let Some(method) = &self.allow_shutdown {
+            worker = worker.allow_shutdown(method);
+        }
+
+        // The builder checks that the umask isn't too big
+        if let Some(umask) = &self.umask {
+            match u32::from_str_radix(umask.trim_start_matches("0o"), 8) {
+                Ok(umask) => worker = worker.umask(umask),
+                Err(_) => errors.push(ConfigError::BadValue {
+                    context,
+                    section: String::from("umask"),
+                    value: umask.clone(),
+                    reason: String::from("the umask must be an octal number like 022"),
+                }),
+            }
+        }
+
+        with_errors(worker.build(), errors)
+    }
+}
+
+impl BuilderSection {
+    /// Converts this into a builder.
+    /// A builder with a matrix is more than one builder, so it's converted with `to_builders`.
+    pub fn to_builder(&self, name: &str) -> Result<Builder, Vec<ConfigError>> {
+        let (builder, errors) = self.builder(name);
+        with_errors(builder.build(), errors)
+    }
+
+    /// Converts this into a builder for each combination of its matrix,
+    /// or just the one builder if it doesn't have one
+    pub fn to_builders(&self, name: &str) -> Result<Vec<Builder>, Vec<ConfigError>> {
+        let (builder, errors) = self.builder(name);
+        with_errors(builder.build_matrix(), errors)
+    }
+
+    /// Reads each section into a `BuilderBuilder`, with the mistakes it found on the way
+    fn builder(&self, name: &str) -> (BuilderBuilder, Vec<ConfigError>) {
+        let context = format!("builder '{}'", name);
+        let mut errors = unknown_sections(&self.unknown.get_names(), BUILDER_SECTIONS, &context);
+        let mut builder = Builder::builder(name);
+
+        for worker in self.workers.iter().flatten() {
+            builder = builder.worker(worker);
+        }
+        for line in self.script.iter().flatten() {
+            builder = builder.script(line);
+        }
+        if let Some(repo) = &self.repo {
+            builder = builder.repo(repo);
+        }
+        if let Some(shell) = self.shell {
+            builder = builder.shell(shell);
+        }
+        if let Some(vcs) = &self.vcs {
+            match vcs.parse() {
+                Ok(vcs) => builder = builder.vcs(vcs),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        if let Some(branch) = &self.branch {
+            builder = builder.branch(branch);
+        }
+        if self.shallow == Some(true) {
+            builder = builder.depth(1);
+        }
+        // An explicit depth overrides the `shallow` section
+        if let Some(depth) = &self.depth {
+            // A bad depth isn't given to the builder, which would report it as 0 too
+            match depth.parse() {
+                Ok(depth) => builder = builder.depth(depth),
+                Err(_) => errors.push(ConfigError::BadValue {
+                    context: context.clone(),
+                    section: String::from("depth"),
+                    value: depth.clone(),
+                    reason: String::from("the depth must be a positive number of commits"),
+                }),
+            }
+        }
+        if let Some(submodules) = self.submodules {
+            builder = builder.submodules(submodules);
+        }
+        if let Some(method) = &self.method {
+            builder = builder.method(method);
+        }
+        if let Some(matrix) = &self.matrix {
+            builder = builder.matrix(Matrix::from(matrix.clone()));
+        }
+
+        (builder, errors)
+    }
+}
+
+impl SchedulerSection {
+    pub fn to_scheduler(&self, name: &str) -> Result<Scheduler, Vec<ConfigError>> {
+        let context = format!("scheduler '{}'", name);
+        let errors = unknown_sections(&self.unknown.get_names(), SCHEDULER_SECTIONS, &context);
+        let mut scheduler = Scheduler::builder(name);
+
+        match &self.depends {
+            // The `branch`, `triggers`, and `password` sections are ignored
+            Some(depends) => scheduler = scheduler.depends(depends),
+            None => {
+                if let Some(branch) = &self.branch {
+                    scheduler = scheduler.branch(branch);
+                }
+                if let Some(password) = &self.password {
+                    scheduler = scheduler.password(password);
+                }
+                for trigger in self.triggers.iter().flatten() {
+                    scheduler = scheduler.trigger(trigger);
+                }
+            }
+        }
+
+        for builder in self.builders.iter().flatten() {
+            scheduler = scheduler.builder(builder);
+        }
+
+        with_errors(scheduler.build(), errors)
+    }
+}
+
+impl<T: Interpolate> Interpolate for Sections<T> {
+    fn interpolate(&mut self, kind: &str, resolver: &mut Resolver) {
+        for (name, section) in &mut self.sections {
+            section.interpolate(&format!("{} '{}'", kind, name), resolver);
+        }
+    }
+}
+
+impl Interpolate for MasterFile {
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver) {
+        resolver.text(&mut self.requires, context, "requires");
+        if let Some(master) = &mut self.master {
+            master.interpolate("master", resolver);
+        }
+        if let Some(handler) = &mut self.merge_request_handler {
+            handler.interpolate("the merge request handler", resolver);
+        }
+        if let Some(workers) = &mut self.workers {
+            workers.interpolate("worker", resolver);
+        }
+        if let Some(builders) = &mut self.builders {
+            builders.interpolate("builder", resolver);
+        }
+        if let Some(schedulers) = &mut self.schedulers {
+            schedulers.interpolate("scheduler", resolver);
+        }
+    }
+}
+
+impl Interpolate for MasterSection {
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver) {
+        resolver.text(&mut self.title, context, "title");
+        resolver.text(&mut self.title_url, context, "title-url");
+        resolver.text(&mut self.repo, context, "repo");
+        resolver.text(&mut self.webserver_ip, context, "webserver-ip");
+        resolver.text(&mut self.webserver_port, context, "webserver-port");
+        resolver.text(&mut self.poll_interval, context, "poll-interval");
+        resolver.text(&mut self.worker_port, context, "worker-port");
+    }
+}
+
+impl Interpolate for MergeRequestHandlerSection {
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver) {
+        resolver.text(
+            &mut self.version_control_system,
+            context,
+            "version-control-system",
+        );
+        resolver.text(&mut self.owner, context, "owner");
+        resolver.text(&mut self.repo_name, context, "repo-name");
+        resolver.list(&mut self.whitelist, context, "whitelist");
+        resolver.path(&mut self.token_file, context, "token-file");
+        resolver.text(&mut self.token_env, context, "token-env");
+        resolver.path(&mut self.poll_token_file, context, "poll-token-file");
+        resolver.text(&mut self.poll_token_env, context, "poll-token-env");
+        resolver.path(&mut self.status_token_file, context, "status-token-file");
+        resolver.text(&mut self.status_token_env, context, "status-token-env");
+    }
+}
+
+impl Interpolate for WorkerSection {
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver) {
+        resolver.text(&mut self.master_ip, context, "master-ip");
+        resolver.text(&mut self.working_dir, context, "working-dir");
+        resolver.text(&mut self.keepalive, context, "keepalive");
+        resolver.text(&mut self.max_delay, context, "max-delay");
+        resolver.text(&mut self.umask, context, "umask");
+        resolver.text(&mut self.numcpus, context, "numcpus");
+        resolver.text(&mut self.max_retries, context, "max-retries");
+        resolver.text(&mut self.allow_shutdown, context, "allow-shutdown");
+    }
+}
+
+impl Interpolate for BuilderSection {
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver) {
+        resolver.list(&mut self.workers, context, "workers");
+        resolver.list(&mut self.script, context, "script");
+        resolver.text(&mut self.repo, context, "repo");
+        resolver.text(&mut self.vcs, context, "vcs");
+        resolver.text(&mut self.branch, context, "branch");
+        resolver.text(&mut self.depth, context, "depth");
+        resolver.text(&mut self.method, context, "method");
+        if let Some(matrix) = &mut self.matrix {
+            for (_, values) in matrix.axes.iter_mut() {
+                let mut resolved = Some(std::mem::take(values));
+                resolver.list(&mut resolved, context, "matrix");
+                *values = resolved.unwrap_or_default();
+            }
+        }
+    }
+}
+
+impl Interpolate for SchedulerSection {
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver) {
+        resolver.text(&mut self.depends, context, "depends");
+        resolver.text(&mut self.branch, context, "branch");
+        resolver.list(&mut self.triggers, context, "triggers");
+        resolver.text(&mut self.password, context, "password");
+        resolver.list(&mut self.builders, context, "builders");
+    }
+}
+
+impl Interpolate for MailFile {
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver) {
+        if let Some(recipients) = &mut self.extra_recipients {
+            recipients.interpolate(
+                "the 'extra-recipients' subsection of the mail notifier",
+                resolver,
+            );
+        }
+        resolver.text(&mut self.from_address, context, "from-address");
+        resolver.text(&mut self.smtp_relay_host, context, "smtp-relay-host");
+        resolver.text(&mut self.smtp_port, context, "smtp-port");
+        resolver.text(&mut self.lookup, context, "lookup");
+        resolver.text(&mut self.smtp_password, context, "smtp-password");
+    }
+}
+
+impl Interpolate for Recipients {
+    fn interpolate(&mut self, context: &str, resolver: &mut Resolver) {
+        resolver.list(&mut self.all, context, "all");
+        resolver.list(&mut self.failure, context, "failure");
+        resolver.list(&mut self.success, context, "success");
+    }
+}
+
+impl MergeRequestHandlerSection {
+    /// The value of a token section, by its name in the file
+    fn token_section(&self, name: &str) -> Option<String> {
+        match name {
+            "token-file" => self.token_file.clone(),
+            "token-env" => self.token_env.clone(),
+            "poll-token-file" => self.poll_token_file.clone(),
+            "poll-token-env" => self.poll_token_env.clone(),
+            "status-token-file" => self.status_token_file.clone(),
+            "status-token-env" => self.status_token_env.clone(),
+            _ => None,
+        }
+    }
+
+    /// Converts this into a merge request handler for a config file in the directory `dir`.
+    /// The tokens are read from `auth.token` in `dir` unless the section says where they are,
+    /// and relative token files are relative to `dir` too.
+    pub fn to_handler(&self, dir: &Path) -> Result<MergeRequestHandler, Vec<ConfigError>> {
+        self.handler(dir, true)
+    }
+
+    /// Like `to_handler`, but the tokens are only read if `read_tokens` is set.
+    /// The token sections are checked either way.
+    fn handler(
+        &self,
+        dir: &Path,
+        read_tokens: bool,
+    ) -> Result<MergeRequestHandler, Vec<ConfigError>> {
+        let context = "the merge request handler";
+        let mut errors = missing(
+            context,
+            &[
+                (
+                    "version-control-system",
+                    self.version_control_system.is_none(),
+                ),
+                ("owner", self.owner.is_none()),
+                ("repo-name", self.repo_name.is_none()),
+                ("whitelist", self.whitelist.is_none()),
+            ],
+        );
+        errors.extend(unknown_sections(
+            &self.unknown.get_names(),
+            MERGE_REQUEST_HANDLER_SECTIONS,
+            context,
+        ));
+        if !errors.is_empty() {
+            return Err(explain_typos(errors));
+        }
+
+        let vcs = self
+            .version_control_system
+            .clone()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|e| vec![e])?;
+        let tokens = Tokens::from_sections(|name| self.token_section(name), dir, context)?;
+        let owner = self.owner.clone().unwrap_or_default();
+        let repo_name = self.repo_name.clone().unwrap_or_default();
+        let whitelist = self.whitelist.clone().unwrap_or_default();
+        if read_tokens {
+            MergeRequestHandler::with_tokens(vcs, owner, repo_name, whitelist, &tokens)
+                .map_err(|e| vec![e])
+        } else {
+            Ok(MergeRequestHandler::without_tokens(
+                vcs, owner, repo_name, whitelist,
+            ))
+        }
+    }
+}
+
+/// Converts a master file into a MasterConfig,
+/// collecting every error in it like `MasterConfig::try_from` does for YAML.
+impl TryFrom<MasterFile> for MasterConfig {
+    type Error = Vec<ConfigError>;
+
+    fn try_from(file: MasterFile) -> Result<Self, Self::Error> {
+        file.to_config(true)
+    }
+}
+
+impl MasterFile {
+    /// Converts this into a MasterConfig without reading the VCS tokens, which are left empty.
+    /// This is for `check`, which only looks at the builders, schedulers and workers,
+    /// so it works without credentials.
+    pub fn to_config_without_tokens(&self) -> Result<MasterConfig, Vec<ConfigError>> {
+        self.to_config(false)
+    }
+
+    fn to_config(&self, read_tokens: bool) -> Result<MasterConfig, Vec<ConfigError>> {
+        let mut errors = missing(
+            "the master configuration",
+            &[
+                ("master", self.master.is_none()),
+                ("workers", self.workers.is_none()),
+                ("builders", self.builders.is_none()),
+                ("schedulers", self.schedulers.is_none()),
+                (
+                    "merge-request-handler",
+                    self.merge_request_handler.is_none(),
+                ),
+            ],
+        );
+        errors.extend(unknown_sections(
+            &self.unknown.get_names(),
+            MASTER_FILE_SECTIONS,
+            "the master configuration",
+        ));
+        if !errors.is_empty() {
+            return Err(explain_typos(errors));
+        }
+
+        let master = self.master.clone().unwrap_or_default();
+        errors.extend(unknown_sections(
+            &master.unknown.get_names(),
+            MASTER_SECTIONS,
+            "master",
+        ));
+        let mut config = MasterConfig::builder();
+        if let Some(title) = master.title {
+            config = config.title(title);
+        }
+        if let Some(title_url) = master.title_url {
+            config = config.title_url(title_url);
+        }
+        if let Some(repo) = master.repo {
+            config = config.repo(repo);
+        }
+        if let Some(ip) = master.webserver_ip {
+            config = config.webserver_ip(ip);
+        }
+        // These are written into the python as numbers, so they had better be numbers
+        if let Some(port) = master.webserver_port {
+            let port = number(&port, "master", "webserver-port", PORT, &mut errors);
+            config = config.webserver_port(port);
+        }
+        if let Some(seconds) = master.poll_interval {
+            let reason = "the poll interval must be a whole number of seconds";
+            config = config.poll_interval(number(
+                &seconds,
+                "master",
+                "poll-interval",
+                reason,
+                &mut errors,
+            ));
+        }
+        if let Some(port) = master.worker_port {
+            config = config.worker_port(number(&port, "master", "worker-port", PORT, &mut errors));
+        }
+
+        // The tokens are read next to the master file, so that this works from any directory.
+        // Token files written in an included file are already relative to that file.
+        let dir = self
+            .get_source()
+            .and_then(|source| {
+                Path::new(&source.get_path())
+                    .parent()
+                    .map(Path::to_path_buf)
+            })
+            .unwrap_or_default();
+        let handler = self.merge_request_handler.clone().unwrap_or_default();
+        match handler.handler(&dir, read_tokens) {
+            Ok(handler) => config = config.merge_request_handler(handler),
+            Err(e) => errors.extend(e),
+        }
+
+        match self.to_schedulers() {
+            Ok(schedulers) => {
+                for scheduler in schedulers {
+                    config = config.scheduler(scheduler);
+                }
+            }
+            Err(e) => errors.extend(e),
+        }
+        match self.to_builders() {
+            Ok(builders) => {
+                for builder in builders {
+                    config = config.builder(builder);
+                }
+            }
+            Err(e) => errors.extend(e),
+        }
+        match self.to_workers() {
+            Ok(workers) => {
+                for worker in workers {
+                    config = config.worker(worker);
+                }
+            }
+            Err(e) => errors.extend(e),
+        }
+
+        // The builder reports the missing `master` sections and the duplicate names
+        with_errors(config.build(), errors)
+    }
+}
+
+impl MailFile {
+    /// Reads a mail file written in YAML, TOML or JSON,
+    /// and resolves the references in it like `MasterFile::load` does
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Vec<Diagnostic>> {
+        let source = Source::read(path.as_ref()).map_err(|e| vec![e])?;
+        let mut file = Self {
+            source: Some(source.clone()),
+            ..source.parse().map_err(|e| vec![e])?
+        };
+
+        let mut resolver = Resolver::new(path);
+        file.interpolate("the mail notifier", &mut resolver);
+        match resolver.get_errors() {
+            errors if errors.is_empty() => Ok(file),
+            errors => Err(file.diagnose(errors)),
+        }
+    }
+
+    /// The file this was loaded from, if it was loaded from one
+    pub fn get_source(&self) -> Option<Source> {
+        self.source.clone()
+    }
+
+    /// Points each error at the place in the file it was made,
+    /// if this was loaded from a file
+    pub fn diagnose(&self, errors: Vec<ConfigError>) -> Vec<Diagnostic> {
+        errors
+            .into_iter()
+            .map(|error| match &self.source {
+                Some(source) => Diagnostic::locate(error, source),
+                None => Diagnostic::from(error),
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<MailFile> for MailNotifier {
+    type Error = Vec<ConfigError>;
+
+    fn try_from(file: MailFile) -> Result<Self, Self::Error> {
+        let mut errors = missing(
+            "the mail notifier",
+            &[
+                ("extra-recipients", file.extra_recipients.is_none()),
+                ("from-address", file.from_address.is_none()),
+                ("smtp-relay-host", file.smtp_relay_host.is_none()),
+                ("smtp-port", file.smtp_port.is_none()),
+                ("lookup", file.lookup.is_none()),
+                ("smtp-password", file.smtp_password.is_none()),
+            ],
+        );
+        errors.extend(unknown_sections(
+            &file.unknown.get_names(),
+            MAIL_SECTIONS,
+            "the mail notifier",
+        ));
+        // The port is written into the python as a number, so it had better be one
+        let smtp_port = file
+            .smtp_port
+            .as_ref()
+            .map(|port| number(port, "the mail notifier", "smtp-port", PORT, &mut errors));
+
+        let recipients = file.extra_recipients.unwrap_or_default();
+        let context = "the 'extra-recipients' subsection of the mail notifier";
+        errors.extend(missing(
+            context,
+            &[
+                ("all", recipients.all.is_none()),
+                ("failure", recipients.failure.is_none()),
+                ("success", recipients.success.is_none()),
+            ],
+        ));
+        errors.extend(unknown_sections(
+            &recipients.unknown.get_names(),
+            RECIPIENT_SECTIONS,
+            context,
+        ));
+        if !errors.is_empty() {
+            return Err(explain_typos(errors));
+        }
+
+        Ok(MailNotifier::new(
+            recipients.all.unwrap_or_default(),
+            recipients.success.unwrap_or_default(),
+            recipients.failure.unwrap_or_default(),
+            file.from_address.unwrap_or_default(),
+            file.smtp_relay_host.unwrap_or_default(),
+            smtp_port.unwrap_or_default(),
+            file.lookup.unwrap_or_default(),
+            file.smtp_password.unwrap_or_default(),
+        ))
+    }
+}
@@ -0,0 +1,596 @@
+use super::diagnostic::Diagnostic;
+use super::Format;
+use crate::ConfigError;
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, TScalarStyle};
+
+/// A range of bytes in the text of a configuration file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    fn new(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end: end.max(start),
+        }
+    }
+}
+
+/// Where a section was written: its key, if it has one, and its value
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Entry {
+    key: Option<Span>,
+    value: Span,
+}
+
+/// The text of a configuration file, with the span of every section in it.
+/// The spans are found by the path of keys to the section, like
+/// `["builders", "cargo-test", "script"]`, or the index for an item of a list.
+///
+/// Sections that come from a YAML alias or `<<` merge key aren't written
+/// under their own path, so they don't have spans.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Source {
+    path: String,
+    format: Format,
+    text: String,
+    entries: HashMap<Vec<String>, Entry>,
+    /// Where the top of this file sits in the configuration it was merged into
+    root: Vec<String>,
+}
+
+impl Source {
+    /// Indexes the text of a file written in `format`.
+    /// `path` is only used to describe where an error came from.
+    pub fn new<S: ToString>(path: S, format: Format, text: S) -> Self {
+        let text = text.to_string();
+        let entries = match format {
+            // JSON is YAML too, as far as the spans are concerned
+            Format::Yaml | Format::Json => yaml_entries(&text),
+            Format::Toml => toml_entries(&text),
+        };
+        Self {
+            path: path.to_string(),
+            format,
+            text,
+            entries,
+            root: vec![],
+        }
+    }
+
+    /// Puts the top of this file at `root` in the configuration it's merged into,
+    /// so its sections are found by the paths they have there.
+    /// A file included by `builders`, for example, holds the sections under `["builders"]`.
+    pub fn with_root<S: ToString>(mut self, root: &[S]) -> Self {
+        self.root = root.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Reads a configuration file, in the format its extension names
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, Diagnostic> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)?;
+        let text = fs::read_to_string(path).map_err(|e| ConfigError::File {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        Ok(Self::new(path.display().to_string(), format, text))
+    }
+
+    pub fn get_path(&self) -> String {
+        self.path.clone()
+    }
+
+    pub fn get_format(&self) -> Format {
+        self.format
+    }
+
+    pub fn get_text(&self) -> String {
+        self.text.clone()
+    }
+
+    pub fn get_root(&self) -> Vec<String> {
+        self.root.clone()
+    }
+
+    /// Is the section at `path` written in this file?
+    pub fn contains<S: AsRef<str>>(&self, path: &[S]) -> bool {
+        self.entry(path).is_some()
+    }
+
+    /// Deserializes the file. A syntax error points at the position the parser
+    /// gave up at, and a section with the wrong type points at that section.
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, Diagnostic> {
+        let (reason, span) = match self.format {
+            Format::Yaml => match self.parse_yaml() {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            },
+            Format::Toml => {
+                let deserializer = toml::Deserializer::new(&self.text);
+                match serde_path_to_error::deserialize(deserializer) {
+                    Ok(value) => return Ok(value),
+                    Err(e) => {
+                        let span = e.inner().span().map(|r| Span::new(r.start, r.end));
+                        let reason = e.inner().message().to_string();
+                        self.describe(e.path(), reason, span)
+                    }
+                }
+            }
+            Format::Json => {
+                let mut deserializer = serde_json::Deserializer::from_str(&self.text);
+                match serde_path_to_error::deserialize(&mut deserializer) {
+                    // Anything after the document is a mistake too
+                    Ok(value) => match deserializer.end() {
+                        Ok(()) => return Ok(value),
+                        Err(e) => (json_reason(&e), self.span_at(e.line(), e.column())),
+                    },
+                    // The parser knows where a syntax error is, but a section with the
+                    // wrong type is easier to find by its path
+                    Err(e) => match e.inner().classify() {
+                        Category::Data => self.describe(e.path(), json_reason(e.inner()), None),
+                        _ => (
+                            json_reason(e.inner()),
+                            self.span_at(e.inner().line(), e.inner().column()),
+                        ),
+                    },
+                }
+            }
+        };
+
+        let error = ConfigError::File {
+            path: self.path.clone(),
+            reason,
+        };
+        Err(match span {
+            Some(span) => Diagnostic::at(error, self, span),
+            None => Diagnostic::in_file(error, self),
+        })
+    }
+
+    /// Deserializes YAML, applying the `<<` merge keys first.
+    /// serde_yaml resolves anchors and aliases itself, but leaves the merge keys to us,
+    /// and they're how builders share their sections with an anchor.
+    fn parse_yaml<T: DeserializeOwned>(&self) -> Result<T, (String, Option<Span>)> {
+        let mut value: serde_yaml::Value = serde_yaml::from_str(&self.text).map_err(|e| {
+            let span = e
+                .location()
+                .map(|l| Span::new(l.index(), self.line_end(l.index())));
+            (e.to_string(), span)
+        })?;
+        value.apply_merge().map_err(|e| (e.to_string(), None))?;
+        serde_path_to_error::deserialize(value)
+            .map_err(|e| self.describe(e.path(), e.inner().to_string(), None))
+    }
+
+    /// Names the section a deserializing error happened in, and finds its span
+    /// if the parser didn't give one
+    fn describe(
+        &self,
+        path: &serde_path_to_error::Path,
+        reason: String,
+        span: Option<Span>,
+    ) -> (String, Option<Span>) {
+        let keys: Vec<String> = self
+            .root
+            .iter()
+            .cloned()
+            .chain(path.iter().map(|segment| segment.to_string()))
+            .collect();
+        let span = span.or_else(|| self.value_span(&keys));
+        match path.to_string().as_str() {
+            "." => (reason, span),
+            path => (format!("{}: {}", path, reason), span),
+        }
+    }
+
+    /// The span of the key of the section at `path`, or its value if it has no key.
+    /// If the section isn't written in the file, this is the span of the closest
+    /// section that holds it.
+    pub fn key_span<S: AsRef<str>>(&self, path: &[S]) -> Option<Span> {
+        self.closest(path)
+            .map(|entry| entry.key.unwrap_or(entry.value))
+    }
+
+    /// The span of the value of the section at `path`, or of the key
+    /// of the closest section that holds it
+    pub fn value_span<S: AsRef<str>>(&self, path: &[S]) -> Option<Span> {
+        match self.entry(path) {
+            Some(entry) => Some(entry.value),
+            None => self.key_span(path),
+        }
+    }
+
+    /// The span of `needle` where it's written in the value of the section at `path`.
+    /// This finds a line of a script, whether the script is a list or a block.
+    pub(crate) fn find_in<S: AsRef<str>>(&self, path: &[S], needle: &str) -> Option<Span> {
+        let value = self.entry(path)?.value;
+        if needle.is_empty() {
+            return None;
+        }
+        let start = value.start + self.text.get(value.start..value.end)?.find(needle)?;
+        Some(Span::new(start, start + needle.len()))
+    }
+
+    /// The span of the first line of the file that `line` is, ignoring indentation
+    pub(crate) fn find_line(&self, line: &str) -> Option<Span> {
+        let line = line.trim();
+        let mut start = 0;
+        for text in self.text.split('\n') {
+            if text.trim() == line {
+                let indent = text.len() - text.trim_start().len();
+                return Some(Span::new(start + indent, start + indent + line.len()));
+            }
+            start += text.len() + 1;
+        }
+        None
+    }
+
+    /// The names of the sections in the section at `path`, in no particular order
+    pub(crate) fn get_children<S: AsRef<str>>(&self, path: &[S]) -> Vec<String> {
+        let path = match self.in_file(path) {
+            Some(path) => path,
+            None => return vec![],
+        };
+        self.entries
+            .keys()
+            .filter(|key| key.len() == path.len() + 1 && key.starts_with(&path))
+            .map(|key| key[path.len()].clone())
+            .collect()
+    }
+
+    /// The path a section has in this file, if it's under the root of the file
+    fn in_file<S: AsRef<str>>(&self, path: &[S]) -> Option<Vec<String>> {
+        let path: Vec<String> = path.iter().map(|s| s.as_ref().to_string()).collect();
+        path.strip_prefix(self.root.as_slice())
+            .map(<[String]>::to_vec)
+    }
+
+    fn entry<S: AsRef<str>>(&self, path: &[S]) -> Option<Entry> {
+        self.entries.get(&self.in_file(path)?).copied()
+    }
+
+    fn closest<S: AsRef<str>>(&self, path: &[S]) -> Option<Entry> {
+        (1..=path.len())
+            .rev()
+            .find_map(|len| self.entry(&path[..len]))
+    }
+
+    /// The 1-based line and column that a byte of the text is on
+    pub(crate) fn line_and_column(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..floor_char_boundary(&self.text, offset)];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        (line, before[line_start..].chars().count() + 1)
+    }
+
+    /// The text of the line that a byte of the text is on
+    pub(crate) fn line_of(&self, offset: usize) -> String {
+        let offset = floor_char_boundary(&self.text, offset);
+        let start = self.text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        self.text[start..self.line_end(offset)].to_string()
+    }
+
+    /// The byte that ends the line `offset` is on, before the newline
+    fn line_end(&self, offset: usize) -> usize {
+        let offset = floor_char_boundary(&self.text, offset);
+        self.text[offset..]
+            .find(['\n', '\r'])
+            .map(|i| offset + i)
+            .unwrap_or_else(|| self.text.len())
+    }
+
+    /// The span of a 1-based line and column, to the end of that line
+    fn span_at(&self, line: usize, column: usize) -> Option<Span> {
+        if line == 0 {
+            return None;
+        }
+        let line_start: usize = self
+            .text
+            .split('\n')
+            .take(line - 1)
+            .map(|text| text.len() + 1)
+            .sum();
+        let start = self.text.get(line_start..)?;
+        let start = line_start
+            + start
+                .char_indices()
+                .nth(column.saturating_sub(1))
+                .map(|(i, _)| i)
+                .unwrap_or_else(|| start.len());
+        Some(Span::new(start, self.line_end(start)))
+    }
+}
+
+/// The message of a JSON error, without the line and column it ends with
+fn json_reason(error: &serde_json::Error) -> String {
+    let reason = error.to_string();
+    let suffix = format!(" at line {} column {}", error.line(), error.column());
+    reason
+        .strip_suffix(&suffix)
+        .map(String::from)
+        .unwrap_or(reason)
+}
+
+/// The closest char boundary at or before `offset`
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Where the YAML parser is in the tree of sections
+enum Frame {
+    Mapping {
+        path: Vec<String>,
+        start: usize,
+        key: Option<(String, Span)>,
+    },
+    Sequence {
+        path: Vec<String>,
+        start: usize,
+        index: usize,
+        key: Option<Span>,
+    },
+}
+
+/// Records the span of every node of a YAML document as the parser finds them
+struct YamlIndexer<'a> {
+    text: &'a str,
+    /// The byte that each char of the text starts at, because the parser counts chars
+    offsets: Vec<usize>,
+    stack: Vec<Frame>,
+    entries: HashMap<Vec<String>, Entry>,
+    /// A block scalar, which ends where the next event starts
+    unfinished: Option<(Vec<String>, Entry)>,
+}
+
+impl<'a> YamlIndexer<'a> {
+    fn offset(&self, mark: Marker) -> usize {
+        self.offsets
+            .get(mark.index())
+            .copied()
+            .unwrap_or(self.text.len())
+    }
+
+    /// The path and key span of the node that starts now, if it's a value,
+    /// or `None` if it's the key of a mapping
+    fn next_node(&mut self) -> Option<(Vec<String>, Option<Span>)> {
+        match self.stack.last_mut() {
+            None => Some((vec![], None)),
+            Some(Frame::Mapping { path, key, .. }) => match key.take() {
+                Some((name, span)) => {
+                    let mut path = path.clone();
+                    path.push(name);
+                    Some((path, Some(span)))
+                }
+                None => None,
+            },
+            Some(Frame::Sequence { path, index, .. }) => {
+                let mut path = path.clone();
+                path.push(index.to_string());
+                *index += 1;
+                Some((path, None))
+            }
+        }
+    }
+
+    /// The end of a scalar that starts at `start`
+    fn scalar_end(&self, start: usize, value: &str, style: &TScalarStyle) -> usize {
+        let rest = &self.text[start..];
+        match style {
+            TScalarStyle::SingleQuoted => quoted_end(rest, '\'').map(|i| start + i),
+            TScalarStyle::DoubleQuoted => quoted_end(rest, '"').map(|i| start + i),
+            _ => Some(start + value.len().min(rest.len())),
+        }
+        .unwrap_or(start)
+    }
+}
+
+/// The length of a quoted scalar at the start of `text`, including the quotes
+fn quoted_end(text: &str, quote: char) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            // Single quotes are escaped by doubling them
+            if quote == '\'' && chars.peek().map(|(_, c)| *c) == Some('\'') {
+                chars.next();
+            } else {
+                return Some(i + c.len_utf8());
+            }
+        }
+    }
+    None
+}
+
+impl<'a> MarkedEventReceiver for YamlIndexer<'a> {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        let offset = self.offset(mark);
+        if let Some((path, mut entry)) = self.unfinished.take() {
+            let end = self.text[..offset.max(entry.value.start)].trim_end().len();
+            entry.value = Span::new(entry.value.start, end);
+            self.entries.insert(path, entry);
+        }
+
+        match event {
+            Event::Scalar(value, style, ..) => {
+                let end = self.scalar_end(offset, &value, &style);
+                match self.next_node() {
+                    Some((path, key)) => {
+                        let entry = Entry {
+                            key,
+                            value: Span::new(offset, end),
+                        };
+                        match style {
+                            TScalarStyle::Literal | TScalarStyle::Foled => {
+                                self.unfinished = Some((path, entry))
+                            }
+                            _ => {
+                                self.entries.insert(path, entry);
+                            }
+                        }
+                    }
+                    // This is the key of a mapping
+                    None => {
+                        if let Some(Frame::Mapping { key, .. }) = self.stack.last_mut() {
+                            *key = Some((value, Span::new(offset, end)));
+                        }
+                    }
+                }
+            }
+            Event::Alias(_) => {
+                if let Some((path, key)) = self.next_node() {
+                    let value = Span::new(offset, self.text.len().min(offset + 1));
+                    self.entries.insert(path, Entry { key, value });
+                }
+            }
+            Event::MappingStart(_) => {
+                let (path, key) = self.next_node().unwrap_or_default();
+                if let Some(key) = key {
+                    self.entries.insert(
+                        path.clone(),
+                        Entry {
+                            key: Some(key),
+                            value: Span::new(offset, offset),
+                        },
+                    );
+                }
+                self.stack.push(Frame::Mapping {
+                    path,
+                    start: offset,
+                    key: None,
+                });
+            }
+            Event::SequenceStart(_) => {
+                let (path, key) = self.next_node().unwrap_or_default();
+                self.stack.push(Frame::Sequence {
+                    path,
+                    start: offset,
+                    index: 0,
+                    key,
+                });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                let (path, start, key) = match self.stack.pop() {
+                    Some(Frame::Mapping { path, start, .. }) => {
+                        let key = self.entries.get(&path).and_then(|entry| entry.key);
+                        (path, start, key)
+                    }
+                    Some(Frame::Sequence {
+                        path, start, key, ..
+                    }) => (path, start, key),
+                    None => return,
+                };
+                // A block ends where the next section starts, so leave out the whitespace
+                let end = self.text[..offset.max(start)].trim_end().len();
+                let value = Span::new(start, end);
+                self.entries.insert(path, Entry { key, value });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Finds the span of every section of a YAML or JSON document.
+/// If the document can't be parsed, the sections before the mistake still have their spans.
+fn yaml_entries(text: &str) -> HashMap<Vec<String>, Entry> {
+    let mut offsets: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    offsets.push(text.len());
+    let mut indexer = YamlIndexer {
+        text,
+        offsets,
+        stack: vec![],
+        entries: HashMap::new(),
+        unfinished: None,
+    };
+    let _ = Parser::new(text.chars()).load(&mut indexer, false);
+    indexer.entries
+}
+
+/// Finds the span of every section of a TOML document
+fn toml_entries(text: &str) -> HashMap<Vec<String>, Entry> {
+    let mut entries = HashMap::new();
+    if let Ok(document) = toml_edit::ImDocument::parse(text) {
+        toml_table(document.as_table(), &mut vec![], &mut entries);
+    }
+    entries
+}
+
+fn toml_span(range: Option<std::ops::Range<usize>>) -> Option<Span> {
+    range.map(|r| Span::new(r.start, r.end))
+}
+
+fn toml_table(
+    table: &toml_edit::Table,
+    path: &mut Vec<String>,
+    entries: &mut HashMap<Vec<String>, Entry>,
+) {
+    for (name, item) in table.iter() {
+        path.push(name.to_string());
+        let key = table.key(name).and_then(|key| toml_span(key.span()));
+        let value = toml_span(item.span()).or(key);
+        if let Some(value) = value {
+            entries.insert(path.clone(), Entry { key, value });
+        }
+        match item {
+            toml_edit::Item::Table(table) => toml_table(table, path, entries),
+            toml_edit::Item::Value(value) => toml_value(value, path, entries),
+            toml_edit::Item::ArrayOfTables(tables) => {
+                for (i, table) in tables.iter().enumerate() {
+                    path.push(i.to_string());
+                    if let Some(value) = toml_span(table.span()) {
+                        entries.insert(path.clone(), Entry { key: None, value });
+                    }
+                    toml_table(table, path, entries);
+                    path.pop();
+                }
+            }
+            toml_edit::Item::None => {}
+        }
+        path.pop();
+    }
+}
+
+fn toml_value(
+    value: &toml_edit::Value,
+    path: &mut Vec<String>,
+    entries: &mut HashMap<Vec<String>, Entry>,
+) {
+    match value {
+        toml_edit::Value::Array(array) => {
+            for (i, item) in array.iter().enumerate() {
+                path.push(i.to_string());
+                if let Some(value) = toml_span(item.span()) {
+                    entries.insert(path.clone(), Entry { key: None, value });
+                }
+                toml_value(item, path, entries);
+                path.pop();
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            for (name, item) in table.iter() {
+                path.push(name.to_string());
+                let key = table.key(name).and_then(|key| toml_span(key.span()));
+                if let Some(value) = toml_span(item.span()).or(key) {
+                    entries.insert(path.clone(), Entry { key, value });
+                }
+                toml_value(item, path, entries);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,24 +1,56 @@
-use std::fmt::Display;
-use std::process::Command;
-use std::str::from_utf8;
+use std::fmt::{Display, Error, Formatter};
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often to check whether a command with a timeout has finished yet
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// This struct is basically identical to the std::process::Command,
-/// but when it is executed, it returns the stdout of the process as a string.
+/// but when it is executed, it returns everything the process printed along with its exit status.
 #[derive(Clone, PartialEq, Debug)]
 pub struct Cmd {
     pub program: String,
     pub args: Vec<String>,
+    /// Environment variables to set for the program, on top of the ones rusty-ci has
+    pub env: Vec<(String, String)>,
+    /// The directory to run the program in, or None for the current directory
+    pub cwd: Option<String>,
+    /// How long the program can run before it's killed, or None to wait forever
+    pub timeout: Option<Duration>,
+    /// Whether to print the output of the program while it runs, as well as capturing it
+    pub stream: bool,
+}
+
+/// What a finished command returned
+#[derive(Clone, PartialEq, Debug)]
+pub struct CmdOutput {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CmdOutput {
+    /// Whether the command exited successfully
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
 }
 
 impl Cmd {
     /// Create a command that will call `program`.
     /// For example, if you want to run the command
     /// `echo 'hello world!'`, you would write:
-    /// Cmd::new("echo").arg("'hello world!'").run();
+    /// Cmd::new("echo").arg("hello world!").run();
     pub fn new<S: Display>(program: S) -> Self {
         Self {
             program: program.to_string(),
             args: vec![],
+            env: vec![],
+            cwd: None,
+            timeout: None,
+            stream: false,
         }
     }
 
@@ -28,15 +60,164 @@ impl Cmd {
         self
     }
 
-    /// Execute the shell command we've defined
-    pub fn run(&self) -> String {
-        match Command::new(&self.program).args(&self.args).output() {
-            // If Ok, return stdout as String
-            Ok(o) => match from_utf8(&o.stdout) {
-                Ok(s) => s.to_string(),
-                Err(_) => String::from(""),
-            },
-            Err(_) => String::from(""),
+    /// Set an environment variable for the program we're calling
+    pub fn env<K: Display, V: Display>(mut self, key: K, value: V) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Run the program in the directory `dir`
+    pub fn cwd<S: Display>(mut self, dir: S) -> Self {
+        self.cwd = Some(dir.to_string());
+        self
+    }
+
+    /// Kill the program if it runs for longer than `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Print the output of the program as it runs instead of only capturing it
+    pub fn stream(mut self) -> Self {
+        self.stream = true;
+        self
+    }
+
+    /// Execute the command we've defined, and fail if it doesn't exit successfully.
+    /// The error has the command line and whatever the program printed to stderr.
+    pub fn run(&self) -> Result<CmdOutput, String> {
+        let output = self.output()?;
+        if output.success() {
+            return Ok(output);
+        }
+
+        let status = match output.status.code() {
+            Some(code) => format!("exit status {}", code),
+            None => String::from("a signal"),
+        };
+        let stderr = output.stderr.trim();
+        if stderr.is_empty() {
+            Err(format!("`{}` failed with {}", self, status))
+        } else {
+            Err(format!("`{}` failed with {}:\n{}", self, status, stderr))
+        }
+    }
+
+    /// Execute the command we've defined, and return its output whether it succeeded or not.
+    /// This only fails if the program couldn't be started, or if it ran past its timeout.
+    pub fn output(&self) -> Result<CmdOutput, String> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        for (key, value) in &self.env {
+            command.env(key, value);
+        }
+        if let Some(dir) = &self.cwd {
+            command.current_dir(dir);
+        }
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => return Err(format!("Could not run `{}` because {}", self, e)),
+        };
+
+        // Read both pipes at the same time, so the program never blocks on a full pipe
+        let stdout = capture(child.stdout.take(), self.stream, false);
+        let stderr = capture(child.stderr.take(), self.stream, true);
+
+        let status = self.wait(&mut child)?;
+        Ok(CmdOutput {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+
+    /// Waits for the program to exit, killing it if it runs past the timeout
+    fn wait(&self, child: &mut Child) -> Result<ExitStatus, String> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => {
+                return child
+                    .wait()
+                    .map_err(|e| format!("Could not wait for `{}` because {}", self, e))
+            }
+        };
+
+        let start = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => return Ok(status),
+                Ok(None) if start.elapsed() >= timeout => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!(
+                        "`{}` was killed because it ran for longer than {} seconds",
+                        self,
+                        timeout.as_secs_f64()
+                    ));
+                }
+                Ok(None) => sleep(POLL_INTERVAL),
+                Err(e) => return Err(format!("Could not wait for `{}` because {}", self, e)),
+            }
+        }
+    }
+}
+
+/// Reads everything from one of the program's pipes in another thread.
+/// If `stream` is true, it's also printed to our stdout, or stderr if `to_stderr` is true.
+fn capture<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    stream: bool,
+    to_stderr: bool,
+) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut pipe = match pipe {
+            Some(pipe) => pipe,
+            None => return String::new(),
+        };
+
+        let mut captured = vec![];
+        let mut buffer = [0; 4096];
+        while let Ok(n) = pipe.read(&mut buffer) {
+            if n == 0 {
+                break;
+            }
+            captured.extend_from_slice(&buffer[..n]);
+            if stream {
+                // Failing to print the output shouldn't stop us from capturing it
+                let _ = if to_stderr {
+                    io::stderr().write_all(&buffer[..n])
+                } else {
+                    io::stdout().write_all(&buffer[..n])
+                };
+            }
+        }
+        String::from_utf8_lossy(&captured).to_string()
+    })
+}
+
+/// Quotes a word so that it reads the same way in a shell
+fn shell_quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Displays the command line, quoted the way you'd type it into a shell
+impl Display for Cmd {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", shell_quote(&self.program))?;
+        for arg in &self.args {
+            write!(f, " {}", shell_quote(arg))?;
         }
+        Ok(())
     }
 }
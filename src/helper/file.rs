@@ -18,21 +18,15 @@ impl File {
                     Ok(_) => Ok(contents),
                     Err(e) => Err(format!(
                         "Could not read from file '{}' because {}",
-                        match path.as_ref().to_str() {
-                            Some(s) => s,
-                            None => "",
-                        },
-                        e.to_string()
+                        path.as_ref().to_str().unwrap_or_default(),
+                        e
                     )),
                 }
             }
             Err(e) => Err(format!(
                 "Could not open file '{}' because {}",
-                match path.as_ref().to_str() {
-                    Some(s) => s,
-                    None => "",
-                },
-                e.to_string()
+                path.as_ref().to_str().unwrap_or_default(),
+                e
             )),
         }
     }
@@ -52,48 +46,74 @@ impl File {
                 Ok(_) => Ok(()),
                 Err(e) => Err(format!(
                     "Could not open file '{}' because {}",
-                    match path.as_ref().to_str() {
-                        Some(s) => s,
-                        None => "",
-                    },
-                    e.to_string()
+                    path.as_ref().to_str().unwrap_or_default(),
+                    e
                 )),
             },
             Err(e) => Err(format!(
                 "Could not open file '{}' because {}",
-                match path.as_ref().to_str() {
-                    Some(s) => s,
-                    None => "",
-                },
-                e.to_string()
+                path.as_ref().to_str().unwrap_or_default(),
+                e
             )),
         }
     }
 
+    /// This does the same as write, but creates the directories leading to the file,
+    /// and only lets the current user read or write the file.
+    /// This is for files with secrets in them, like passwords.
+    pub fn write_private<P: AsRef<Path>, S: Display>(path: P, contents: S) -> Result<(), String> {
+        let path = path.as_ref();
+        let name = path.to_str().unwrap_or_default();
+        if let Some(dir) = path.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                return Err(format!(
+                    "Could not create the directory for '{}' because {}",
+                    name, e
+                ));
+            }
+        }
+
+        let mut options = fs::OpenOptions::new();
+        options.create(true).write(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            // The mode only applies to new files, so fix up files that already exist
+            if path.exists() {
+                if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(0o600)) {
+                    return Err(format!("Could not protect file '{}' because {}", name, e));
+                }
+            }
+        }
+
+        match options.open(path) {
+            Ok(mut f) => match writeln!(f, "{}", contents) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(format!("Could not write to file '{}' because {}", name, e)),
+            },
+            Err(e) => Err(format!("Could not open file '{}' because {}", name, e)),
+        }
+    }
+
     /// This does the same as write, but does not wipe the file,
     /// and appends `contents` to the end of the file.
     pub fn append<P: AsRef<Path>, S: Display>(path: P, contents: S) -> Result<(), String> {
-        let mut file = fs::OpenOptions::new().append(true).write(true).open(&path);
+        let mut file = fs::OpenOptions::new().append(true).open(&path);
 
         match &mut file {
             Ok(f) => match writeln!(f, "{}", contents) {
                 Ok(_) => Ok(()),
                 Err(e) => Err(format!(
                     "Could not open file '{}' because {}",
-                    match path.as_ref().to_str() {
-                        Some(s) => s,
-                        None => "",
-                    },
-                    e.to_string()
+                    path.as_ref().to_str().unwrap_or_default(),
+                    e
                 )),
             },
             Err(e) => Err(format!(
                 "Could not open file '{}' because {}",
-                match path.as_ref().to_str() {
-                    Some(s) => s,
-                    None => "",
-                },
-                e.to_string()
+                path.as_ref().to_str().unwrap_or_default(),
+                e
             )),
         }
     }
@@ -1,14 +1,21 @@
 mod string;
-pub use string::unquote;
+pub use string::{shell_words, unquote};
 
 mod file;
 pub use file::File;
 
 mod cmd;
-pub use cmd::Cmd;
+pub use cmd::{Cmd, CmdOutput};
 
 mod yaml;
-pub use yaml::{unmatched_quotes, unwrap};
+pub use yaml::{scalar, unmatched_quotes, unwrap};
 
 pub mod stdio;
 pub use stdio::{input, yes_or_no};
+
+pub mod python;
+pub use python::{PyRegex, ToPython};
+
+pub mod python_ast;
+
+pub mod process;
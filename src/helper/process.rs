@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// The name of the file twistd writes the process id of a master or worker to,
+/// inside the master or worker's directory
+pub const PID_FILE: &str = "twistd.pid";
+
+/// How long to wait for a master or worker to shut down before killing it
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to check whether a process has shut down yet
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What happened when we tried to stop a process
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stopped {
+    /// There was no process running
+    NotRunning,
+    /// The process shut down on its own after we asked it to
+    Gracefully(u32),
+    /// The process didn't shut down in time, so it was killed
+    Killed(u32),
+}
+
+/// Returns the path of the pid file in a master or worker's directory
+pub fn pid_file<P: AsRef<Path>>(dir: P) -> PathBuf {
+    dir.as_ref().join(PID_FILE)
+}
+
+/// Reads the process id from the pid file in a master or worker's directory.
+/// This returns None if there's no pid file, which means nothing is running there.
+pub fn read_pid<P: AsRef<Path>>(dir: P) -> Result<Option<u32>, String> {
+    let path = pid_file(dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => match contents.trim().parse::<u32>() {
+            // `kill 0` would signal our own process group, so 0 is never a real pid here
+            Ok(pid) if pid != 0 => Ok(Some(pid)),
+            _ => Err(format!(
+                "The pid file '{}' doesn't contain a process id",
+                path.display()
+            )),
+        },
+        Err(e) => Err(format!(
+            "Could not read the pid file '{}' because {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+/// Sends a signal to a process with the `kill` program.
+/// Returns whether the signal was delivered.
+fn signal(pid: u32, signal: &str) -> bool {
+    Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Returns whether a process is running.
+/// This is an error if the process exists but belongs to someone we can't signal.
+pub fn is_running(pid: u32) -> Result<bool, String> {
+    // Signal 0 doesn't do anything to the process, but still fails if it doesn't exist
+    if signal(pid, "0") {
+        return Ok(true);
+    }
+
+    // `kill` also fails when we aren't allowed to signal the process,
+    // but `ps` can still see processes that belong to other users
+    if command_line(pid).is_some() {
+        Err(format!(
+            "The process {} is running, but we don't have permission to signal it",
+            pid
+        ))
+    } else {
+        Ok(false)
+    }
+}
+
+/// Returns the command line a process was started with, or None if there's no such process
+fn command_line(pid: u32) -> Option<String> {
+    // Zombies and some sandboxes have an empty cmdline, so only trust it if it has something in it
+    if let Ok(cmdline) = fs::read(format!("/proc/{}/cmdline", pid)) {
+        if !cmdline.is_empty() {
+            return Some(String::from_utf8_lossy(&cmdline).replace('\0', " "));
+        }
+    }
+
+    // Not every system has a /proc filesystem, so fall back on `ps`
+    let output = Command::new("ps")
+        .args(["-o", "args=", "-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+/// Returns whether a process is a buildbot master or worker.
+/// Both are run by twistd, but the pid in a stale pid file may have been
+/// reused by an unrelated process, which we mustn't stop.
+pub fn is_buildbot(pid: u32) -> bool {
+    match command_line(pid) {
+        Some(cmdline) => cmdline.contains("twistd") || cmdline.contains("buildbot"),
+        None => false,
+    }
+}
+
+/// Waits up to `timeout` for a process to exit, and returns whether it did
+fn wait_for_exit(pid: u32, timeout: Duration) -> Result<bool, String> {
+    let start = Instant::now();
+    while is_running(pid)? {
+        if start.elapsed() >= timeout {
+            return Ok(false);
+        }
+        sleep(POLL_INTERVAL);
+    }
+    Ok(true)
+}
+
+/// Stops a process by asking it to shut down with SIGTERM.
+/// If it's still running after `timeout`, it is killed with SIGKILL.
+pub fn stop_pid(pid: u32, timeout: Duration) -> Result<Stopped, String> {
+    if !is_running(pid)? {
+        return Ok(Stopped::NotRunning);
+    }
+
+    signal(pid, "TERM");
+    if wait_for_exit(pid, timeout)? {
+        return Ok(Stopped::Gracefully(pid));
+    }
+
+    signal(pid, "KILL");
+    if wait_for_exit(pid, timeout)? {
+        Ok(Stopped::Killed(pid))
+    } else {
+        Err(format!("Could not kill the process {}", pid))
+    }
+}
+
+/// Stops the master or worker running in `dir`, using the pid file twistd wrote there.
+/// Only the process in the pid file is stopped, nothing else running on the machine.
+///
+/// A process that is killed doesn't get a chance to remove its pid file,
+/// so any pid file left behind is removed too. If the pid in the file belongs to
+/// something other than twistd, the file is stale and the process is left alone.
+pub fn stop_dir<P: AsRef<Path>>(dir: P, timeout: Duration) -> Result<Stopped, String> {
+    let stopped = match read_pid(&dir)? {
+        Some(pid) if is_running(pid)? && !is_buildbot(pid) => Stopped::NotRunning,
+        Some(pid) => stop_pid(pid, timeout)?,
+        None => return Ok(Stopped::NotRunning),
+    };
+
+    let path = pid_file(&dir);
+    if path.exists() {
+        if let Err(e) = fs::remove_file(&path) {
+            return Err(format!(
+                "Could not remove the pid file '{}' because {}",
+                path.display(),
+                e
+            ));
+        }
+    }
+
+    Ok(stopped)
+}
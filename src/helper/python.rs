@@ -0,0 +1,170 @@
+use super::Cmd;
+use std::collections::BTreeMap;
+
+/// This trait converts a Rust value into the source code of the equivalent Python literal.
+///
+/// Every value that comes from the user's YAML and ends up in a generated
+/// Python file should go through this trait, so that quotes, backslashes and
+/// newlines in the YAML can't break or inject code into the output.
+pub trait ToPython {
+    fn to_python(&self) -> String;
+}
+
+/// A regular expression, written as a raw Python string when possible
+/// so that the output stays readable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PyRegex<S: AsRef<str>>(pub S);
+
+/// Returns a valid Python identifier for `name`.
+/// Every character that can't be in an identifier is replaced by an underscore,
+/// so a scheduler named `any-change` becomes the variable `any_change`.
+pub fn identifier(name: &str) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    // Identifiers can't be empty or start with a digit
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+
+    result
+}
+
+/// Compiles a regular expression with python's `re` module, the way the generated master config does.
+/// Python's regexes have features that the `regex` crate doesn't, like look-ahead and backreferences,
+/// so this is the only way to know for sure whether buildbot will accept one.
+///
+/// This returns None if the regex is valid, the reason python gave if it isn't,
+/// or an error if `python3` couldn't be run at all.
+pub fn regex_error(regex: &str) -> Result<Option<String>, String> {
+    let output = Cmd::new("python3")
+        .arg("-c")
+        .arg("import re, sys\ntry:\n    re.compile(sys.argv[1])\nexcept re.error as e:\n    print(e)\n    sys.exit(1)")
+        .arg(regex)
+        .output()?;
+    match output.status.code() {
+        Some(0) => Ok(None),
+        Some(1) if !output.stdout.trim().is_empty() => Ok(Some(output.stdout.trim().to_string())),
+        _ => Err(format!(
+            "python3 could not check the regular expression: {}",
+            output.stderr.trim()
+        )),
+    }
+}
+
+impl ToPython for str {
+    /// Writes a double quoted Python string literal, escaping everything
+    /// that would end the literal early or is not printable.
+    fn to_python(&self) -> String {
+        let mut result = String::from("\"");
+        for c in self.chars() {
+            match c {
+                '\\' => result.push_str("\\\\"),
+                '"' => result.push_str("\\\""),
+                '\n' => result.push_str("\\n"),
+                '\r' => result.push_str("\\r"),
+                '\t' => result.push_str("\\t"),
+                c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                    result.push_str(&format!("\\x{:02x}", c as u32))
+                }
+                // Python would treat these as line breaks in the source
+                '\u{2028}' | '\u{2029}' | '\u{85}' => {
+                    result.push_str(&format!("\\u{:04x}", c as u32))
+                }
+                c => result.push(c),
+            }
+        }
+        result.push('"');
+        result
+    }
+}
+
+impl ToPython for String {
+    fn to_python(&self) -> String {
+        self.as_str().to_python()
+    }
+}
+
+impl<S: AsRef<str>> ToPython for PyRegex<S> {
+    /// A raw string can't contain its own quote or a line break,
+    /// and can't end in an odd number of backslashes.
+    /// In any of those cases, we fall back on a regular string literal.
+    fn to_python(&self) -> String {
+        let regex = self.0.as_ref();
+        let trailing_backslashes = regex.chars().rev().take_while(|c| *c == '\\').count();
+        if regex
+            .chars()
+            .any(|c| c == '"' || c.is_control() || c == '\u{2028}' || c == '\u{2029}')
+            || trailing_backslashes % 2 == 1
+        {
+            regex.to_python()
+        } else {
+            format!("r\"{}\"", regex)
+        }
+    }
+}
+
+impl ToPython for bool {
+    fn to_python(&self) -> String {
+        String::from(if *self { "True" } else { "False" })
+    }
+}
+
+macro_rules! integer_to_python {
+    ($($t:ty),*) => {
+        $(impl ToPython for $t {
+            fn to_python(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+integer_to_python!(u8, u16, u32, u64, usize, i32, i64);
+
+impl<T: ToPython> ToPython for Option<T> {
+    fn to_python(&self) -> String {
+        match self {
+            Some(value) => value.to_python(),
+            None => String::from("None"),
+        }
+    }
+}
+
+impl<T: ToPython + ?Sized> ToPython for &T {
+    fn to_python(&self) -> String {
+        (*self).to_python()
+    }
+}
+
+impl<T: ToPython> ToPython for [T] {
+    fn to_python(&self) -> String {
+        format!(
+            "[{}]",
+            self.iter()
+                .map(|item| item.to_python())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+impl<T: ToPython> ToPython for Vec<T> {
+    fn to_python(&self) -> String {
+        self.as_slice().to_python()
+    }
+}
+
+impl<K: ToPython, V: ToPython> ToPython for BTreeMap<K, V> {
+    fn to_python(&self) -> String {
+        format!(
+            "{{{}}}",
+            self.iter()
+                .map(|(key, value)| format!("{}: {}", key.to_python(), value.to_python()))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
@@ -0,0 +1,483 @@
+use crate::ToPython;
+use std::fmt::{Display, Error, Formatter};
+use std::ops::Not;
+
+/// Lines of generated Python longer than this are split up,
+/// one argument or list item per line.
+pub const MAX_WIDTH: usize = 79;
+
+/// The indentation of one block of Python code
+const INDENT: &str = "    ";
+
+/// A Python expression.
+///
+/// User supplied values should only ever get into an expression through
+/// `Expr::lit`, which escapes them. `Expr::name` and `Expr::raw` are written
+/// into the output as is, so they're only for code we write ourselves.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// Python source code that is written as is, like a name or a literal
+    Raw(String),
+    /// `value.attr`
+    Attr(Box<Expr>, String),
+    /// `value[index]`
+    Index(Box<Expr>, Box<Expr>),
+    /// `func(args..., key=value...)`
+    Call(Box<Call>),
+    /// `[items...]`
+    List(Vec<Expr>),
+    /// `{key: value...}`
+    Dict(Vec<(Expr, Expr)>),
+    /// `left op right`, like `a in b` or `a + b`
+    BinOp(Box<Expr>, &'static str, Box<Expr>),
+    /// `not value`
+    Not(Box<Expr>),
+}
+
+/// A call to a Python function, built up one argument at a time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Call {
+    func: Expr,
+    args: Vec<Expr>,
+    kwargs: Vec<(String, Expr)>,
+}
+
+/// A Python statement.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stmt {
+    /// An expression on its own line, usually a call
+    Expr(Expr),
+    /// `target = value`
+    Assign(Expr, Expr),
+    /// A function definition with its decorators
+    Def {
+        decorators: Vec<Expr>,
+        name: String,
+        params: Vec<String>,
+        body: Vec<Stmt>,
+    },
+    /// `if test:` with an optional `else:`
+    If {
+        test: Expr,
+        body: Vec<Stmt>,
+        orelse: Vec<Stmt>,
+    },
+    /// `for target in iter:`
+    For {
+        target: String,
+        iter: Expr,
+        body: Vec<Stmt>,
+    },
+    /// `try:` followed by one or more `except` clauses
+    Try {
+        body: Vec<Stmt>,
+        handlers: Vec<Except>,
+    },
+    /// `return` with an optional value
+    Return(Option<Expr>),
+    /// A comment, which can span multiple lines
+    Comment(String),
+    /// A line of Python code that is written as is, like an import
+    Raw(String),
+    /// An empty line
+    Blank,
+}
+
+/// An `except` clause of a `try` statement.
+/// Without an exception, this catches everything.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Except {
+    pub exception: Option<Expr>,
+    pub name: Option<String>,
+    pub body: Vec<Stmt>,
+}
+
+impl Expr {
+    /// The literal for a Rust value, escaped with `ToPython`
+    pub fn lit<T: ToPython>(value: T) -> Self {
+        Expr::Raw(value.to_python())
+    }
+
+    /// A name, or a dotted path like `util.Property`
+    pub fn name<S: ToString>(name: S) -> Self {
+        Expr::Raw(name.to_string())
+    }
+
+    /// Python code that is written as is
+    pub fn raw<S: ToString>(code: S) -> Self {
+        Expr::Raw(code.to_string())
+    }
+
+    /// The list of the given expressions
+    pub fn list<I: IntoIterator<Item = Expr>>(items: I) -> Self {
+        Expr::List(items.into_iter().collect())
+    }
+
+    /// `self.attr`
+    pub fn attr(self, attr: &str) -> Self {
+        Expr::Attr(Box::new(self), attr.to_string())
+    }
+
+    /// `self[index]`
+    pub fn index(self, index: Expr) -> Self {
+        Expr::Index(Box::new(self), Box::new(index))
+    }
+
+    /// Starts a call to the method `self.method`
+    pub fn method(self, method: &str) -> Call {
+        Call::new(self.attr(method))
+    }
+
+    /// `self op other`
+    pub fn op(self, op: &'static str, other: Expr) -> Self {
+        Expr::BinOp(Box::new(self), op, Box::new(other))
+    }
+
+    /// Renders the expression on a single line
+    fn flat(&self) -> String {
+        match self {
+            Expr::Raw(code) => code.clone(),
+            Expr::Attr(value, attr) => format!("{}.{}", value.operand(), attr),
+            Expr::Index(value, index) => format!("{}[{}]", value.operand(), index.flat()),
+            Expr::Call(call) => format!(
+                "{}({})",
+                call.func.operand(),
+                call.arguments()
+                    .into_iter()
+                    .map(|(key, value)| argument(key, &value.flat()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Expr::List(items) => format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(Expr::flat)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Expr::Dict(items) => format!(
+                "{{{}}}",
+                items
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key.flat(), value.flat()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Expr::BinOp(left, op, right) => {
+                format!("{} {} {}", left.operand(), op, right.operand())
+            }
+            Expr::Not(value) => format!("not {}", value.operand()),
+        }
+    }
+
+    /// Renders the expression so that it can be used as part of a bigger expression,
+    /// wrapping it in parentheses if it's an operation.
+    fn operand(&self) -> String {
+        match self {
+            Expr::BinOp(..) | Expr::Not(..) => format!("({})", self.flat()),
+            _ => self.flat(),
+        }
+    }
+
+    /// Renders the expression starting at `column` of a line indented by `indent`.
+    /// If it doesn't fit in `MAX_WIDTH`, the items of calls, lists and dicts
+    /// are each put on their own line.
+    fn render(&self, indent: usize, column: usize) -> String {
+        let flat = self.flat();
+        if column + flat.chars().count() <= MAX_WIDTH {
+            return flat;
+        }
+
+        let (open, items, close) = match self {
+            Expr::Call(call) if !call.args.is_empty() || !call.kwargs.is_empty() => (
+                format!("{}(", call.func.operand()),
+                call.arguments()
+                    .into_iter()
+                    .map(|(key, value)| (key.map(|k| format!("{}=", k)), value))
+                    .collect::<Vec<_>>(),
+                ")",
+            ),
+            Expr::List(items) if !items.is_empty() => (
+                String::from("["),
+                items.iter().map(|item| (None, item)).collect(),
+                "]",
+            ),
+            Expr::Dict(items) if !items.is_empty() => (
+                String::from("{"),
+                items
+                    .iter()
+                    .map(|(key, value)| (Some(format!("{}: ", key.flat())), value))
+                    .collect(),
+                "}",
+            ),
+            _ => return flat,
+        };
+
+        let inner = indent + 1;
+        let mut result = open;
+        for (prefix, value) in items {
+            let prefix = prefix.unwrap_or_default();
+            result += "\n";
+            result += &INDENT.repeat(inner);
+            result += &prefix;
+            result += &value.render(inner, inner * INDENT.len() + prefix.len());
+            result += ",";
+        }
+        result += "\n";
+        result += &INDENT.repeat(indent);
+        result += close;
+        result
+    }
+}
+
+/// Renders a single argument of a call
+fn argument(key: Option<&str>, value: &str) -> String {
+    match key {
+        Some(key) => format!("{}={}", key, value),
+        None => value.to_string(),
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.flat())
+    }
+}
+
+/// `!expr` is `not expr`
+impl Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Self {
+        Expr::Not(Box::new(self))
+    }
+}
+
+impl From<Call> for Expr {
+    fn from(call: Call) -> Self {
+        Expr::Call(Box::new(call))
+    }
+}
+
+impl Call {
+    /// Starts a call to a function.
+    /// The function is written as is, so it should be a name like `steps.Git`.
+    pub fn new<E: Into<Expr>>(func: E) -> Self {
+        Self {
+            func: func.into(),
+            args: vec![],
+            kwargs: vec![],
+        }
+    }
+
+    /// Adds a positional argument
+    pub fn arg<E: Into<Expr>>(mut self, value: E) -> Self {
+        self.args.push(value.into());
+        self
+    }
+
+    /// Adds a keyword argument
+    pub fn kwarg<E: Into<Expr>>(mut self, key: &str, value: E) -> Self {
+        self.kwargs.push((key.to_string(), value.into()));
+        self
+    }
+
+    /// Adds several keyword arguments at once
+    pub fn kwargs<I: IntoIterator<Item = (String, Expr)>>(mut self, kwargs: I) -> Self {
+        self.kwargs.extend(kwargs);
+        self
+    }
+
+    /// Retrieves the keyword argument named `key`, if it was given
+    pub fn get_kwarg(&self, key: &str) -> Option<&Expr> {
+        self.kwargs.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// The positional arguments followed by the keyword arguments
+    fn arguments(&self) -> Vec<(Option<&str>, &Expr)> {
+        self.args
+            .iter()
+            .map(|value| (None, value))
+            .chain(self.kwargs.iter().map(|(k, v)| (Some(k.as_str()), v)))
+            .collect()
+    }
+}
+
+/// Lets a bare name be used wherever an expression is expected,
+/// for example `Call::new("steps.Git")`.
+impl From<&str> for Expr {
+    fn from(name: &str) -> Self {
+        Expr::name(name)
+    }
+}
+
+impl Stmt {
+    /// `target = value`
+    pub fn assign<T: Into<Expr>, V: Into<Expr>>(target: T, value: V) -> Self {
+        Stmt::Assign(target.into(), value.into())
+    }
+
+    /// An expression on its own line
+    pub fn expr<E: Into<Expr>>(value: E) -> Self {
+        Stmt::Expr(value.into())
+    }
+
+    /// A function definition without decorators
+    pub fn def(name: &str, params: &[&str], body: Vec<Stmt>) -> Self {
+        Stmt::Def {
+            decorators: vec![],
+            name: name.to_string(),
+            params: params.iter().map(|p| p.to_string()).collect(),
+            body,
+        }
+    }
+
+    /// `if test:` without an `else:`
+    pub fn if_<E: Into<Expr>>(test: E, body: Vec<Stmt>) -> Self {
+        Stmt::If {
+            test: test.into(),
+            body,
+            orelse: vec![],
+        }
+    }
+
+    /// `for target in iter:`
+    pub fn for_<E: Into<Expr>>(target: &str, iter: E, body: Vec<Stmt>) -> Self {
+        Stmt::For {
+            target: target.to_string(),
+            iter: iter.into(),
+            body,
+        }
+    }
+
+    /// `return value`
+    pub fn ret<E: Into<Expr>>(value: E) -> Self {
+        Stmt::Return(Some(value.into()))
+    }
+
+    /// `try: body` followed by `except Exception as e: handler`
+    pub fn try_except(body: Vec<Stmt>, handler: Vec<Stmt>) -> Self {
+        Stmt::Try {
+            body,
+            handlers: vec![Except {
+                exception: Some(Expr::name("Exception")),
+                name: Some(String::from("e")),
+                body: handler,
+            }],
+        }
+    }
+
+    /// A comment
+    pub fn comment<S: ToString>(text: S) -> Self {
+        Stmt::Comment(text.to_string())
+    }
+
+    /// A line of Python code that is written as is
+    pub fn raw<S: ToString>(code: S) -> Self {
+        Stmt::Raw(code.to_string())
+    }
+
+    /// Writes the statement into `out`, indented by `indent` levels
+    fn render(&self, indent: usize, out: &mut String) {
+        let prefix = INDENT.repeat(indent);
+        let column = prefix.len();
+        match self {
+            Stmt::Expr(value) => line(out, &prefix, &value.render(indent, column)),
+            Stmt::Assign(target, value) => {
+                let target = target.flat();
+                let value = value.render(indent, column + target.len() + 3);
+                line(out, &prefix, &format!("{} = {}", target, value))
+            }
+            Stmt::Def {
+                decorators,
+                name,
+                params,
+                body,
+            } => {
+                for decorator in decorators {
+                    line(out, &prefix, &format!("@{}", decorator.flat()));
+                }
+                line(
+                    out,
+                    &prefix,
+                    &format!("def {}({}):", name, params.join(", ")),
+                );
+                block(body, indent + 1, out);
+            }
+            Stmt::If { test, body, orelse } => {
+                line(out, &prefix, &format!("if {}:", test.flat()));
+                block(body, indent + 1, out);
+                if !orelse.is_empty() {
+                    line(out, &prefix, "else:");
+                    block(orelse, indent + 1, out);
+                }
+            }
+            Stmt::For { target, iter, body } => {
+                line(out, &prefix, &format!("for {} in {}:", target, iter.flat()));
+                block(body, indent + 1, out);
+            }
+            Stmt::Try { body, handlers } => {
+                line(out, &prefix, "try:");
+                block(body, indent + 1, out);
+                for handler in handlers {
+                    let clause = match (&handler.exception, &handler.name) {
+                        (Some(exception), Some(name)) => {
+                            format!("except {} as {}:", exception.flat(), name)
+                        }
+                        (Some(exception), None) => format!("except {}:", exception.flat()),
+                        (None, _) => String::from("except:"),
+                    };
+                    line(out, &prefix, &clause);
+                    block(&handler.body, indent + 1, out);
+                }
+            }
+            Stmt::Return(Some(value)) => line(
+                out,
+                &prefix,
+                &format!("return {}", value.render(indent, column + 7)),
+            ),
+            Stmt::Return(None) => line(out, &prefix, "return"),
+            Stmt::Comment(text) => {
+                for comment in text.lines() {
+                    line(out, &prefix, format!("# {}", comment).trim_end());
+                }
+            }
+            Stmt::Raw(code) => line(out, &prefix, code),
+            Stmt::Blank => out.push('\n'),
+        }
+    }
+}
+
+/// Writes a single line of code
+fn line(out: &mut String, prefix: &str, code: &str) {
+    out.push_str(prefix);
+    out.push_str(code);
+    out.push('\n');
+}
+
+/// Writes the body of a compound statement.
+/// Python doesn't allow empty blocks, so an empty body becomes `pass`.
+fn block(body: &[Stmt], indent: usize, out: &mut String) {
+    if body.is_empty() {
+        line(out, &INDENT.repeat(indent), "pass");
+    }
+    for stmt in body {
+        stmt.render(indent, out);
+    }
+}
+
+/// Renders a list of statements as the source code of a Python module
+pub fn render(stmts: &[Stmt]) -> String {
+    let mut out = String::new();
+    for stmt in stmts {
+        stmt.render(0, &mut out);
+    }
+    out
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", render(std::slice::from_ref(self)))
+    }
+}
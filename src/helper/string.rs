@@ -16,3 +16,80 @@ pub fn unquote(s: &str) -> String {
 
     String::from(&s[1..s.len() - 1])
 }
+
+/// Splits `s` into words the way a POSIX shell would.
+///
+/// Words are separated by whitespace, single quotes preserve everything
+/// inside of them literally, double quotes preserve everything but
+/// backslash escapes of `"`, `\`, `$` and `` ` ``, and a backslash outside
+/// of quotes escapes the character after it.
+///
+/// This returns an error if a quote is never closed, or if the string ends with a backslash.
+pub fn shell_words(s: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    // Whether or not we are in the middle of a word.
+    // This lets `''` produce an empty word.
+    let mut in_word = false;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unmatched single quote in `{}`", s)),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if "\"\\$`".contains(c) => word.push(c),
+                            // A newline after a backslash is a line continuation
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(format!("unmatched double quote in `{}`", s)),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unmatched double quote in `{}`", s)),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some(c) => {
+                    in_word = true;
+                    word.push(c);
+                }
+                None => return Err(format!("trailing backslash in `{}`", s)),
+            },
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(word.clone());
+                    word.clear();
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    Ok(words)
+}
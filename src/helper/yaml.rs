@@ -1,27 +1,40 @@
 use rusty_yaml::Yaml;
+use yaml_rust::{Yaml as RawYaml, YamlLoader};
 
 /// This function unwraps a Yaml object,
 /// takes its first value, and converts it into a string,
 /// and trims quotation marks.
 pub fn unwrap<S: ToString>(yaml: &Yaml, section: S) -> String {
-    let result = yaml
-        .get_section(section.to_string())
-        .unwrap()
-        .nth(0)
-        .to_string();
+    scalar(&yaml.get_section(section.to_string()).unwrap().nth(0))
+}
+
+/// This function converts a Yaml object holding a single value into a string.
+///
+/// A Yaml object stores its contents as YAML text, so a quoted value keeps
+/// its quotes and escape sequences. This parses the text to get the actual value,
+/// so `"echo \"hi\""` becomes `echo "hi"`.
+pub fn scalar(yaml: &Yaml) -> String {
+    let result = yaml.to_string();
+
+    match YamlLoader::load_from_str(&result) {
+        Ok(docs) => match docs.first() {
+            Some(RawYaml::String(s)) => s.clone(),
+            Some(RawYaml::Real(s)) => s.clone(),
+            Some(RawYaml::Integer(i)) => i.to_string(),
+            Some(RawYaml::Boolean(b)) => b.to_string(),
+            _ => trim_quotes(&result),
+        },
+        Err(_) => trim_quotes(&result),
+    }
+}
 
-    let first = match result.chars().nth(0) {
-        Some(v) => v,
-        None => ' ',
-    };
-    let last = match result.chars().nth(result.len() - 1) {
-        Some(v) => v,
-        None => ' ',
-    };
-    if first == last {
+/// If the first and last character are the same, and are
+/// both forms of quotes, trim the outer most ones.
+fn trim_quotes(result: &str) -> String {
+    let first = result.chars().next().unwrap_or(' ');
+    let last = result.chars().last().unwrap_or(' ');
+    if result.len() >= 2 && first == last {
         match first {
-            // If the first and last character are the same, and are
-            // both forms of quotes, trim the outer most ones.
             '\'' | '"' => result[1..result.len() - 1].to_string(),
             _ => result.to_string(),
         }
@@ -1,13 +1,29 @@
 /// This crate can also be used as a library for other tools to generate buildbot projects
 #[macro_use]
 pub mod helper;
-pub use helper::{input, unmatched_quotes, unquote, unwrap, yes_or_no, Cmd, File};
+pub use helper::{
+    input, scalar, shell_words, unmatched_quotes, unquote, unwrap, yes_or_no, Cmd, CmdOutput, File,
+    PyRegex, ToPython,
+};
 
 pub mod buildbot;
 pub use buildbot::{
-    Builder, MailNotifier, MasterConfig, MergeRequestHandler, Scheduler, Step, Worker,
-    AUTH_TOKEN_PATH,
+    check, explain, generate, generate_password, plan, write_secrets, Builder, BuilderBuilder,
+    Change, CheckIssue, CloneOptions, ConfigError, Explanation, FilePlan, FileStatus,
+    GeneratedProject, MailNotifier, MasterConfig, MasterConfigBuilder, Matrix, MergeRequestHandler,
+    NameChanges, Plan, Reason, RunReport, Runner, Scheduler, SchedulerBuilder,
+    SchedulerExplanation, State, Step, StepReport, StepStatus, TokenSource, Tokens,
+    VersionControlSystem, Worker, WorkerBuilder, WorkerOptions, AUTH_TOKEN_PATH,
+    DEFAULT_WORKER_PORT, INSTALL_MAKEFILE, INSTALL_MAKEFILE_PATH, INSTALL_SCRIPT,
+    INSTALL_SCRIPT_PATH, MASTER_CFG_PATH, MATRIX_REFERENCE, POLL_TOKEN_SECRET, SECRETS_DIR_PATH,
+    SMTP_PASSWORD_SECRET, STATE_PATH, STATUS_TOKEN_SECRET,
 };
 
+/// The configuration files, read into typed structs with serde.
+/// The master and mail files can be written in YAML, TOML or JSON,
+/// and the format is picked by the extension of the file.
+pub mod config;
+pub use config::{Diagnostic, Format, MailFile, MasterFile, Source};
+
 pub mod buildsystem;
 pub use buildsystem::*;
@@ -2,7 +2,8 @@ extern crate rusty_yaml;
 use rusty_yaml::Yaml;
 
 extern crate rusty_ci;
-use rusty_ci::Builder;
+use rusty_ci::{Builder, ConfigError, VersionControlSystem};
+use std::convert::TryFrom;
 
 #[test]
 fn builder_from_yaml() {
@@ -21,21 +22,186 @@ fn builder_from_yaml() {
     .get_section("xasm-build")
     .unwrap();
 
-    let builder = Builder::from(yaml);
+    let builder = Builder::try_from(yaml).unwrap();
     let output = builder.to_string();
 
     assert_eq!(
         output,
-        "
-temp_factory = util.BuildFactory()
-temp_factory.addStep(steps.Git(repourl=\"https://github.com/adam-mcdaniel/xasm\", mode=\"full\", branch=\"master\", method=\"clobber\", shallow=False, submodules=True))
-temp_factory.addStep(steps.GitLab(repourl=\"https://github.com/adam-mcdaniel/xasm\", mode=\"full\", branch=\"master\", method=\"clobber\", shallow=False, submodules=True))
-temp_factory.addStep(steps.ShellCommand(command=[\"python\", \"main.py\"], workdir=\"./build\"))
-temp_factory.addStep(steps.ShellCommand(command=[\"echo\", \"XASM\", \"build\", \"done!\"], workdir=\"./build\"))
-c['builders'].append(
-    util.BuilderConfig(name=\"xasm-build\",
-    workernames=[\"xasm-worker\"],
-    factory=temp_factory))
-        ".to_string()
+        r#"temp_factory = util.BuildFactory()
+temp_factory.addStep(
+    steps.Git(
+        repourl="https://github.com/adam-mcdaniel/xasm",
+        mode="full",
+        branch=util.Property("branch", default="master"),
+        method="clobber",
+        shallow=False,
+        submodules=True,
+    ),
+)
+temp_factory.addStep(
+    steps.ShellCommand(command=["python", "main.py"], workdir="./build"),
+)
+temp_factory.addStep(
+    steps.ShellCommand(
+        command=["echo", "XASM", "build", "done!"],
+        workdir="./build",
+    ),
+)
+c["builders"].append(
+    util.BuilderConfig(
+        name="xasm-build",
+        workernames=["xasm-worker"],
+        factory=temp_factory,
+    ),
+)
+"#
     )
 }
+
+#[test]
+fn builder_missing_sections() {
+    let yaml = Yaml::from(
+        r#"xasm-build:
+  workers:
+    - xasm-worker
+"#,
+    )
+    .get_section("xasm-build")
+    .unwrap();
+
+    assert_eq!(
+        Builder::try_from(yaml).err(),
+        Some(vec![
+            ConfigError::MissingSection {
+                context: String::from("builder 'xasm-build'"),
+                section: String::from("script"),
+            },
+            ConfigError::MissingSection {
+                context: String::from("builder 'xasm-build'"),
+                section: String::from("repo"),
+            },
+        ])
+    )
+}
+
+#[test]
+fn builder_bad_clone_options() {
+    let yaml = Yaml::from(
+        r#"xasm-build:
+  workers:
+    - xasm-worker
+  script:
+    - python main.py
+  repo: "https://github.com/adam-mcdaniel/xasm"
+  shallow: maybe
+  method: copy
+  depth: 0
+"#,
+    )
+    .get_section("xasm-build")
+    .unwrap();
+
+    assert_eq!(
+        Builder::try_from(yaml).err(),
+        Some(vec![
+            ConfigError::BadValue {
+                context: String::from("builder 'xasm-build'"),
+                section: String::from("shallow"),
+                value: String::from("maybe"),
+                reason: String::from("expected `true` or `false`"),
+            },
+            ConfigError::BadValue {
+                context: String::from("builder 'xasm-build'"),
+                section: String::from("depth"),
+                value: String::from("0"),
+                reason: String::from("the depth must be a positive number of commits"),
+            },
+        ])
+    )
+}
+
+#[test]
+fn builder_with_vcs() {
+    let yaml = Yaml::from(
+        r#"xasm-build:
+  workers:
+    - xasm-worker
+  script:
+    - python main.py
+  repo: "https://gitlab.com/adam-mcdaniel/xasm"
+  vcs: gitlab
+"#,
+    )
+    .get_section("xasm-build")
+    .unwrap();
+
+    let mut builder = Builder::try_from(yaml).unwrap();
+    // The builder's own `vcs` section wins over the master's default
+    builder.set_default_vcs(VersionControlSystem::GitHub);
+
+    assert_eq!(
+        builder.get_clone_step().to_string(),
+        "steps.GitLab(repourl=\"https://gitlab.com/adam-mcdaniel/xasm\", mode=\"full\", branch=util.Property(\"branch\", default=\"master\"), method=\"clobber\", shallow=False, submodules=True)"
+    )
+}
+
+#[test]
+fn builder_with_shell() {
+    let yaml = Yaml::from(
+        r#"xasm-build:
+  workers:
+    - xasm-worker
+  script:
+    - cd "src dir"
+    - cargo test 2>&1 | tee log
+  repo: "https://github.com/adam-mcdaniel/xasm"
+  shell: true
+"#,
+    )
+    .get_section("xasm-build")
+    .unwrap();
+
+    let builder = Builder::try_from(yaml).unwrap();
+
+    assert_eq!(
+        builder.get_steps()[1].to_string(),
+        "steps.ShellCommand(command=[\"/bin/sh\", \"-c\", \"cargo test 2>&1 | tee log\"], workdir=\"./build/src dir\")"
+    )
+}
+
+#[test]
+fn builder_unknown_sections() {
+    let yaml = Yaml::from(
+        r#"xasm-build:
+  workers:
+    - xasm-worker
+  scrpt:
+    - python main.py
+  repo: "https://github.com/adam-mcdaniel/xasm"
+  colour: blue
+"#,
+    )
+    .get_section("xasm-build")
+    .unwrap();
+
+    let errors = match Builder::try_from(yaml) {
+        Ok(_) => panic!("the builder has unknown sections"),
+        Err(errors) => errors,
+    };
+    // The misspelled `script` isn't also reported missing
+    assert_eq!(
+        errors,
+        vec![
+            ConfigError::UnknownSection {
+                context: String::from("builder 'xasm-build'"),
+                section: String::from("scrpt"),
+                suggestion: Some(String::from("script")),
+            },
+            ConfigError::UnknownSection {
+                context: String::from("builder 'xasm-build'"),
+                section: String::from("colour"),
+                suggestion: None,
+            },
+        ]
+    );
+}
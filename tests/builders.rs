@@ -0,0 +1,234 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{Builder, ConfigError, MasterConfig, Scheduler, Worker, DEFAULT_WORKER_PORT};
+use std::convert::TryFrom;
+
+fn missing(context: &str, section: &str) -> ConfigError {
+    ConfigError::MissingSection {
+        context: context.to_string(),
+        section: section.to_string(),
+    }
+}
+
+#[test]
+fn builders_match_yaml() {
+    let yaml = Yaml::from(
+        r#"master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  poll-interval: 120
+
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist:
+    - adam-mcdaniel
+
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+
+schedulers:
+  ci-change:
+    builders:
+      - rusty-ci-test
+    branch: ".*"
+    triggers:
+      - '.*\.rs'
+    password: "ok to test"
+
+builders:
+  rusty-ci-test:
+    workers:
+      - test-worker
+    script:
+      - cd rusty-ci
+      - cargo test
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+"#,
+    );
+    let mut from_yaml = MasterConfig::try_from(yaml).unwrap();
+
+    let mut from_rust = MasterConfig::builder()
+        .title("Rusty-CI")
+        .title_url("https://github.com/adam-mcdaniel/rusty-ci")
+        .repo("https://github.com/adam-mcdaniel/rusty-ci")
+        .webserver_ip("localhost")
+        .webserver_port(8010)
+        .poll_interval(120)
+        .worker(
+            Worker::builder("test-worker")
+                .master_ip("localhost")
+                .working_dir("test-worker")
+                .build()
+                .unwrap(),
+        )
+        .scheduler(
+            Scheduler::builder("ci-change")
+                .builder("rusty-ci-test")
+                .branch(".*")
+                .trigger(r".*\.rs")
+                .password("ok to test")
+                .build()
+                .unwrap(),
+        )
+        .builder(
+            Builder::builder("rusty-ci-test")
+                .worker("test-worker")
+                .script("cd rusty-ci")
+                .script("cargo test")
+                .repo("https://github.com/adam-mcdaniel/rusty-ci")
+                .build()
+                .unwrap(),
+        )
+        .build()
+        .unwrap();
+
+    // The workers generate their own passwords
+    let mut state = rusty_ci::State::default();
+    from_yaml.sync_passwords(&mut state);
+    from_rust.sync_passwords(&mut state);
+    assert_eq!(from_rust.to_string(), from_yaml.to_string());
+}
+
+#[test]
+fn master_config_reports_every_mistake() {
+    let worker = || {
+        Worker::builder("test-worker")
+            .master_ip("localhost")
+            .working_dir("test-worker")
+            .build()
+            .unwrap()
+    };
+
+    let errors = match MasterConfig::builder()
+        .title("Rusty-CI")
+        .worker(worker())
+        .worker(worker())
+        .build()
+    {
+        Ok(_) => panic!("the master config should be missing sections"),
+        Err(errors) => errors,
+    };
+
+    assert_eq!(
+        errors,
+        vec![
+            missing("master", "title-url"),
+            missing("master", "repo"),
+            missing("master", "webserver-ip"),
+            missing("master", "webserver-port"),
+            missing("master", "poll-interval"),
+            ConfigError::DuplicateName {
+                kind: String::from("worker"),
+                name: String::from("test-worker"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn master_config_sets_worker_port() {
+    let master = MasterConfig::builder()
+        .title("Rusty-CI")
+        .title_url("https://github.com/adam-mcdaniel/rusty-ci")
+        .repo("https://github.com/adam-mcdaniel/rusty-ci")
+        .webserver_ip("localhost")
+        .webserver_port(8010)
+        .poll_interval(120)
+        .worker_port(9999)
+        .worker(
+            Worker::builder("test-worker")
+                .master_ip("localhost")
+                .working_dir("test-worker")
+                .build()
+                .unwrap(),
+        )
+        .build()
+        .unwrap();
+
+    assert_eq!(master.get_workers()[0].get_master_port(), 9999);
+}
+
+#[test]
+fn worker_builder_errors() {
+    let errors = match Worker::builder("")
+        .master_ip("localhost")
+        .umask(0o1777)
+        .allow_shutdown("reboot")
+        .build()
+    {
+        Ok(_) => panic!("the worker should have errors"),
+        Err(errors) => errors,
+    };
+
+    assert_eq!(errors.len(), 4);
+    assert_eq!(errors[1], missing("worker ''", "working-dir"));
+
+    let worker = Worker::builder("test-worker")
+        .master_ip("localhost")
+        .working_dir("test-worker")
+        .password("hunter2")
+        .keepalive(60)
+        .build()
+        .unwrap();
+    assert_eq!(worker.get_password(), "hunter2");
+    assert_eq!(worker.get_options().keepalive, 60);
+    assert_eq!(worker.get_master_port(), DEFAULT_WORKER_PORT);
+}
+
+#[test]
+fn builder_builder_errors() {
+    let errors = match Builder::builder("cargo-test")
+        .depth(0)
+        .method("teleport")
+        .script(r#"echo "unclosed"#)
+        .build()
+    {
+        Ok(_) => panic!("the builder should have errors"),
+        Err(errors) => errors,
+    };
+
+    assert_eq!(errors.len(), 5);
+    assert_eq!(errors[0], missing("builder 'cargo-test'", "workers"));
+    assert_eq!(errors[1], missing("builder 'cargo-test'", "repo"));
+}
+
+#[test]
+fn scheduler_builder_errors() {
+    let errors = match Scheduler::builder("ci-change").build() {
+        Ok(_) => panic!("the scheduler should have errors"),
+        Err(errors) => errors,
+    };
+    assert_eq!(
+        errors,
+        vec![
+            missing("scheduler 'ci-change'", "builders"),
+            missing("scheduler 'ci-change'", "branch"),
+            missing("scheduler 'ci-change'", "password"),
+            missing("scheduler 'ci-change'", "triggers"),
+        ]
+    );
+
+    // A dependent scheduler only runs after its upstream
+    assert!(Scheduler::builder("deploy")
+        .depends("ci-change")
+        .branch("master")
+        .builder("deploy")
+        .build()
+        .is_err());
+
+    let scheduler = Scheduler::builder("deploy")
+        .depends("ci-change")
+        .builder("deploy")
+        .build()
+        .unwrap();
+    assert_eq!(scheduler.get_depends(), Some(String::from("ci-change")));
+}
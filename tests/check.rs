@@ -0,0 +1,188 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{check, CheckIssue, ConfigError, MasterConfig, MasterFile};
+use std::convert::TryFrom;
+
+mod common;
+use common::TempDir;
+
+fn master(builders: &str, schedulers: &str) -> MasterConfig {
+    let yaml = Yaml::from(format!(
+        r#"
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist:
+    - adam-mcdaniel
+
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+  idle-worker:
+    master-ip: localhost
+    working-dir: 'idle-worker'
+
+builders:
+{}
+
+schedulers:
+{}
+"#,
+        builders, schedulers
+    ));
+
+    match MasterConfig::try_from(yaml) {
+        Ok(master) => master,
+        Err(e) => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn check_cross_references() {
+    let master = master(
+        r#"
+  cargo-test:
+    script:
+      - cargo test
+    workers:
+      - test-worker
+      - missing-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+"#,
+        r#"
+  source-change:
+    builders:
+      - cargo-test
+      - cargo-tset
+    branch: "(master"
+    triggers:
+      - ".*\\.rs"
+    password: "ok to test"
+  first:
+    builders:
+      - cargo-test
+    depends: "second"
+  second:
+    builders:
+      - cargo-test
+    depends: "first"
+  orphan:
+    builders:
+      - cargo-test
+    depends: "nowhere"
+"#,
+    );
+
+    let issues = check(&master);
+    let describe = |issues: Vec<CheckIssue>| {
+        issues
+            .iter()
+            .map(|i| format!("{} {}", i.is_error(), i.path))
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(
+        describe(issues),
+        vec![
+            "true builders.cargo-test.workers[1]",
+            "true schedulers.source-change.builders[1]",
+            "true schedulers.source-change.branch",
+            "true schedulers.orphan.depends",
+            "false workers.idle-worker",
+            "true schedulers.second.depends",
+        ]
+    );
+}
+
+#[test]
+fn python_regex_features() {
+    // Buildbot compiles these with python's `re`, which supports look-ahead and backreferences
+    let master = master(
+        r#"
+  cargo-test:
+    script:
+      - cargo test
+    workers:
+      - test-worker
+      - idle-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+"#,
+        r#"
+  not-wip:
+    builders:
+      - cargo-test
+    branch: "^(?!wip/).*"
+    triggers:
+      - '(?P<dir>\w+)/(?P=dir)\.rs'
+    password: "ok to test"
+"#,
+    );
+
+    assert!(check(&master).iter().all(|issue| !issue.is_error()));
+}
+
+#[test]
+fn check_without_token() {
+    let dir = TempDir::new("check-token");
+    let path = dir.write(&[(
+        "master.yaml",
+        r#"
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+merge-request-handler:
+  version-control-system: github
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist: [adam-mcdaniel]
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+builders:
+  cargo-test:
+    script: [cargo test]
+    workers: [test-worker]
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+schedulers:
+  ci-change:
+    builders: [cargo-test, missing-builder]
+    branch: ".*"
+    triggers: [".*"]
+    password: "ok to test"
+"#,
+    )]);
+    let file = MasterFile::load(path).unwrap();
+
+    // There's no `auth.token`, so the whole config can't be built...
+    match MasterConfig::try_from(file.clone()) {
+        Err(errors) => assert!(errors
+            .iter()
+            .any(|e| matches!(e, ConfigError::MissingAuthToken { .. }))),
+        Ok(_) => panic!("the config shouldn't build without a token"),
+    }
+
+    // ...but it can still be checked
+    let master = file.to_config_without_tokens().unwrap();
+    assert!(master.get_secrets().is_empty());
+    let issues = check(&master);
+    assert!(issues
+        .iter()
+        .any(|issue| issue.is_error() && issue.to_string().contains("missing-builder")));
+}
@@ -1,15 +1,84 @@
 extern crate rusty_ci;
 use rusty_ci::Cmd;
+use std::time::Duration;
 
 #[test]
 fn cmd_test() {
     let cmd = Cmd::new("rusty-ci").arg("build").arg("--help");
 
+    assert_eq!(cmd.program, String::from("rusty-ci"));
     assert_eq!(
-        cmd,
-        Cmd {
-            program: String::from("rusty-ci"),
-            args: vec![String::from("build"), String::from("--help")]
-        }
-    )
+        cmd.args,
+        vec![String::from("build"), String::from("--help")]
+    );
+    assert_eq!(cmd.to_string(), "rusty-ci build --help");
+}
+
+#[test]
+fn display_quotes_args() {
+    let cmd = Cmd::new("echo").arg("hello world!").arg("it's").arg("");
+    assert_eq!(cmd.to_string(), r#"echo 'hello world!' 'it'\''s' ''"#);
+}
+
+#[test]
+fn captures_output() {
+    let output = Cmd::new("sh")
+        .arg("-c")
+        .arg("echo out; echo err >&2")
+        .run()
+        .unwrap();
+
+    assert!(output.success());
+    assert_eq!(output.stdout, "out\n");
+    assert_eq!(output.stderr, "err\n");
+}
+
+#[test]
+fn env_and_cwd() {
+    let dir = std::env::temp_dir();
+    let output = Cmd::new("sh")
+        .arg("-c")
+        .arg("echo $GREETING; pwd")
+        .env("GREETING", "hi")
+        .cwd(dir.display())
+        .run()
+        .unwrap();
+
+    let expected = format!("hi\n{}\n", dir.canonicalize().unwrap().display());
+    assert_eq!(output.stdout, expected);
+}
+
+#[test]
+fn failure_has_command_and_stderr() {
+    let cmd = Cmd::new("sh").arg("-c").arg("echo oh no >&2; exit 3");
+
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(3));
+
+    let error = cmd.run().unwrap_err();
+    assert!(error.contains("`sh -c 'echo oh no >&2; exit 3'`"));
+    assert!(error.contains("exit status 3"));
+    assert!(error.contains("oh no"));
+}
+
+#[test]
+fn missing_program() {
+    let error = Cmd::new("rusty-ci-no-such-program").run().unwrap_err();
+    assert!(error.contains("Could not run `rusty-ci-no-such-program`"));
+}
+
+#[test]
+fn timeout() {
+    let error = Cmd::new("sleep")
+        .arg("10")
+        .timeout(Duration::from_millis(200))
+        .run()
+        .unwrap_err();
+    assert!(error.contains("`sleep 10` was killed"));
+
+    let output = Cmd::new("true")
+        .timeout(Duration::from_secs(10))
+        .run()
+        .unwrap();
+    assert!(output.success());
 }
@@ -0,0 +1,53 @@
+// Not every test file uses every helper
+#![allow(dead_code)]
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A fresh temporary directory for a test, which is removed when it's dropped.
+/// It derefs to its path, so `dir.join("file")` works like it would on a `PathBuf`.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates an empty directory. `name` should be unique among the tests,
+    /// like `plan-new` for the test `new_project` in `tests/plan.rs`.
+    pub fn new(name: &str) -> Self {
+        let mut path = std::env::temp_dir();
+        path.push(format!("rusty-ci-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    /// Writes each of `files`, creating the directories they're in,
+    /// and returns the path of the first one
+    pub fn write(&self, files: &[(&str, &str)]) -> PathBuf {
+        for (name, contents) in files {
+            let path = self.0.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        self.0.join(files[0].0)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
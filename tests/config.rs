@@ -0,0 +1,523 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::config::Sections;
+use rusty_ci::{ConfigError, Format, MailFile, MailNotifier, MasterConfig, MasterFile, State};
+use std::convert::TryFrom;
+
+const MASTER_YAML: &str = r#"
+requires: 0.9.0
+
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist:
+    - adam-mcdaniel
+
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+    umask: 022
+
+schedulers:
+  ci-change:
+    builders:
+      - rusty-ci-test
+      - rusty-ci-build
+    branch: ".*"
+    triggers:
+      - '.*\.rs'
+    password: "ok to test"
+
+builders:
+  rusty-ci-test:
+    workers:
+      - test-worker
+    script:
+      - cd rusty-ci
+      - cargo test
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+    depth: 1
+  rusty-ci-build:
+    workers:
+      - test-worker
+    script:
+      - cargo build
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+"#;
+
+const MASTER_TOML: &str = r#"
+requires = "0.9.0"
+
+[master]
+title = "Rusty-CI"
+title-url = "https://github.com/adam-mcdaniel/rusty-ci"
+webserver-ip = "localhost"
+webserver-port = 8010
+repo = "https://github.com/adam-mcdaniel/rusty-ci"
+poll-interval = 120
+
+[merge-request-handler]
+version-control-system = "none"
+owner = "adam-mcdaniel"
+repo-name = "rusty-ci"
+whitelist = ["adam-mcdaniel"]
+
+[workers.test-worker]
+master-ip = "localhost"
+working-dir = "test-worker"
+umask = "022"
+
+[schedulers.ci-change]
+builders = ["rusty-ci-test", "rusty-ci-build"]
+branch = ".*"
+triggers = ['.*\.rs']
+password = "ok to test"
+
+[builders.rusty-ci-test]
+workers = ["test-worker"]
+script = ["cd rusty-ci", "cargo test"]
+repo = "https://github.com/adam-mcdaniel/rusty-ci"
+depth = 1
+
+[builders.rusty-ci-build]
+workers = ["test-worker"]
+script = ["cargo build"]
+repo = "https://github.com/adam-mcdaniel/rusty-ci"
+"#;
+
+const MASTER_JSON: &str = r#"{
+  "requires": "0.9.0",
+  "master": {
+    "title": "Rusty-CI",
+    "title-url": "https://github.com/adam-mcdaniel/rusty-ci",
+    "webserver-ip": "localhost",
+    "webserver-port": 8010,
+    "repo": "https://github.com/adam-mcdaniel/rusty-ci",
+    "poll-interval": 120
+  },
+  "merge-request-handler": {
+    "version-control-system": "none",
+    "owner": "adam-mcdaniel",
+    "repo-name": "rusty-ci",
+    "whitelist": ["adam-mcdaniel"]
+  },
+  "workers": {
+    "test-worker": {
+      "master-ip": "localhost",
+      "working-dir": "test-worker",
+      "umask": "022"
+    }
+  },
+  "schedulers": {
+    "ci-change": {
+      "builders": ["rusty-ci-test", "rusty-ci-build"],
+      "branch": ".*",
+      "triggers": [".*\\.rs"],
+      "password": "ok to test"
+    }
+  },
+  "builders": {
+    "rusty-ci-test": {
+      "workers": ["test-worker"],
+      "script": ["cd rusty-ci", "cargo test"],
+      "repo": "https://github.com/adam-mcdaniel/rusty-ci",
+      "depth": 1
+    },
+    "rusty-ci-build": {
+      "workers": ["test-worker"],
+      "script": ["cargo build"],
+      "repo": "https://github.com/adam-mcdaniel/rusty-ci"
+    }
+  }
+}"#;
+
+/// Renders the master config with the passwords in `state`, so the outputs can be compared
+fn render(mut master: MasterConfig, state: &mut State) -> String {
+    master.sync_passwords(state);
+    master.to_string()
+}
+
+#[test]
+fn every_format_matches_yaml() {
+    let mut state = State::default();
+    let expected = render(
+        MasterConfig::try_from(Yaml::from(MASTER_YAML)).unwrap(),
+        &mut state,
+    );
+
+    for (format, contents) in &[
+        (Format::Yaml, MASTER_YAML),
+        (Format::Toml, MASTER_TOML),
+        (Format::Json, MASTER_JSON),
+    ] {
+        let file: MasterFile = format.parse(contents, "master").unwrap();
+        assert_eq!(file.requires, Some(String::from("0.9.0")));
+        assert_eq!(
+            file.builders.as_ref().unwrap().get_names(),
+            vec!["rusty-ci-test", "rusty-ci-build"],
+            "{:?} should keep the builders in order",
+            format
+        );
+
+        let master = MasterConfig::try_from(file).unwrap();
+        assert_eq!(render(master, &mut state), expected, "{:?}", format);
+    }
+}
+
+#[test]
+fn format_from_extension() {
+    assert_eq!(Format::from_path("master.yaml"), Ok(Format::Yaml));
+    assert_eq!(Format::from_path("master.yml"), Ok(Format::Yaml));
+    assert_eq!(Format::from_path("master"), Ok(Format::Yaml));
+    assert_eq!(Format::from_path("ci/master.toml"), Ok(Format::Toml));
+    assert_eq!(Format::from_path("master.json"), Ok(Format::Json));
+    assert!(Format::from_path("master.txt").is_err());
+}
+
+#[test]
+fn missing_sections() {
+    let file: MasterFile = Format::Toml
+        .parse(
+            r#"
+[master]
+title = "Rusty-CI"
+
+[merge-request-handler]
+version-control-system = "none"
+owner = "adam-mcdaniel"
+repo-name = "rusty-ci"
+whitelist = []
+
+[workers.test-worker]
+master-ip = "localhost"
+
+[builders.rusty-ci-test]
+workers = ["test-worker"]
+repo = "https://github.com/adam-mcdaniel/rusty-ci"
+
+[schedulers.ci-change]
+depends = "other"
+builders = ["rusty-ci-test"]
+"#,
+            "master.toml",
+        )
+        .unwrap();
+
+    let errors = match MasterConfig::try_from(file) {
+        Ok(_) => panic!("the master file should be missing sections"),
+        Err(errors) => errors,
+    };
+    let missing = |context: &str, section: &str| ConfigError::MissingSection {
+        context: context.to_string(),
+        section: section.to_string(),
+    };
+    for error in &[
+        missing("builder 'rusty-ci-test'", "script"),
+        missing("worker 'test-worker'", "working-dir"),
+        missing("master", "title-url"),
+        missing("master", "poll-interval"),
+    ] {
+        assert!(
+            errors.contains(error),
+            "{:?} should contain {}",
+            errors,
+            error
+        );
+    }
+}
+
+#[test]
+fn syntax_errors_name_the_file() {
+    let error = Format::Json
+        .parse::<MasterFile>("{ \"master\": ", "master.json")
+        .unwrap_err();
+    match error {
+        ConfigError::File { path, .. } => assert_eq!(path, "master.json"),
+        other => panic!("expected a file error, got {}", other),
+    }
+}
+
+#[test]
+fn mail_file() {
+    let file: MailFile = Format::Toml
+        .parse(
+            r#"
+from-address = "your-email-here@gmail.com"
+lookup = "gmail.com"
+smtp-relay-host = "smtp.gmail.com"
+smtp-port = 587
+smtp-password = "p@$$w0rd"
+
+[extra-recipients]
+all = ["all_tests@gmail.com"]
+failure = ["failure@gmail.com"]
+success = ["success@gmail.com"]
+"#,
+            "mail.toml",
+        )
+        .unwrap();
+
+    let expected = MailNotifier::try_from(Yaml::from(
+        r#"
+extra-recipients:
+  failure:
+    - failure@gmail.com
+  success:
+    - success@gmail.com
+  all:
+    - all_tests@gmail.com
+
+from-address: your-email-here@gmail.com
+lookup: gmail.com
+smtp-relay-host: smtp.gmail.com
+smtp-port: 587
+smtp-password: "p@$$w0rd""#,
+    ))
+    .unwrap();
+
+    let mailer = MailNotifier::try_from(file).unwrap();
+    assert_eq!(mailer.to_string(), expected.to_string());
+
+    assert!(MailNotifier::try_from(MailFile::default()).is_err());
+}
+
+#[test]
+fn sections_keep_their_order() {
+    let sections: Sections<u32> = Format::Json
+        .parse(r#"{ "b": 1, "a": 2, "c": 3 }"#, "sections")
+        .unwrap();
+    assert_eq!(sections.get_names(), vec!["b", "a", "c"]);
+    assert_eq!(sections.get("a"), Some(&2));
+    assert_eq!(sections.len(), 3);
+}
+
+#[test]
+fn yaml_anchors_and_merge_keys() {
+    let file: MasterFile = Format::Yaml
+        .parse(
+            r#"
+builders:
+  rusty-ci-test: &rust
+    workers: [test-worker]
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+    script: [cargo test]
+  rusty-ci-build:
+    <<: *rust
+    script: [cargo build]
+"#,
+            "master.yaml",
+        )
+        .unwrap();
+
+    for builder in file.to_builders().unwrap() {
+        assert_eq!(builder.get_workernames(), vec!["test-worker"]);
+    }
+}
+
+#[test]
+fn yaml_block_scripts() {
+    let file: MasterFile = Format::Yaml
+        .parse(
+            r#"
+builders:
+  lines:
+    workers: test-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+    script: |
+      cd rusty-ci
+
+      cargo build
+      cargo test
+  shell:
+    workers: test-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+    shell: true
+    script:
+      - |
+        for i in 1 2 3; do
+          echo $i
+        done
+"#,
+            "master.yaml",
+        )
+        .unwrap();
+
+    let builders = file.to_builders().unwrap();
+    // The clone step, then `cargo build` and `cargo test`; `cd` isn't a step
+    assert_eq!(builders[0].get_steps().len(), 3);
+    // The whole block runs as one shell step
+    assert_eq!(builders[1].get_steps().len(), 2);
+}
+
+#[test]
+fn yaml_scalars_as_lists_and_text() {
+    let file: MasterFile = Format::Yaml
+        .parse(
+            r#"
+requires: 1.0
+schedulers:
+  ci-change:
+    builders: update-rustup
+    branch: master
+    triggers: ['.*']
+    password: 1234
+"#,
+            "master.yaml",
+        )
+        .unwrap();
+
+    assert_eq!(file.requires, Some(String::from("1.0")));
+    let scheduler = file.schedulers.as_ref().unwrap().get("ci-change").unwrap();
+    assert_eq!(
+        scheduler.builders,
+        Some(vec![String::from("update-rustup")])
+    );
+    assert_eq!(scheduler.password, Some(String::from("1234")));
+}
+
+#[test]
+fn unknown_sections_suggest_the_closest() {
+    let file: MasterFile = Format::Yaml
+        .parse(
+            MASTER_YAML
+                .replace("poll-interval", "poll-intreval")
+                .as_str(),
+            "master.yaml",
+        )
+        .unwrap();
+    // `poll-interval` is misspelled, not missing
+    let errors = match MasterConfig::try_from(file) {
+        Ok(_) => panic!("the master file has a typo"),
+        Err(errors) => errors,
+    };
+    assert_eq!(
+        errors,
+        vec![ConfigError::UnknownSection {
+            context: String::from("master"),
+            section: String::from("poll-intreval"),
+            suggestion: Some(String::from("poll-interval")),
+        }]
+    );
+
+    let file: MasterFile = Format::Toml
+        .parse(
+            r#"
+[schedulers.ci-change]
+builders = ["rusty-ci-test"]
+branch = ".*"
+trigger = ['.*\.rs']
+password = "ok to test"
+
+[builders.rusty-ci-test]
+workers = ["test-worker"]
+script = ["cargo test"]
+repo = "https://github.com/adam-mcdaniel/rusty-ci"
+shalow = true
+"#,
+            "master.toml",
+        )
+        .unwrap();
+    let unknown = |context: &str, section: &str, suggestion: &str| ConfigError::UnknownSection {
+        context: context.to_string(),
+        section: section.to_string(),
+        suggestion: Some(suggestion.to_string()),
+    };
+    assert_eq!(
+        file.to_schedulers().err(),
+        Some(vec![unknown(
+            "scheduler 'ci-change'",
+            "trigger",
+            "triggers"
+        )])
+    );
+    assert_eq!(
+        file.to_builders().err(),
+        Some(vec![unknown(
+            "builder 'rusty-ci-test'",
+            "shalow",
+            "shallow"
+        )])
+    );
+}
+
+#[test]
+fn quoted_numbers() {
+    let mut state = State::default();
+    let yaml = MASTER_YAML.replace("umask: 022", "umask: 022\n    keepalive: 60");
+    let expected = render(
+        MasterConfig::try_from(Yaml::from(yaml.as_str())).unwrap(),
+        &mut state,
+    );
+
+    let quoted = yaml
+        .replace("webserver-port: 8010", "webserver-port: \"8010\"")
+        .replace("poll-interval: 120", "poll-interval: '120'")
+        .replace("keepalive: 60", "keepalive: \"60\"")
+        .replace("depth: 1", "depth: '1'");
+    let file: MasterFile = Format::Yaml.parse(&quoted, "master.yaml").unwrap();
+    assert_eq!(
+        render(MasterConfig::try_from(file).unwrap(), &mut state),
+        expected
+    );
+
+    let file: MasterFile = Format::Yaml
+        .parse(
+            &MASTER_YAML.replace("webserver-port: 8010", "webserver-port: 80100"),
+            "master.yaml",
+        )
+        .unwrap();
+    let errors = match MasterConfig::try_from(file) {
+        Ok(_) => panic!("the port is too big"),
+        Err(errors) => errors,
+    };
+    assert_eq!(
+        errors,
+        vec![ConfigError::BadValue {
+            context: String::from("master"),
+            section: String::from("webserver-port"),
+            value: String::from("80100"),
+            reason: String::from("the port must be a number between 0 and 65535"),
+        }]
+    );
+
+    let mail = |port: &str| {
+        let yaml = format!(
+            r#"
+from-address: your-email-here@gmail.com
+lookup: gmail.com
+smtp-relay-host: smtp.gmail.com
+smtp-port: {}
+smtp-password: "p@$$w0rd"
+extra-recipients:
+  all: [all_tests@gmail.com]
+  failure: [failure@gmail.com]
+  success: [success@gmail.com]
+"#,
+            port
+        );
+        let file: MailFile = Format::Yaml.parse(&yaml, "mail.yaml").unwrap();
+        MailNotifier::try_from(file).map(|mailer| mailer.to_string())
+    };
+    assert_eq!(mail("\"587\"").unwrap(), mail("587").unwrap());
+    assert_eq!(
+        mail("smtp").unwrap_err(),
+        vec![ConfigError::BadValue {
+            context: String::from("the mail notifier"),
+            section: String::from("smtp-port"),
+            value: String::from("smtp"),
+            reason: String::from("the port must be a number between 0 and 65535"),
+        }]
+    );
+}
@@ -0,0 +1,184 @@
+extern crate rusty_ci;
+use rusty_ci::config::Span;
+use rusty_ci::{ConfigError, Diagnostic, Format, MasterConfig, MasterFile, Source};
+use std::convert::TryFrom;
+
+const MASTER_YAML: &str = r#"requires: 0.9.0
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+merge-request-handler:
+  version-control-system: svn
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist: [adam-mcdaniel]
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+schedulers:
+  ci-change:
+    builders: [cargo-test]
+    branch: ".*"
+    triggers: ['.*']
+builders:
+  cargo-test: &rust
+    workers: test-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+    depth: 0
+    script: |
+      cargo build
+      cargo test "unclosed
+  cargo-doc:
+    <<: *rust
+    depth: 1
+    script: [cargo doc]
+    method: scrub
+"#;
+
+/// Converts the master file in `source`, and returns where each error was found
+fn diagnose(source: &Source) -> Vec<Diagnostic> {
+    let file: MasterFile = source.parse().unwrap();
+    match MasterConfig::try_from(file) {
+        Ok(_) => panic!("the master file should have mistakes"),
+        Err(errors) => errors
+            .into_iter()
+            .map(|e| Diagnostic::locate(e, source))
+            .collect(),
+    }
+}
+
+/// Finds the diagnostic for the error that displays as `message`
+fn find<'a>(diagnostics: &'a [Diagnostic], message: &str) -> &'a Diagnostic {
+    diagnostics
+        .iter()
+        .find(|d| d.get_error().to_string().starts_with(message))
+        .unwrap_or_else(|| panic!("no error starting with {:?} in {:?}", message, diagnostics))
+}
+
+fn line_and_column(diagnostic: &Diagnostic) -> (usize, usize) {
+    (
+        diagnostic.get_line().unwrap(),
+        diagnostic.get_column().unwrap(),
+    )
+}
+
+#[test]
+fn yaml_errors_have_locations() {
+    let source = Source::new("master.yaml", Format::Yaml, MASTER_YAML);
+    let diagnostics = diagnose(&source);
+
+    let vcs = find(&diagnostics, "unknown version control system 'svn'");
+    assert_eq!(line_and_column(vcs), (10, 27));
+    let password = find(&diagnostics, "'password' section not specified");
+    assert_eq!(line_and_column(password), (19, 3));
+    let depth = find(&diagnostics, "invalid value '0' for the 'depth'");
+    assert_eq!(line_and_column(depth), (27, 12));
+    // A line of a block script points at that line
+    let script = find(&diagnostics, "invalid value 'cargo test \"unclosed'");
+    assert_eq!(line_and_column(script), (30, 7));
+    let method = find(&diagnostics, "invalid value 'scrub'");
+    assert_eq!(line_and_column(method), (35, 13));
+
+    for diagnostic in &diagnostics {
+        assert_eq!(diagnostic.get_path(), Some(String::from("master.yaml")));
+    }
+}
+
+#[test]
+fn rendered_like_rustc() {
+    let source = Source::new("master.yaml", Format::Yaml, MASTER_YAML);
+    let diagnostics = diagnose(&source);
+
+    let password = find(&diagnostics, "'password' section not specified");
+    assert_eq!(
+        password.to_string(),
+        "error: 'password' section not specified for scheduler 'ci-change'
+  --> master.yaml:19:3
+   |
+19 |   ci-change:
+   |   ^^^^^^^^^ missing `password`
+   |
+   = help: add a `password` section to scheduler 'ci-change'"
+    );
+
+    // Errors that aren't in the file don't have a snippet
+    let token = Diagnostic::from(ConfigError::MissingAuthToken {
+        path: String::from("auth.token"),
+        reason: String::from("the file is empty"),
+    });
+    assert_eq!(
+        token.to_string(),
+        "error: could not get the authentication token from 'auth.token': the file is empty"
+    );
+}
+
+#[test]
+fn toml_and_json_errors_have_locations() {
+    let toml = Source::new(
+        "master.toml",
+        Format::Toml,
+        r#"[master]
+title = "Rusty-CI"
+
+[workers.test-worker]
+master-ip = "localhost"
+"#,
+    );
+    let file: MasterFile = toml.parse().unwrap();
+    let errors = match file.to_workers() {
+        Ok(_) => panic!("the worker has no working-dir"),
+        Err(errors) => errors,
+    };
+    let diagnostic = Diagnostic::locate(errors[0].clone(), &toml);
+    assert_eq!(line_and_column(&diagnostic), (4, 10));
+
+    // A section of the wrong type points at its value
+    let json = Source::new(
+        "master.json",
+        Format::Json,
+        r#"{
+  "master": {
+    "title": "Rusty-CI",
+    "webserver-port": ["abc"]
+  }
+}"#,
+    );
+    let diagnostic = json.parse::<MasterFile>().unwrap_err();
+    assert_eq!(line_and_column(&diagnostic), (4, 23));
+    assert!(diagnostic
+        .get_error()
+        .to_string()
+        .contains("master.webserver-port"));
+}
+
+#[test]
+fn syntax_errors_have_locations() {
+    let yaml = Source::new("master.yaml", Format::Yaml, "master:\n  - a\n  b: c\n");
+    assert_eq!(yaml.parse::<MasterFile>().unwrap_err().get_line(), Some(3));
+
+    let toml = Source::new("master.toml", Format::Toml, "[master]\ntitle = \n");
+    assert_eq!(toml.parse::<MasterFile>().unwrap_err().get_line(), Some(2));
+
+    let json = Source::new("master.json", Format::Json, "{\n  \"master\": {,}\n}");
+    let diagnostic = json.parse::<MasterFile>().unwrap_err();
+    assert_eq!(line_and_column(&diagnostic), (2, 14));
+}
+
+#[test]
+fn spans_of_sections() {
+    let source = Source::new("master.yaml", Format::Yaml, MASTER_YAML);
+    let span = source
+        .value_span(&["merge-request-handler", "whitelist", "0"])
+        .unwrap();
+    assert_eq!(&MASTER_YAML[span.start..span.end], "adam-mcdaniel");
+    assert_eq!(
+        source.key_span(&["builders", "cargo-test", "vcs"]),
+        source.key_span(&["builders", "cargo-test"])
+    );
+    assert_eq!(source.value_span(&["nothing"]), None::<Span>);
+}
@@ -0,0 +1,132 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{explain, Change, Reason, Scheduler};
+use std::convert::TryFrom;
+
+fn schedulers() -> Vec<Scheduler> {
+    let yaml = Yaml::from(
+        r#"schedulers:
+  ci-change:
+    builders:
+      - test
+    branch: "feature/.*"
+    triggers:
+      - '.*\.rs'
+      - 'Cargo\.toml'
+    password: "ok to test"
+  docs:
+    builders:
+      - docs
+    branch: "master"
+    triggers:
+      - 'docs/.*'
+    password: "ok to test"
+  deploy:
+    depends: release
+    builders:
+      - deploy
+  release:
+    depends: ci-change
+    builders:
+      - test
+      - release
+"#,
+    );
+
+    yaml.get_section("schedulers")
+        .unwrap()
+        .into_iter()
+        .map(|s| Scheduler::try_from(s).unwrap())
+        .collect()
+}
+
+#[test]
+fn triggers_match_whole_file_names() {
+    let change = Change::new(
+        "feature/x",
+        vec!["src/lib.rs", "src/lib.rs.bak", "README.md"],
+    );
+    let explanation = explain(&schedulers(), &change);
+
+    let ci = &explanation.schedulers[0];
+    assert!(ci.triggered);
+    assert_eq!(
+        ci.reason,
+        Reason::Triggered {
+            branch: String::from("feature/.*"),
+            matches: vec![(String::from(r".*\.rs"), String::from("src/lib.rs"))],
+        }
+    );
+}
+
+#[test]
+fn branch_matches_from_the_start() {
+    // Like buildbot's change filter, `master` matches `master-2` but not `old-master`
+    let explanation = explain(&schedulers(), &Change::new("master-2", vec!["docs/a.md"]));
+    assert!(explanation.schedulers[1].triggered);
+
+    let explanation = explain(&schedulers(), &Change::new("old-master", vec!["docs/a.md"]));
+    assert_eq!(
+        explanation.schedulers[1].reason,
+        Reason::BranchMismatch {
+            branch: String::from("master")
+        }
+    );
+    assert!(explanation.get_buildernames().is_empty());
+}
+
+#[test]
+fn depends_chains() {
+    let explanation = explain(&schedulers(), &Change::new("feature/x", vec!["Cargo.toml"]));
+
+    // `deploy` depends on `release`, which depends on `ci-change`
+    assert!(explanation.schedulers[2].triggered);
+    assert!(explanation.schedulers[3].triggered);
+    assert_eq!(
+        explanation.get_buildernames(),
+        vec!["test", "deploy", "release"]
+    );
+
+    let explanation = explain(&schedulers(), &Change::new("feature/x", vec!["README.md"]));
+    assert_eq!(
+        explanation.schedulers[0].reason,
+        Reason::NoTriggerMatched {
+            branch: String::from("feature/.*")
+        }
+    );
+    assert!(!explanation.schedulers[2].triggered);
+    assert!(!explanation.schedulers[3].triggered);
+    assert!(explanation.to_string().ends_with("No builders would run"));
+}
+
+#[test]
+fn unsupported_regex() {
+    let yaml = Yaml::from(
+        r#"not-wip:
+  builders:
+    - lint
+  branch: "(?!wip/)"
+  triggers:
+    - ".*"
+  password: "ok to test"
+"#,
+    )
+    .get_section("not-wip")
+    .unwrap();
+    let mut schedulers = schedulers();
+    schedulers.insert(0, Scheduler::try_from(yaml).unwrap());
+
+    // The other schedulers are still explained
+    let explanation = explain(&schedulers, &Change::new("feature/x", vec!["src/lib.rs"]));
+    assert!(!explanation.schedulers[0].triggered);
+    assert!(matches!(
+        &explanation.schedulers[0].reason,
+        Reason::Unsimulated { reason } if reason.starts_with("the branch `(?!wip/)`: look-around")
+    ));
+    assert!(explanation.schedulers[1].triggered);
+    assert!(explanation
+        .to_string()
+        .contains("  scheduler 'not-wip' may run lint\n    it can't be simulated: the branch"));
+}
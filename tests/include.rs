@@ -0,0 +1,200 @@
+extern crate rusty_ci;
+use rusty_ci::{ConfigError, Diagnostic, Format, MasterConfig, MasterFile};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+mod common;
+use common::TempDir;
+
+const MASTER_YAML: &str = r#"requires: 0.9.0
+include: schedulers.toml
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist: [adam-mcdaniel]
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+builders:
+  include: builders/*.yaml
+  cargo-build:
+    workers: [test-worker]
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+    script: [cargo build]
+"#;
+
+const SCHEDULERS_TOML: &str = r#"
+[schedulers.ci-change]
+builders = ["cargo-build", "cargo-test", "cargo-doc"]
+branch = ".*"
+triggers = [".*"]
+password = "ok to test"
+"#;
+
+const TEST_YAML: &str = r#"cargo-test:
+  workers: [test-worker]
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  script: [cargo test]
+"#;
+
+const DOC_YAML: &str = r#"cargo-doc:
+  workers: [test-worker]
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  script: [cargo doc]
+"#;
+
+fn load_errors(path: PathBuf) -> Vec<Diagnostic> {
+    match MasterFile::load(path) {
+        Ok(_) => panic!("the master file should not load"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn includes_are_merged_in_order() {
+    let dir = TempDir::new("include-merge");
+    let path = dir.write(&[
+        ("master.yaml", MASTER_YAML),
+        ("schedulers.toml", SCHEDULERS_TOML),
+        ("builders/test.yaml", TEST_YAML),
+        ("builders/doc.yaml", DOC_YAML),
+    ]);
+    let file = MasterFile::load(path).unwrap();
+
+    // The globbed files are merged in alphabetical order, after the builders written here
+    let builders = file.builders.clone().unwrap();
+    assert_eq!(
+        builders.get_names(),
+        vec!["cargo-build", "cargo-doc", "cargo-test"]
+    );
+    assert!(builders.get_includes().is_empty());
+    assert_eq!(file.include, None);
+    assert_eq!(
+        file.schedulers.clone().unwrap().get_names(),
+        vec!["ci-change"]
+    );
+    assert_eq!(file.get_sources().len(), 4);
+
+    let master = MasterConfig::try_from(file).unwrap();
+    assert_eq!(master.get_builders().len(), 3);
+}
+
+#[test]
+fn names_defined_in_two_files() {
+    let dir = TempDir::new("include-duplicate");
+    let path = dir.write(&[
+        ("master.yaml", MASTER_YAML),
+        ("schedulers.toml", SCHEDULERS_TOML),
+        ("builders/test.yaml", TEST_YAML),
+        ("builders/doc.yaml", DOC_YAML),
+        (
+            "builders/more.yaml",
+            "cargo-build:\n  script: [cargo build]\n",
+        ),
+    ]);
+    let errors = load_errors(path.clone());
+    assert_eq!(errors.len(), 1);
+
+    let dir = path.parent().unwrap();
+    assert_eq!(
+        errors[0].get_error(),
+        ConfigError::DefinedTwice {
+            kind: String::from("builder"),
+            name: String::from("cargo-build"),
+            first: path.display().to_string(),
+            second: dir.join("builders/more.yaml").display().to_string(),
+        }
+    );
+    assert_eq!(errors[0].get_line(), Some(1));
+    assert_eq!(errors[0].get_column(), Some(1));
+}
+
+#[test]
+fn includes_that_cant_be_read() {
+    let master = MASTER_YAML
+        .replace(
+            "include: schedulers.toml",
+            "include: [missing.yaml, master.yaml]",
+        )
+        .replace("include: builders/*.yaml", "include: nothing/*.yaml");
+    let dir = TempDir::new("include-missing");
+    let path = dir.write(&[("master.yaml", master.as_str())]);
+    let errors = load_errors(path.clone());
+    assert_eq!(errors.len(), 3);
+
+    // Each one points at where the include was written
+    let reasons: Vec<String> = errors
+        .iter()
+        .map(|e| match e.get_error() {
+            ConfigError::File { reason, .. } => reason,
+            error => panic!("unexpected error {}", error),
+        })
+        .collect();
+    assert_eq!(reasons[0], "no files match this glob");
+    assert_eq!(errors[0].get_line(), Some(20));
+    assert!(reasons[1].contains("No such file"));
+    assert_eq!(errors[1].get_line(), Some(2));
+    assert_eq!(errors[1].get_column(), Some(11));
+    assert!(reasons[2].contains("includes itself"));
+    assert_eq!(errors[2].get_column(), Some(25));
+}
+
+#[test]
+fn errors_point_at_the_included_file() {
+    let dir = TempDir::new("include-diagnose");
+    let path = dir.write(&[
+        ("master.yaml", MASTER_YAML),
+        (
+            "schedulers.toml",
+            &SCHEDULERS_TOML.replace("password", "pasword"),
+        ),
+        ("builders/test.yaml", TEST_YAML),
+        ("builders/doc.yaml", DOC_YAML),
+    ]);
+    let file = MasterFile::load(path.clone()).unwrap();
+    let errors = match MasterConfig::try_from(file.clone()) {
+        Ok(_) => panic!("the scheduler is missing its password"),
+        Err(e) => e,
+    };
+    let diagnostics = file.diagnose(errors);
+    assert_eq!(diagnostics.len(), 1);
+
+    let diagnostic = &diagnostics[0];
+    assert_eq!(
+        diagnostic.get_path(),
+        Some(path.with_file_name("schedulers.toml").display().to_string())
+    );
+    assert_eq!(diagnostic.get_line(), Some(6));
+}
+
+#[test]
+fn render_the_merged_file() {
+    let dir = TempDir::new("include-render");
+    let path = dir.write(&[
+        ("master.yaml", MASTER_YAML),
+        ("schedulers.toml", SCHEDULERS_TOML),
+        ("builders/test.yaml", TEST_YAML),
+        ("builders/doc.yaml", DOC_YAML),
+    ]);
+    let file = MasterFile::load(path).unwrap();
+
+    for &format in &[Format::Yaml, Format::Toml, Format::Json] {
+        let text = file.render(format).unwrap();
+        assert!(!text.contains("include"));
+        let rendered: MasterFile = format.parse(&text, "rendered").unwrap();
+        assert_eq!(rendered.render(format).unwrap(), text);
+        assert_eq!(
+            rendered.builders.unwrap().get_names(),
+            vec!["cargo-build", "cargo-doc", "cargo-test"]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+extern crate rusty_ci;
+use rusty_ci::{ConfigError, MailFile, MasterFile};
+
+mod common;
+use common::TempDir;
+
+const MAIL_YAML: &str = r#"extra-recipients:
+  all: ["${RUSTY_CI_TEST_ADMIN}", "dev@example.com"]
+  failure: []
+  success: []
+from-address: "${RUSTY_CI_TEST_UNSET:-ci@example.com}"
+smtp-relay-host: "${RUSTY_CI_TEST_EMPTY:-smtp.example.com}"
+smtp-port: 587
+lookup: "example.com"
+smtp-password: "${file:secrets/smtp}"
+"#;
+
+#[test]
+fn references_are_resolved() {
+    std::env::set_var("RUSTY_CI_TEST_ADMIN", "admin@example.com");
+    std::env::set_var("RUSTY_CI_TEST_EMPTY", "");
+    let dir = TempDir::new("interpolate-resolved");
+    let path = dir.write(&[("mail.yaml", MAIL_YAML), ("secrets/smtp", "p@$$w0rd\n")]);
+    let file = MailFile::load(path).unwrap();
+
+    let recipients = file.extra_recipients.unwrap();
+    assert_eq!(
+        recipients.all,
+        Some(vec![
+            String::from("admin@example.com"),
+            String::from("dev@example.com")
+        ])
+    );
+    assert_eq!(file.from_address, Some(String::from("ci@example.com")));
+    assert_eq!(file.smtp_relay_host, Some(String::from("smtp.example.com")));
+    // The file is read as it is, without resolving it again
+    assert_eq!(file.smtp_password, Some(String::from("p@$$w0rd")));
+}
+
+#[test]
+fn escaped_references_are_kept() {
+    let master = r#"builders:
+  cargo-test:
+    script: ["echo $${HOME}", "echo $$ $HOME"]
+"#;
+    let dir = TempDir::new("interpolate-escaped");
+    let path = dir.write(&[("master.yaml", master)]);
+    let file = MasterFile::load(path).unwrap();
+    let builder = file.builders.unwrap().get("cargo-test").cloned().unwrap();
+    assert_eq!(
+        builder.script,
+        Some(vec![
+            String::from("echo ${HOME}"),
+            String::from("echo $$ $HOME")
+        ])
+    );
+}
+
+#[test]
+fn every_unresolved_reference_is_reported() {
+    let master = r#"include: schedulers.yaml
+master:
+  title: "${RUSTY_CI_TEST_UNSET}"
+builders:
+  cargo-test:
+    script: ["cargo test", "echo ${not a name}"]
+    repo: "${file:missing}"
+"#;
+    let schedulers = r#"schedulers:
+  ci-change:
+    password: "${RUSTY_CI_TEST_UNSET}"
+    branch: "${unclosed"
+"#;
+    let dir = TempDir::new("interpolate-unresolved");
+    let path = dir.write(&[("master.yaml", master), ("schedulers.yaml", schedulers)]);
+    let errors = match MasterFile::load(path.clone()) {
+        Ok(_) => panic!("the references can't be resolved"),
+        Err(e) => e,
+    };
+
+    let found: Vec<(String, String, Option<usize>)> = errors
+        .iter()
+        .map(|e| match e.get_error() {
+            ConfigError::Unresolved {
+                section, reference, ..
+            } => (section, reference, e.get_line()),
+            error => panic!("unexpected error {}", error),
+        })
+        .collect();
+    let expected = vec![
+        ("title", "${RUSTY_CI_TEST_UNSET}", 3),
+        ("script", "${not a name}", 6),
+        ("repo", "${file:missing}", 7),
+        ("branch", "${unclosed", 4),
+        ("password", "${RUSTY_CI_TEST_UNSET}", 3),
+    ];
+    assert_eq!(
+        found,
+        expected
+            .into_iter()
+            .map(|(s, r, l)| (s.to_string(), r.to_string(), Some(l)))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        errors[3].get_path(),
+        Some(path.with_file_name("schedulers.yaml").display().to_string())
+    );
+}
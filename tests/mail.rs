@@ -3,6 +3,7 @@ use rusty_yaml::Yaml;
 
 extern crate rusty_ci;
 use rusty_ci::MailNotifier;
+use std::convert::TryFrom;
 
 #[test]
 fn mailer_from_yaml() {
@@ -23,11 +24,54 @@ smtp-port: 587
 smtp-password: "p@$$w0rd""#,
     );
 
-    let mailer = MailNotifier::from(yaml);
+    let mailer = MailNotifier::try_from(yaml).unwrap();
     let output = mailer.to_string();
 
     assert_eq!(
         output.trim(),
-        "# The mail notifier responsible for all info\nall = reporters.MailNotifier(fromaddr=\"your-email-here@gmail.com\",\n                            sendToInterestedUsers=True,\n                            extraRecipients=[\"all_tests@gmail.com\"],\n                            lookup=\"gmail.com\",\n                            relayhost=\"smtp.gmail.com\", smtpPort=587,\n                            smtpUser=\"your-email-here@gmail.com\", buildSetSummary=True,\n                            # addLogs=True,\n                            mode=\"all\",\n                            smtpPassword=\"p@$$w0rd\")\nc[\'services\'].append(all)\n\n\n# The mail notifier responsible for failures\nfailures = reporters.MailNotifier(fromaddr=\"your-email-here@gmail.com\",\n                            sendToInterestedUsers=True,\n                            extraRecipients=[\"failure@gmail.com\"],\n                            lookup=\"gmail.com\",\n                            relayhost=\"smtp.gmail.com\", smtpPort=587,\n                            smtpUser=\"your-email-here@gmail.com\", buildSetSummary=True,\n                            # addLogs=True,\n                            mode=\"failing\",\n                            smtpPassword=\"p@$$w0rd\")\nc[\'services\'].append(failures)\n\n\n\n# The mail notifier responsible for successes\nsuccesses = reporters.MailNotifier(fromaddr=\"your-email-here@gmail.com\",\n                            sendToInterestedUsers=True,\n                            extraRecipients=[\"success@gmail.com\"],\n                            lookup=\"gmail.com\",\n                            relayhost=\"smtp.gmail.com\", smtpPort=587,\n                            smtpUser=\"your-email-here@gmail.com\", buildSetSummary=True,\n                            # addLogs=True,\n                            mode=\"passing\",\n                            smtpPassword=\"p@$$w0rd\")\nc[\'services\'].append(successes)".trim()
+        r#"# The mail notifier responsible for all info
+all = reporters.MailNotifier(
+    fromaddr="your-email-here@gmail.com",
+    sendToInterestedUsers=True,
+    extraRecipients=["all_tests@gmail.com"],
+    lookup="gmail.com",
+    relayhost="smtp.gmail.com",
+    smtpPort=587,
+    smtpUser="your-email-here@gmail.com",
+    buildSetSummary=True,
+    mode="all",
+    smtpPassword=util.Secret("smtp-password"),
+)
+c["services"].append(all)
+
+# The mail notifier responsible for failures
+failures = reporters.MailNotifier(
+    fromaddr="your-email-here@gmail.com",
+    sendToInterestedUsers=True,
+    extraRecipients=["failure@gmail.com"],
+    lookup="gmail.com",
+    relayhost="smtp.gmail.com",
+    smtpPort=587,
+    smtpUser="your-email-here@gmail.com",
+    buildSetSummary=True,
+    mode="failing",
+    smtpPassword=util.Secret("smtp-password"),
+)
+c["services"].append(failures)
+
+# The mail notifier responsible for successes
+successes = reporters.MailNotifier(
+    fromaddr="your-email-here@gmail.com",
+    sendToInterestedUsers=True,
+    extraRecipients=["success@gmail.com"],
+    lookup="gmail.com",
+    relayhost="smtp.gmail.com",
+    smtpPort=587,
+    smtpUser="your-email-here@gmail.com",
+    buildSetSummary=True,
+    mode="passing",
+    smtpPassword=util.Secret("smtp-password"),
+)
+c["services"].append(successes)"#
     );
 }
@@ -0,0 +1,222 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{explain, Builder, Change, ConfigError, Format, MasterConfig, MasterFile, Matrix};
+use std::convert::TryFrom;
+
+mod common;
+use common::TempDir;
+
+const MATRIX_YAML: &str = r#"cargo-test:
+  workers: ["${matrix.toolchain}-worker"]
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  script:
+    - rustup run ${matrix.toolchain} cargo test --features ${matrix.features}
+  matrix:
+    toolchain: [stable, nightly]
+    features: [default, all]
+    exclude:
+      - toolchain: nightly
+        features: all
+    include:
+      - features: simd
+        toolchain: nightly
+"#;
+
+fn expand(yaml: &str) -> Result<Vec<Builder>, Vec<ConfigError>> {
+    let name = Yaml::from(yaml).get_section_names().unwrap()[0].clone();
+    Builder::expand(Yaml::from(yaml).get_section(name).unwrap())
+}
+
+fn reasons(result: Result<Vec<Builder>, Vec<ConfigError>>) -> Vec<String> {
+    let errors = match result {
+        Ok(_) => panic!("the matrix should have been rejected"),
+        Err(e) => e,
+    };
+    errors
+        .into_iter()
+        .map(|e| match e {
+            ConfigError::BadValue { reason, .. } => reason,
+            e => panic!("unexpected error {:?}", e),
+        })
+        .collect()
+}
+
+#[test]
+fn matrix_from_yaml() {
+    let builders = expand(MATRIX_YAML).unwrap();
+
+    let names: Vec<String> = builders.iter().map(|b| b.get_name()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "cargo-test-stable-default",
+            "cargo-test-stable-all",
+            "cargo-test-nightly-default",
+            "cargo-test-nightly-simd",
+        ]
+    );
+    for builder in &builders {
+        assert_eq!(builder.get_matrix_name(), Some(String::from("cargo-test")));
+    }
+
+    assert_eq!(builders[1].get_workernames(), vec!["stable-worker"]);
+    assert_eq!(builders[3].get_workernames(), vec!["nightly-worker"]);
+    assert!(builders[3]
+        .to_string()
+        .contains(r#"["rustup", "run", "nightly", "cargo", "test", "--features", "simd"]"#));
+}
+
+#[test]
+fn builder_without_matrix_expands_to_itself() {
+    let builders = expand(
+        r#"cargo-test:
+  workers: [test-worker]
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  script: [cargo test]
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(builders.len(), 1);
+    assert_eq!(builders[0].get_name(), "cargo-test");
+    assert_eq!(builders[0].get_matrix_name(), None);
+}
+
+#[test]
+fn matrix_from_builder() {
+    let builders = Builder::builder("build")
+        .worker("${matrix.os}")
+        .repo("https://github.com/adam-mcdaniel/rusty-ci")
+        .script("cargo build --target ${matrix.target}")
+        .matrix(
+            Matrix::default()
+                .axis("os", &["linux", "windows"])
+                .axis("target", &["x86_64"]),
+        )
+        .build_matrix()
+        .unwrap();
+
+    let names: Vec<String> = builders.iter().map(|b| b.get_name()).collect();
+    assert_eq!(names, vec!["build-linux-x86_64", "build-windows-x86_64"]);
+    assert_eq!(builders[1].get_workernames(), vec!["windows"]);
+    assert!(builders[0]
+        .to_string()
+        .contains(r#"["cargo", "build", "--target", "x86_64"]"#));
+}
+
+#[test]
+fn bad_matrices() {
+    let reasons = reasons(expand(
+        &MATRIX_YAML
+            .replace("      - features: simd", "      - feature: simd")
+            .replace("${matrix.features}", "${matrix.feature}"),
+    ));
+
+    assert!(reasons.contains(&String::from(
+        "there's no axis named 'feature' in the matrix"
+    )));
+    assert!(reasons
+        .iter()
+        .any(|r| r.contains("needs a value for the axis 'features'")));
+}
+
+#[test]
+fn matrix_reference_without_matrix() {
+    let reasons = reasons(expand(
+        r#"cargo-test:
+  workers: [test-worker]
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  script: ["cargo +${matrix.toolchain} test"]
+"#,
+    ));
+
+    assert_eq!(reasons, vec!["the builder doesn't have a matrix"]);
+}
+
+const MASTER_YAML: &str = r#"master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist: [adam-mcdaniel]
+workers:
+  stable-worker:
+    master-ip: localhost
+    working-dir: 'stable-worker'
+  nightly-worker:
+    master-ip: localhost
+    working-dir: 'nightly-worker'
+builders:
+  cargo-test:
+    workers: ["${matrix.toolchain}-worker"]
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+    script:
+      - rustup run ${matrix.toolchain} cargo test --features ${matrix.features}
+    matrix:
+      toolchain: [stable, nightly]
+      features: [default, all]
+      exclude:
+        - toolchain: nightly
+          features: all
+schedulers:
+  ci-change:
+    builders: [cargo-test]
+    branch: ".*"
+    triggers: [".*"]
+    password: "ok to test"
+"#;
+
+fn master_file(test: &str, contents: &str) -> MasterFile {
+    let dir = TempDir::new(&format!("matrix-{}", test));
+    MasterFile::load(dir.write(&[("master.yaml", contents)])).unwrap()
+}
+
+#[test]
+fn matrix_in_master_file() {
+    let master = MasterConfig::try_from(master_file("master", MASTER_YAML)).unwrap();
+
+    let names: Vec<String> = master.get_builders().iter().map(|b| b.get_name()).collect();
+    let expanded = vec![
+        "cargo-test-stable-default",
+        "cargo-test-stable-all",
+        "cargo-test-nightly-default",
+    ];
+    assert_eq!(names, expanded);
+    assert_eq!(master.get_schedulers()[0].get_buildernames(), expanded);
+}
+
+#[test]
+fn explain_expands_matrix() {
+    let schedulers = master_file("explain", MASTER_YAML)
+        .to_expanded_schedulers()
+        .unwrap();
+    let explanation = explain(&schedulers, &Change::new("master", vec!["src/lib.rs"]));
+
+    assert_eq!(
+        explanation.get_buildernames(),
+        vec![
+            "cargo-test-stable-default",
+            "cargo-test-stable-all",
+            "cargo-test-nightly-default",
+        ]
+    );
+}
+
+#[test]
+fn render_keeps_matrix() {
+    let rendered = master_file("render", MASTER_YAML)
+        .render(Format::Yaml)
+        .unwrap();
+
+    assert!(rendered.contains("${matrix.toolchain}"));
+    let master = MasterConfig::try_from(master_file("rendered", &rendered)).unwrap();
+    assert_eq!(master.get_builders().len(), 3);
+}
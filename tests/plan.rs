@@ -0,0 +1,162 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{plan, FileStatus, MasterConfig, State};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+mod common;
+use common::TempDir;
+
+fn master(builder: &str, worker: &str) -> MasterConfig {
+    let yaml = Yaml::from(format!(
+        r#"
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist:
+    - adam-mcdaniel
+
+workers:
+  {worker}:
+    master-ip: localhost
+    working-dir: '{worker}'
+
+builders:
+  {builder}:
+    script:
+      - cargo test
+    workers:
+      - {worker}
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+
+schedulers:
+  any-change:
+    builders:
+      - {builder}
+    branch: ".*"
+    triggers:
+      - '.*'
+    password: "ok to test"
+"#,
+        builder = builder,
+        worker = worker
+    ));
+
+    let mut master = MasterConfig::try_from(yaml).unwrap();
+    // Keep the passwords the same between calls
+    let mut state = State::default();
+    state.set_password(worker, "password");
+    master.sync_passwords(&mut state);
+    master
+}
+
+/// Writes the generated files the same way the build subcommand does
+fn build(master: &MasterConfig, root: &Path) {
+    fs::create_dir_all(root.join("master")).unwrap();
+    fs::write(root.join("master/master.cfg"), format!("{}\n", master)).unwrap();
+    for worker in master.get_workers() {
+        fs::create_dir_all(root.join(worker.get_dir())).unwrap();
+        fs::write(
+            root.join(worker.get_dir()).join("buildbot.tac"),
+            format!("{}\n", worker),
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn new_project() {
+    let root = TempDir::new("plan-new");
+    let plan = plan(&master("cargo-test", "test-worker"), &root).unwrap();
+
+    assert_eq!(plan.files.len(), 2);
+    assert_eq!(plan.files[0].path, "master/master.cfg");
+    assert_eq!(plan.files[1].path, "test-worker/buildbot.tac");
+    assert!(plan.files.iter().all(|f| f.status == FileStatus::Added));
+    assert!(plan.files[0]
+        .diff
+        .starts_with("--- /dev/null\n+++ b/master/master.cfg\n"));
+
+    assert_eq!(plan.builders.added, vec!["cargo-test"]);
+    assert_eq!(plan.schedulers.added, vec!["any_change"]);
+    assert_eq!(plan.workers.added, vec!["test-worker"]);
+    assert!(plan
+        .to_string()
+        .ends_with("2 file(s) would be created, 0 would change, 0 unchanged"));
+
+    // Planning never writes anything
+    assert!(!root.join("master").exists());
+}
+
+#[test]
+fn unchanged_project() {
+    let root = TempDir::new("plan-unchanged");
+    let master = master("cargo-test", "test-worker");
+    build(&master, &root);
+
+    let plan = plan(&master, &root).unwrap();
+    assert!(plan.is_empty());
+    assert!(plan.builders.is_empty());
+    assert_eq!(
+        plan.to_string(),
+        "0 file(s) would be created, 0 would change, 2 unchanged"
+    );
+}
+
+#[test]
+fn changed_project() {
+    let root = TempDir::new("plan-changed");
+    build(&master("cargo-test", "test-worker"), &root);
+
+    let plan = plan(&master("cargo-build", "test-worker"), &root).unwrap();
+    assert_eq!(plan.files[0].status, FileStatus::Modified);
+    assert_eq!(plan.files[1].status, FileStatus::Unchanged);
+    assert!(plan.files[0]
+        .diff
+        .contains("\n-        name=\"cargo-test\",\n"));
+    assert!(plan.files[0]
+        .diff
+        .contains("\n+        name=\"cargo-build\",\n"));
+
+    assert_eq!(plan.builders.added, vec!["cargo-build"]);
+    assert_eq!(plan.builders.removed, vec!["cargo-test"]);
+    assert!(plan.workers.is_empty());
+
+    let output = plan.to_string();
+    assert!(output.contains("+ builder 'cargo-build'\n- builder 'cargo-test'\n"));
+}
+
+#[test]
+fn passwords_are_masked() {
+    let root = TempDir::new("plan-passwords");
+    build(&master("cargo-test", "test-worker"), &root);
+
+    let mut master = master("cargo-test", "test-worker");
+    let mut state = State::default();
+    state.set_password("test-worker", "a new password");
+    master.sync_passwords(&mut state);
+
+    let plan = plan(&master, &root).unwrap();
+    let output = plan.to_string();
+    assert!(!output.contains("password\""));
+    assert!(!output.contains("a new password"));
+    assert!(output.contains("worker passwords in 'master/master.cfg' would change\n"));
+    assert!(output.contains("worker passwords in 'test-worker/buildbot.tac' would change\n"));
+    assert_eq!(plan.files[1].status, FileStatus::Modified);
+
+    let plan = rusty_ci::plan(&master, TempDir::new("plan-new-passwords")).unwrap();
+    assert!(plan.files[1].diff.contains("\n+passwd = \"<password>\"\n"));
+    assert!(!plan.to_string().contains("would change\n"));
+}
@@ -0,0 +1,107 @@
+extern crate rusty_ci;
+use rusty_ci::helper::process::{is_buildbot, is_running, read_pid, stop_dir, Stopped, PID_FILE};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+mod common;
+use common::TempDir;
+
+/// Runs a command in the background and writes its pid to `dir`, like twistd does.
+/// The child is reaped in another thread, so it doesn't linger as a zombie once it's stopped.
+fn spawn(dir: &Path, command: &mut Command) -> u32 {
+    let mut child = command.spawn().unwrap();
+    let pid = child.id();
+    std::fs::write(dir.join(PID_FILE), format!("{}\n", pid)).unwrap();
+    thread::spawn(move || child.wait());
+    pid
+}
+
+#[test]
+fn no_pid_file() {
+    let dir = TempDir::new("process-none");
+    assert_eq!(read_pid(&dir), Ok(None));
+    assert_eq!(
+        stop_dir(&dir, Duration::from_secs(1)),
+        Ok(Stopped::NotRunning)
+    );
+}
+
+#[test]
+fn bad_pid_file() {
+    let dir = TempDir::new("process-bad");
+    std::fs::write(dir.join(PID_FILE), "not a pid").unwrap();
+    assert!(read_pid(&dir).is_err());
+
+    // Signalling pid 0 would signal our own process group
+    std::fs::write(dir.join(PID_FILE), "0\n").unwrap();
+    assert!(read_pid(&dir).is_err());
+}
+
+#[test]
+fn stale_pid_file() {
+    let dir = TempDir::new("process-stale");
+    let pid = spawn(&dir, &mut Command::new("true"));
+    while is_running(pid).unwrap() {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(
+        stop_dir(&dir, Duration::from_secs(1)),
+        Ok(Stopped::NotRunning)
+    );
+    assert!(!dir.join(PID_FILE).exists());
+}
+
+#[test]
+fn stop_gracefully() {
+    let dir = TempDir::new("process-graceful");
+    let pid = spawn(&dir, Command::new("sleep").arg0("twistd").arg("30"));
+    assert_eq!(read_pid(&dir), Ok(Some(pid)));
+    assert!(is_buildbot(pid));
+
+    assert_eq!(
+        stop_dir(&dir, Duration::from_secs(5)),
+        Ok(Stopped::Gracefully(pid))
+    );
+    assert_eq!(is_running(pid), Ok(false));
+    assert!(!dir.join(PID_FILE).exists());
+}
+
+#[test]
+fn kill_after_timeout() {
+    let dir = TempDir::new("process-kill");
+    // This shell ignores SIGTERM. The `:` at the end stops it from exec'ing `sleep`,
+    // so its command line still says twistd.
+    let pid = spawn(
+        &dir,
+        Command::new("sh").args(["-c", "trap '' TERM; sleep 2; :", "twistd"]),
+    );
+    thread::sleep(Duration::from_millis(100));
+
+    assert_eq!(
+        stop_dir(&dir, Duration::from_millis(300)),
+        Ok(Stopped::Killed(pid))
+    );
+    assert_eq!(is_running(pid), Ok(false));
+    assert!(!dir.join(PID_FILE).exists());
+}
+
+#[test]
+fn reused_pid() {
+    let dir = TempDir::new("process-reused");
+    // The pid file points at a process that isn't twistd, as if the pid was reused
+    let pid = spawn(&dir, Command::new("sleep").arg("30"));
+    assert!(!is_buildbot(pid));
+
+    assert_eq!(
+        stop_dir(&dir, Duration::from_secs(1)),
+        Ok(Stopped::NotRunning)
+    );
+    assert_eq!(is_running(pid), Ok(true));
+    assert!(!dir.join(PID_FILE).exists());
+
+    Command::new("kill").arg(pid.to_string()).status().unwrap();
+}
@@ -0,0 +1,176 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{
+    generate, plan, write_secrets, FileStatus, GeneratedProject, MailNotifier, MasterConfig,
+    INSTALL_SCRIPT, MASTER_CFG_PATH,
+};
+use std::convert::TryFrom;
+use std::fs;
+
+mod common;
+use common::TempDir;
+
+fn master() -> MasterConfig {
+    let yaml = Yaml::from(
+        r#"
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist:
+    - adam-mcdaniel
+
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'workers/test'
+
+builders:
+  cargo-test:
+    script:
+      - cargo test
+    workers:
+      - test-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+
+schedulers:
+  any-change:
+    builders:
+      - cargo-test
+    branch: ".*"
+    triggers:
+      - '.*'
+    password: "ok to test"
+"#,
+    );
+    MasterConfig::try_from(yaml).unwrap()
+}
+
+#[test]
+fn generate_project() {
+    let master = master();
+    let project = generate(&master);
+
+    assert_eq!(
+        project.get_paths(),
+        vec![
+            "Makefile",
+            "install.sh",
+            "master/master.cfg",
+            "workers/test/buildbot.tac"
+        ]
+    );
+    assert_eq!(
+        project.get_file(MASTER_CFG_PATH),
+        Some(format!("{}\n", master))
+    );
+    assert_eq!(
+        project.get_file("workers/test/buildbot.tac"),
+        Some(format!("{}\n", master.get_workers()[0]))
+    );
+    assert_eq!(
+        project.get_file("install.sh"),
+        Some(format!("{}\n", INSTALL_SCRIPT))
+    );
+    assert_eq!(GeneratedProject::from(&master), project);
+}
+
+#[test]
+fn write_project() {
+    let root = TempDir::new("project-write");
+
+    let mut project = generate(&master());
+    project.insert("extra/notes.txt", "hello\n");
+    assert!(project.remove("Makefile").is_some());
+    project.write_to(&root).unwrap();
+
+    for (path, contents) in project.get_files() {
+        assert_eq!(&fs::read_to_string(root.join(path)).unwrap(), contents);
+    }
+    assert!(!root.join("Makefile").exists());
+}
+
+fn mail_notifier() -> MailNotifier {
+    MailNotifier::try_from(Yaml::from(
+        r#"
+extra-recipients:
+  failure: []
+  success: []
+  all: []
+from-address: ci@example.com
+lookup: example.com
+smtp-relay-host: smtp.example.com
+smtp-port: 587
+smtp-password: "p@$$w0rd""#,
+    ))
+    .unwrap()
+}
+
+#[test]
+fn secrets_are_kept_out_of_master_cfg() {
+    let mut master = master();
+    master.set_mail_notifier(mail_notifier());
+    let project = generate(&master);
+
+    let master_cfg = project.get_file(MASTER_CFG_PATH).unwrap();
+    assert!(!master_cfg.contains("p@$$w0rd"));
+    assert!(
+        master_cfg.contains("secrets.SecretInAFile(dirname=os.path.join(basedir, \"secrets\"))")
+    );
+    assert!(master_cfg.contains("smtpPassword=util.Secret(\"smtp-password\")"));
+    assert_eq!(
+        project.get_file("master/secrets/smtp-password"),
+        Some(String::from("p@$$w0rd\n"))
+    );
+
+    let root = TempDir::new("project-secrets");
+    project.write_to(&root).unwrap();
+    let path = root.join("master/secrets/smtp-password");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "p@$$w0rd\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // Planning a build says that a secret would change, but not what it is
+    fs::write(&path, "old password\n").unwrap();
+    let plan = plan(&master, &root).unwrap();
+    let file = plan
+        .files
+        .iter()
+        .find(|f| f.path == "master/secrets/smtp-password")
+        .unwrap();
+    assert_eq!(file.status, FileStatus::Modified);
+    assert_eq!(
+        file.diff,
+        "secret 'master/secrets/smtp-password' would change\n"
+    );
+    assert!(!plan.to_string().contains("password\n"));
+}
+
+#[test]
+fn old_secrets_are_removed() {
+    let root = TempDir::new("project-old-secrets");
+    let path = root.join("master/secrets/smtp-password");
+
+    let mut with_mail = master();
+    with_mail.set_mail_notifier(mail_notifier());
+    write_secrets(&with_mail, &root).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "p@$$w0rd\n");
+
+    // Without the mail file, the master doesn't have any secrets
+    write_secrets(&master(), &root).unwrap();
+    assert!(!path.exists());
+}
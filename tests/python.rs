@@ -0,0 +1,106 @@
+extern crate rusty_ci;
+use rusty_ci::helper::python::identifier;
+use rusty_ci::{PyRegex, ToPython};
+
+use std::process::Command;
+
+const NASTY: &[&str] = &[
+    "",
+    "plain",
+    "it's",
+    "say \"hi\"",
+    "\"\"\"",
+    "back\\slash",
+    "ends with \\",
+    "line\nbreak",
+    "tab\there\r",
+    "bell\x07 and del\x7f",
+    "line\u{2028}separator\u{2029}",
+    "unicode ✓",
+    "'); import os; os.system('rm -rf /'); ('",
+];
+
+#[test]
+fn string_literals() {
+    assert_eq!("plain".to_python(), "\"plain\"");
+    assert_eq!("it's".to_python(), "\"it's\"");
+    assert_eq!("say \"hi\"".to_python(), "\"say \\\"hi\\\"\"");
+    assert_eq!("\"\"\"".to_python(), "\"\\\"\\\"\\\"\"");
+    assert_eq!("a\\b".to_python(), "\"a\\\\b\"");
+    assert_eq!("a\nb\r\t".to_python(), "\"a\\nb\\r\\t\"");
+    assert_eq!("\x07\x7f".to_python(), "\"\\x07\\x7f\"");
+    assert_eq!("\u{2028}".to_python(), "\"\\u2028\"");
+
+    // Nothing we emit may span more than one line
+    for s in NASTY {
+        assert!(!s.to_python().contains(|c: char| c.is_control()));
+    }
+}
+
+#[test]
+fn other_literals() {
+    assert_eq!(true.to_python(), "True");
+    assert_eq!(false.to_python(), "False");
+    assert_eq!(9989u16.to_python(), "9989");
+    assert_eq!(None::<String>.to_python(), "None");
+    assert_eq!(Some("x").to_python(), "\"x\"");
+    assert_eq!(vec!["a", "b\""].to_python(), "[\"a\", \"b\\\"\"]");
+    assert_eq!(Vec::<String>::new().to_python(), "[]");
+}
+
+#[test]
+fn regex_literals() {
+    assert_eq!(PyRegex(".*\\.py").to_python(), "r\".*\\.py\"");
+    assert_eq!(PyRegex("ok to test").to_python(), "r\"ok to test\"");
+    // Raw strings can't hold these, so they fall back on escaped strings
+    assert_eq!(PyRegex("say \"hi\"").to_python(), "\"say \\\"hi\\\"\"");
+    assert_eq!(PyRegex("a\\").to_python(), "\"a\\\\\"");
+    assert_eq!(PyRegex("a\\\\").to_python(), "r\"a\\\\\"");
+    assert_eq!(PyRegex("a\nb").to_python(), "\"a\\nb\"");
+}
+
+#[test]
+fn identifiers() {
+    assert_eq!(identifier("any-change"), "any_change");
+    assert_eq!(identifier("ci.change 2"), "ci_change_2");
+    assert_eq!(identifier("2fast"), "_2fast");
+    assert_eq!(identifier(""), "_");
+    assert_eq!(identifier("a\"); os.system(\""), "a____os_system__");
+}
+
+/// Asks Python to evaluate each literal and print back its UTF-8 bytes in hex,
+/// and checks that we get the original strings.
+/// This is skipped when python3 isn't installed.
+#[test]
+fn round_trip_through_python() {
+    let mut literals: Vec<String> = NASTY.iter().map(|s| s.to_python()).collect();
+    literals.extend(NASTY.iter().map(|s| PyRegex(s).to_python()));
+    let program = format!(
+        "for s in [{}]: print(s.encode('utf-8').hex())",
+        literals.join(", ")
+    );
+
+    let output = match Command::new("python3").arg("-c").arg(&program).output() {
+        Ok(output) => output,
+        Err(_) => return,
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let decoded: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let bytes = (0..line.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&line[i..i + 2], 16).unwrap())
+                .collect();
+            String::from_utf8(bytes).unwrap()
+        })
+        .collect();
+    let expected: Vec<String> = NASTY.iter().chain(NASTY).map(|s| s.to_string()).collect();
+    assert_eq!(decoded, expected);
+}
@@ -0,0 +1,210 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::helper::python_ast::{render, Call, Except, Expr, Stmt};
+use rusty_ci::{MasterConfig, Scheduler};
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+#[test]
+fn expressions() {
+    let call = Call::new("util.Property")
+        .arg(Expr::lit("branch"))
+        .kwarg("default", Expr::lit("master"));
+    assert_eq!(
+        Expr::from(call).to_string(),
+        "util.Property(\"branch\", default=\"master\")"
+    );
+
+    assert_eq!(
+        Expr::name("c").index(Expr::lit("workers")).to_string(),
+        "c[\"workers\"]"
+    );
+    assert_eq!(
+        Expr::from(Expr::name("c").method("append").arg("x")).to_string(),
+        "c.append(x)"
+    );
+    assert_eq!(
+        Expr::Dict(vec![(Expr::lit("pb"), Expr::lit(9989u16))]).to_string(),
+        "{\"pb\": 9989}"
+    );
+
+    // Operations are parenthesized when they're part of a bigger expression
+    let test = Expr::name("a")
+        .op("in", Expr::name("b"))
+        .op("and", !Expr::name("c"));
+    assert_eq!(test.to_string(), "(a in b) and (not c)");
+}
+
+#[test]
+fn long_lines_are_wrapped() {
+    let stmt = Stmt::assign(
+        "s",
+        Call::new("reporters.MailNotifier")
+            .kwarg("fromaddr", Expr::lit("your-email-here@gmail.com"))
+            .kwarg("extraRecipients", Expr::lit(vec!["all_tests@gmail.com"]))
+            .kwarg("mode", Expr::lit("all")),
+    );
+    assert_eq!(
+        stmt.to_string(),
+        r#"s = reporters.MailNotifier(
+    fromaddr="your-email-here@gmail.com",
+    extraRecipients=["all_tests@gmail.com"],
+    mode="all",
+)
+"#
+    );
+
+    // Short statements stay on one line
+    let stmt = Stmt::assign("s", Call::new("f").arg(Expr::lit(1u32)));
+    assert_eq!(stmt.to_string(), "s = f(1)\n");
+}
+
+#[test]
+fn blocks_are_indented() {
+    let stmts = vec![
+        Stmt::Def {
+            decorators: vec![Expr::name("util.renderer")],
+            name: String::from("f"),
+            params: vec![String::from("props")],
+            body: vec![
+                Stmt::comment("first line\nsecond line"),
+                Stmt::for_(
+                    "x",
+                    "props",
+                    vec![Stmt::if_(Expr::name("x"), vec![Stmt::ret(Expr::lit(true))])],
+                ),
+                Stmt::Try {
+                    body: vec![Stmt::expr(Call::new("g"))],
+                    handlers: vec![Except {
+                        exception: None,
+                        name: None,
+                        body: vec![],
+                    }],
+                },
+                Stmt::Return(None),
+            ],
+        },
+        Stmt::try_except(vec![], vec![Stmt::raw("raise")]),
+    ];
+
+    assert_eq!(
+        render(&stmts),
+        r#"@util.renderer
+def f(props):
+    # first line
+    # second line
+    for x in props:
+        if x:
+            return True
+    try:
+        g()
+    except:
+        pass
+    return
+try:
+    pass
+except Exception as e:
+    raise
+"#
+    );
+}
+
+#[test]
+fn sections_are_structures() {
+    let yaml = Yaml::from(
+        r#"xasm-test:
+  builders:
+    - xasm-test
+  depends: xasm-change
+"#,
+    )
+    .get_section("xasm-test")
+    .unwrap();
+    let scheduler = Scheduler::try_from(yaml).unwrap();
+
+    match &scheduler.statements()[0] {
+        Stmt::Assign(target, Expr::Call(call)) => {
+            assert_eq!(target, &Expr::name("xasm_test"));
+            assert_eq!(call.get_kwarg("upstream"), Some(&Expr::name("xasm_change")));
+            assert_eq!(
+                call.get_kwarg("builderNames"),
+                Some(&Expr::lit(vec!["xasm-test"]))
+            );
+        }
+        other => panic!("expected the Dependent scheduler, found {:?}", other),
+    }
+}
+
+/// Checks that a whole master config is valid Python.
+/// This is skipped when python3 isn't installed.
+#[test]
+fn master_config_compiles() {
+    let yaml = Yaml::from(
+        r#"
+master:
+  title: "Rusty \"CI\""
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist:
+    - adam-mcdaniel
+
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+
+builders:
+  cargo-test:
+    script:
+      - cargo test
+    workers:
+      - test-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+
+schedulers:
+  any-change:
+    builders:
+      - cargo-test
+    branch: ".*"
+    triggers:
+      - '.*\.rs'
+    password: "ok to test"
+  after-change:
+    builders:
+      - cargo-test
+    depends: any-change
+"#,
+    );
+    let master = MasterConfig::try_from(yaml).unwrap();
+
+    let mut sources = vec![master.to_string()];
+    sources.extend(master.get_workers().iter().map(|w| w.to_string()));
+    for source in sources {
+        let output = match Command::new("python3")
+            .arg("-c")
+            .arg("import sys; compile(sys.argv[1], 'master.cfg', 'exec')")
+            .arg(&source)
+            .output()
+        {
+            Ok(output) => output,
+            Err(_) => return,
+        };
+        assert!(
+            output.status.success(),
+            "{}\n{}",
+            source,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
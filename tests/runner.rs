@@ -0,0 +1,103 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{Builder, Runner, StepStatus};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+mod common;
+use common::TempDir;
+
+/// A fresh temporary directory for a test to run a builder in
+fn temp_dir(test: &str) -> TempDir {
+    let dir = TempDir::new(&format!("runner-{}", test));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    dir
+}
+
+fn builder(script: &str) -> Builder {
+    let yaml = Yaml::from(format!(
+        r#"local:
+  workers:
+    - worker
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  shell: true
+  script:
+{}
+"#,
+        script
+    ))
+    .get_section("local")
+    .unwrap();
+
+    Builder::try_from(yaml).unwrap()
+}
+
+#[test]
+fn workdir_follows_cd() {
+    let runner = Runner::in_checkout("/checkout");
+    assert_eq!(runner.workdir(&None), PathBuf::from("/checkout"));
+    assert_eq!(
+        runner.workdir(&Some(String::from("./build"))),
+        PathBuf::from("/checkout")
+    );
+    assert_eq!(
+        runner.workdir(&Some(String::from("./build/sub/dir"))),
+        PathBuf::from("/checkout/sub/dir")
+    );
+    assert_eq!(
+        runner.workdir(&Some(String::from("/tmp"))),
+        PathBuf::from("/tmp")
+    );
+}
+
+#[test]
+fn runs_steps_in_order() {
+    let dir = temp_dir("order");
+    let builder = builder(
+        r#"    - echo one > log
+    - cd sub
+    - echo two > ../log2
+    - pwd > where"#,
+    );
+
+    let report = Runner::in_checkout(&dir).run(&builder);
+    assert!(report.success());
+    // The clone step isn't run in an existing checkout
+    assert_eq!(report.steps.len(), 3);
+    assert_eq!(report.steps[0].name, "echo one > log");
+
+    assert!(dir.join("log").exists());
+    assert!(dir.join("log2").exists());
+    let pwd = std::fs::read_to_string(dir.join("sub").join("where")).unwrap();
+    assert_eq!(
+        PathBuf::from(pwd.trim()),
+        dir.join("sub").canonicalize().unwrap()
+    );
+}
+
+#[test]
+fn stops_at_first_failure() {
+    let dir = temp_dir("failure");
+    let builder = builder(
+        r#"    - "true"
+    - exit 3
+    - touch never"#,
+    );
+
+    let report = Runner::in_checkout(&dir).run(&builder);
+    assert!(!report.success());
+    assert_eq!(report.steps[0].status, StepStatus::Passed);
+    assert_eq!(
+        report.steps[1].status,
+        StepStatus::Failed(String::from("exited with status 3"))
+    );
+    assert_eq!(report.steps[2].status, StepStatus::Skipped);
+    assert!(!dir.join("never").exists());
+
+    let summary = report.to_string();
+    assert!(summary.starts_with("Summary of builder 'local':\n"));
+    assert!(summary.contains("  skipped           touch never\n"));
+    assert!(summary.contains("`exit 3` failed: exited with status 3\n"));
+}
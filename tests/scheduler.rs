@@ -3,6 +3,7 @@ use rusty_yaml::Yaml;
 
 extern crate rusty_ci;
 use rusty_ci::Scheduler;
+use std::convert::TryFrom;
 
 #[test]
 fn scheduler_from_yaml() {
@@ -24,11 +25,41 @@ fn scheduler_from_yaml() {
     .get_section("xasm-change")
     .unwrap();
 
-    let scheduler = Scheduler::from(yaml);
+    let scheduler = Scheduler::try_from(yaml).unwrap();
     let output = scheduler.to_string();
 
     assert_eq!(
         output.trim(),
-        "@util.renderer\ndef xasm_change_triggers(props):\n    builders = [\"xasm-build\", \"xasm-test\"]\n\n    triggers = [\".*\\\\.py\", \".*\\\\.cpp\", \".*Makefile\", \".*CMakeLists.txt\"]\n\n    if not is_whitelisted(props, \"ok to test\"):\n        print(\'NOT WHITELISTED!!!\')\n        return []\n\n    for f in props.files:\n        for regex in triggers:\n            print(\"FILE:   \", f)\n            print(\"TRIGGER:\", regex)\n            if re.fullmatch(regex, str(f)):\n                return builders\n\n    return []\n\n\nxasm_change = schedulers.AnyBranchScheduler(name=\"xasm_change\",\n    change_filter=util.ChangeFilter(branch_re=\".*\"),\n    builderNames=xasm_change_triggers)\n\nc[\'schedulers\'].append(xasm_change)\n\nc[\'schedulers\'].append(schedulers.ForceScheduler(name=\"force_xasm_change\",\n    builderNames=[\"xasm-build\", \"xasm-test\"]))"
+        r#"@util.renderer
+def xasm_change_triggers(props):
+    builders = ["xasm-build", "xasm-test"]
+    triggers = [r".*\.py", r".*\.cpp", r".*Makefile", r".*CMakeLists.txt"]
+
+    if not is_whitelisted(props, r"ok to test"):
+        print("NOT WHITELISTED!!!")
+        return []
+
+    for f in props.files:
+        for regex in triggers:
+            print("FILE:   ", f)
+            print("TRIGGER:", regex)
+            if re.fullmatch(regex, str(f)):
+                return builders
+
+    return []
+
+
+xasm_change = schedulers.AnyBranchScheduler(
+    name="xasm_change",
+    change_filter=util.ChangeFilter(branch_re=r".*"),
+    builderNames=xasm_change_triggers,
+)
+c["schedulers"].append(xasm_change)
+c["schedulers"].append(
+    schedulers.ForceScheduler(
+        name="force_xasm_change",
+        builderNames=["xasm-build", "xasm-test"],
+    ),
+)"#
     );
 }
@@ -0,0 +1,133 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{MasterConfig, State};
+use std::convert::TryFrom;
+
+mod common;
+use common::TempDir;
+
+fn master() -> MasterConfig {
+    let yaml = Yaml::from(
+        r#"
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist:
+    - adam-mcdaniel
+
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+  new-worker:
+    master-ip: localhost
+    working-dir: 'new-worker'
+
+builders:
+  cargo-test:
+    script:
+      - cargo test
+    workers:
+      - test-worker
+      - new-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+
+schedulers:
+  any-change:
+    builders:
+      - cargo-test
+    branch: ".*"
+    triggers:
+      - '.*'
+    password: "ok to test"
+"#,
+    );
+    MasterConfig::try_from(yaml).unwrap()
+}
+
+#[test]
+fn state_round_trip() {
+    let mut state = State::default();
+    state.set_password("test-worker", "abc123");
+    state.set_password("worker with spaces", "def456");
+
+    let parsed: State = state.to_string().parse().unwrap();
+    assert_eq!(parsed, state);
+    assert_eq!(
+        parsed.get_password("worker with spaces"),
+        Some(String::from("def456"))
+    );
+
+    assert!("not a password".parse::<State>().is_err());
+}
+
+#[test]
+fn state_save_and_load() {
+    let dir = TempDir::new("state-save");
+    let path = dir.join(".rusty-ci").join("state");
+
+    // Nothing has been built yet
+    assert_eq!(State::load(&path), Ok(State::default()));
+
+    let mut state = State::default();
+    state.set_password("test-worker", "abc123");
+    state.save(&path).unwrap();
+    assert_eq!(State::load(&path), Ok(state));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn passwords_persist_between_builds() {
+    let mut state = State::default();
+    state.set_password("test-worker", "saved");
+
+    let mut first = master();
+    first.sync_passwords(&mut state);
+    let workers = first.get_workers();
+    assert_eq!(workers[0].get_password(), "saved");
+    // The new worker's password was saved for the next build
+    assert_eq!(
+        state.get_password("new-worker"),
+        Some(workers[1].get_password())
+    );
+
+    let mut second = master();
+    second.sync_passwords(&mut state);
+    assert_eq!(
+        second.get_workers()[1].get_password(),
+        workers[1].get_password()
+    );
+    assert!(second
+        .to_string()
+        .contains("worker.Worker(\"test-worker\", \"saved\")"));
+}
+
+#[test]
+fn rotate_password() {
+    let mut state = State::default();
+    state.set_password("test-worker", "saved");
+
+    let rotated = state.rotate_password("test-worker").unwrap();
+    assert_ne!(rotated, "saved");
+    assert_eq!(rotated.len(), 30);
+    assert_eq!(state.get_password("test-worker"), Some(rotated));
+
+    assert_eq!(state.rotate_password("missing-worker"), None);
+}
@@ -1,5 +1,5 @@
 extern crate rusty_ci;
-use rusty_ci::Step;
+use rusty_ci::{CloneOptions, Step};
 
 #[test]
 fn command_step() {
@@ -13,13 +13,48 @@ fn command_step() {
     );
 }
 
+#[test]
+fn quoted_command_step() {
+    assert_eq!(
+        Step::command("echo 'hello world'", None).to_string(),
+        format!(
+            "steps.ShellCommand(command={:?})",
+            vec!["echo", "hello world"]
+        )
+    );
+}
+
+#[test]
+fn shell_command_step() {
+    assert_eq!(
+        Step::shell_command("cargo test 2>&1 | tee log", Some("build")).to_string(),
+        format!(
+            "steps.ShellCommand(command={:?}, workdir=\"{}\")",
+            vec!["/bin/sh", "-c", "cargo test 2>&1 | tee log"],
+            "build"
+        )
+    );
+}
+
 #[test]
 fn git_clone_step() {
     let url = "https://github.com/adam-mcdaniel/rusty-ci";
     assert_eq!(
-        Step::git_clone(url).to_string(),
+        Step::git_clone(url, CloneOptions::default()).to_string(),
+        format!(
+            "steps.Git(repourl=\"{}\", mode=\"full\", branch=util.Property(\"branch\", default=\"master\"), method=\"clobber\", shallow=False, submodules=True)",
+            url
+        )
+    );
+}
+
+#[test]
+fn github_clone_step() {
+    let url = "https://github.com/adam-mcdaniel/rusty-ci";
+    assert_eq!(
+        Step::github_clone(url, CloneOptions::default()).to_string(),
         format!(
-            "steps.Git(repourl=\"{}\", mode=\"full\", branch=\"master\", method=\"clobber\", shallow=False, submodules=True)",
+            "steps.GitHub(repourl=\"{}\", mode=\"full\", branch=util.Property(\"branch\", default=\"master\"), method=\"clobber\", shallow=False, submodules=True)",
             url
         )
     );
@@ -29,9 +64,27 @@ fn git_clone_step() {
 fn git_lab_clone_step() {
     let url = "https://gitlab.com/adam-mcdaniel/rusty-ci";
     assert_eq!(
-        Step::gitlab_clone(url).to_string(),
+        Step::gitlab_clone(url, CloneOptions::default()).to_string(),
+        format!(
+            "steps.GitLab(repourl=\"{}\", mode=\"full\", branch=util.Property(\"branch\", default=\"master\"), method=\"clobber\", shallow=False, submodules=True)",
+            url
+        )
+    );
+}
+
+#[test]
+fn git_clone_step_with_options() {
+    let url = "https://github.com/adam-mcdaniel/rusty-ci";
+    let options = CloneOptions {
+        branch: Some(String::from("release")),
+        depth: Some(5),
+        submodules: false,
+        method: String::from("fresh"),
+    };
+    assert_eq!(
+        Step::git_clone(url, options).to_string(),
         format!(
-            "steps.GitLab(repourl=\"{}\", mode=\"full\", branch=\"master\", method=\"clobber\", shallow=False, submodules=True)",
+            "steps.Git(repourl=\"{}\", mode=\"full\", branch=\"release\", alwaysUseLatest=True, method=\"fresh\", shallow=5, submodules=False)",
             url
         )
     );
@@ -1,5 +1,5 @@
 extern crate rusty_ci;
-use rusty_ci::unquote;
+use rusty_ci::{shell_words, unquote};
 
 #[test]
 fn unquote_string() {
@@ -17,3 +17,20 @@ fn unquote_string() {
     assert_eq!(unquote("hey jude\""), String::from("hey jude\""));
     assert_eq!(unquote("''hey jude''"), String::from("'hey jude'"));
 }
+
+#[test]
+fn split_shell_words() {
+    assert_eq!(
+        shell_words("echo   'hello world'  \"a \\\"b\\\" c\" d\\ e ''").unwrap(),
+        vec!["echo", "hello world", "a \"b\" c", "d e", ""]
+    );
+
+    assert_eq!(
+        shell_words("cargo test -- --test-threads=1").unwrap(),
+        vec!["cargo", "test", "--", "--test-threads=1"]
+    );
+
+    assert!(shell_words("echo 'hey jude").is_err());
+    assert!(shell_words("echo \"hey jude").is_err());
+    assert!(shell_words("echo hey jude\\").is_err());
+}
@@ -0,0 +1,147 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::{
+    ConfigError, MasterConfig, MasterFile, TokenSource, POLL_TOKEN_SECRET, STATUS_TOKEN_SECRET,
+};
+use std::convert::TryFrom;
+
+mod common;
+use common::TempDir;
+
+const MASTER_YAML: &str = r#"master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+merge-request-handler:
+  version-control-system: github
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist: [adam-mcdaniel]
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+builders:
+  cargo-test:
+    workers: [test-worker]
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+    script: [cargo test]
+schedulers:
+  ci-change:
+    builders: [cargo-test]
+    branch: ".*"
+    triggers: [".*"]
+    password: "ok to test"
+"#;
+
+/// A master file whose merge request handler has the token sections in `tokens`,
+/// and the directory it's in, which has to outlive it for the tokens to be read
+fn master_file(test: &str, tokens: &str, files: &[(&str, &str)]) -> (TempDir, MasterFile) {
+    let master = MASTER_YAML.replace(
+        "  whitelist: [adam-mcdaniel]\n",
+        &format!("  whitelist: [adam-mcdaniel]\n{}", tokens),
+    );
+    let mut files = files.to_vec();
+    files.insert(0, ("master.yaml", master.as_str()));
+    let dir = TempDir::new(&format!("tokens-{}", test));
+    let file = MasterFile::load(dir.write(&files)).unwrap();
+    (dir, file)
+}
+
+fn config_errors(file: MasterFile) -> Vec<ConfigError> {
+    match MasterConfig::try_from(file) {
+        Ok(_) => panic!("the tokens can't be read"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn separate_poll_and_status_tokens() {
+    std::env::set_var("RUSTY_CI_TEST_POLL_TOKEN", "poll-token\n");
+    let (_dir, file) = master_file(
+        "separate",
+        "  token-env: RUSTY_CI_TEST_POLL_TOKEN\n  status-token-file: tokens/status\n",
+        &[("tokens/status", "status-token\n")],
+    );
+    let master = MasterConfig::try_from(file).unwrap();
+
+    let secrets = master.get_secrets();
+    assert_eq!(secrets[POLL_TOKEN_SECRET], "poll-token");
+    assert_eq!(secrets[STATUS_TOKEN_SECRET], "status-token");
+    let master_cfg = master.to_string();
+    assert!(master_cfg.contains("token=util.Secret(\"vcs-poll-token\")"));
+    assert!(master_cfg.contains("token=util.Secret(\"vcs-status-token\")"));
+}
+
+#[test]
+fn auth_token_is_read_next_to_the_master_file() {
+    // The current directory of the tests has no `auth.token` in it
+    let (_dir, file) = master_file("default", "", &[("auth.token", "token\n")]);
+    let master = MasterConfig::try_from(file).unwrap();
+    assert_eq!(master.get_secrets()[POLL_TOKEN_SECRET], "token");
+    assert_eq!(master.get_secrets()[STATUS_TOKEN_SECRET], "token");
+
+    // A master file read without the serde model doesn't know where it is, so it's told
+    let dir = TempDir::new("tokens-yaml");
+    let path = dir.write(&[
+        ("master.yaml", MASTER_YAML),
+        ("auth.token", "yaml-token\n"),
+        ("status.token", "status-token\n"),
+    ]);
+    let yaml = MASTER_YAML.replace(
+        "  whitelist: [adam-mcdaniel]\n",
+        "  whitelist: [adam-mcdaniel]\n  status-token-file: status.token\n",
+    );
+    let master = MasterConfig::from_yaml(Yaml::from(yaml), path.parent().unwrap()).unwrap();
+    assert_eq!(master.get_secrets()[POLL_TOKEN_SECRET], "yaml-token");
+    assert_eq!(master.get_secrets()[STATUS_TOKEN_SECRET], "status-token");
+
+    // So is a merge request handler section on its own
+    let file = MasterFile::load(&path).unwrap();
+    let section = file.merge_request_handler.clone().unwrap();
+    let handler = section.to_handler(path.parent().unwrap()).unwrap();
+    assert_eq!(handler.get_secrets()[STATUS_TOKEN_SECRET], "yaml-token");
+}
+
+#[test]
+fn missing_tokens_are_errors() {
+    let (_dir, file) = master_file("missing", "  token-env: RUSTY_CI_TEST_UNSET_TOKEN\n", &[]);
+    assert_eq!(
+        config_errors(file),
+        vec![ConfigError::MissingAuthToken {
+            path: TokenSource::Env(String::from("RUSTY_CI_TEST_UNSET_TOKEN")).to_string(),
+            reason: String::from("the environment variable isn't set"),
+        }]
+    );
+
+    let (_dir, file) = master_file(
+        "empty",
+        "  poll-token-file: empty.token\n",
+        &[("empty.token", "\n")],
+    );
+    match &config_errors(file)[..] {
+        [ConfigError::MissingAuthToken { path, reason }] => {
+            assert!(path.ends_with("empty.token"));
+            assert_eq!(
+                reason,
+                "you didn't write your VCS's authentication token to it"
+            );
+        }
+        errors => panic!("unexpected errors {:?}", errors),
+    }
+
+    let (_dir, file) = master_file(
+        "both",
+        "  status-token-file: status.token\n  status-token-env: STATUS_TOKEN\n",
+        &[],
+    );
+    match &config_errors(file)[..] {
+        [ConfigError::BadValue { section, .. }] => assert_eq!(section, "status-token-env"),
+        errors => panic!("unexpected errors {:?}", errors),
+    }
+}
@@ -0,0 +1,170 @@
+extern crate rusty_yaml;
+use rusty_yaml::Yaml;
+
+extern crate rusty_ci;
+use rusty_ci::helper::python_ast::{Expr, Stmt};
+use rusty_ci::{ConfigError, MasterConfig, Worker, WorkerOptions, DEFAULT_WORKER_PORT};
+use std::convert::TryFrom;
+
+/// Finds the value assigned to `name` in the worker's `buildbot.tac`
+fn tac_value(worker: &Worker, name: &str) -> String {
+    worker
+        .statements()
+        .iter()
+        .find_map(|stmt| match stmt {
+            Stmt::Assign(target, value) if *target == Expr::name(name) => Some(value.to_string()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn worker_defaults() {
+    let yaml = Yaml::from(
+        r#"test-worker:
+  master-ip: localhost
+  working-dir: 'test-worker'
+"#,
+    )
+    .get_section("test-worker")
+    .unwrap();
+
+    let worker = Worker::try_from(yaml).unwrap();
+    assert_eq!(worker.get_options(), WorkerOptions::default());
+    assert_eq!(worker.get_master_port(), DEFAULT_WORKER_PORT);
+    assert_eq!(tac_value(&worker, "port"), "9989");
+    assert_eq!(tac_value(&worker, "keepalive"), "600");
+    assert_eq!(tac_value(&worker, "maxdelay"), "300");
+    assert_eq!(tac_value(&worker, "umask"), "None");
+    assert_eq!(tac_value(&worker, "allow_shutdown"), "None");
+}
+
+#[test]
+fn worker_with_options() {
+    let yaml = Yaml::from(
+        r#"test-worker:
+  master-ip: localhost
+  working-dir: 'test-worker'
+  keepalive: 60
+  max-delay: 30
+  umask: 022
+  numcpus: 4
+  max-retries: 10
+  allow-shutdown: signal
+"#,
+    )
+    .get_section("test-worker")
+    .unwrap();
+
+    let worker = Worker::try_from(yaml).unwrap();
+    assert_eq!(
+        worker.get_options(),
+        WorkerOptions {
+            keepalive: 60,
+            max_delay: 30,
+            umask: Some(0o22),
+            numcpus: Some(4),
+            max_retries: Some(10),
+            allow_shutdown: Some(String::from("signal")),
+        }
+    );
+    assert_eq!(tac_value(&worker, "keepalive"), "60");
+    assert_eq!(tac_value(&worker, "maxdelay"), "30");
+    assert_eq!(tac_value(&worker, "umask"), "0o022");
+    assert_eq!(tac_value(&worker, "numcpus"), "4");
+    assert_eq!(tac_value(&worker, "maxretries"), "10");
+    assert_eq!(tac_value(&worker, "allow_shutdown"), "\"signal\"");
+}
+
+#[test]
+fn worker_bad_options() {
+    let yaml = Yaml::from(
+        r#"test-worker:
+  master-ip: localhost
+  working-dir: 'test-worker'
+  keepalive: often
+  umask: 999
+  allow-shutdown: always
+"#,
+    )
+    .get_section("test-worker")
+    .unwrap();
+
+    let context = String::from("worker 'test-worker'");
+    assert_eq!(
+        Worker::try_from(yaml).err(),
+        Some(vec![
+            ConfigError::BadValue {
+                context: context.clone(),
+                section: String::from("keepalive"),
+                value: String::from("often"),
+                reason: String::from("expected a whole number"),
+            },
+            ConfigError::BadValue {
+                context: context.clone(),
+                section: String::from("umask"),
+                value: String::from("999"),
+                reason: String::from("the umask must be an octal number like 022"),
+            },
+            ConfigError::BadValue {
+                context,
+                section: String::from("allow-shutdown"),
+                value: String::from("always"),
+                reason: String::from("the shutdown method must be one of signal, file"),
+            },
+        ])
+    );
+}
+
+#[test]
+fn master_worker_port() {
+    let yaml = Yaml::from(
+        r#"
+master:
+  title: "Rusty-CI"
+  title-url: "https://github.com/adam-mcdaniel/rusty-ci"
+  webserver-ip: localhost
+  webserver-port: 8010
+  repo: "https://github.com/adam-mcdaniel/rusty-ci"
+  poll-interval: 120
+  worker-port: 9000
+
+merge-request-handler:
+  version-control-system: none
+  owner: adam-mcdaniel
+  repo-name: rusty-ci
+  whitelist:
+    - adam-mcdaniel
+
+workers:
+  test-worker:
+    master-ip: localhost
+    working-dir: 'test-worker'
+
+builders:
+  cargo-test:
+    script:
+      - cargo test
+    workers:
+      - test-worker
+    repo: "https://github.com/adam-mcdaniel/rusty-ci"
+
+schedulers:
+  any-change:
+    builders:
+      - cargo-test
+    branch: ".*"
+    triggers:
+      - '.*'
+    password: "ok to test"
+"#,
+    );
+    let master = MasterConfig::try_from(yaml).unwrap();
+
+    // The master listens on the port, and the workers connect to it
+    assert!(master
+        .to_string()
+        .contains("c[\"protocols\"] = {\"pb\": {\"port\": 9000}}"));
+    assert_eq!(master.get_workers()[0].get_master_port(), 9000);
+    assert_eq!(tac_value(&master.get_workers()[0], "port"), "9000");
+}